@@ -47,6 +47,7 @@ use crate::network::ConnectionStatus;
 use crate::world_view::{self, WorldView, serialize};
 use crate::config;
 use crate::init;
+use crate::node::NodeContext;
 use crate::network;
 use crate::print;
 
@@ -165,6 +166,9 @@ pub async fn start_backup_server(
 /// Connects to the main process and listens for serialized `BackupPayload`
 /// updates over TCP. Displays the current worldview and network status in the terminal.
 ///
+/// # Parameters
+/// - `ctx`: The node the backup takes over for.
+///
 /// # Behavior
 /// - Continuously tries to connect to the main process until success or timeout.
 /// - Deserializes incoming data and prints system state via `print::worldview`.
@@ -178,10 +182,12 @@ pub async fn start_backup_server(
 /// # Notes
 /// In the current solution, this failover logic is disabled using a high timeout.
 /// The function is now used solely as a live GUI for displaying the system state.
-pub async fn run_as_backup() -> Option<world_view::ElevatorContainer> 
+pub async fn run_as_backup(
+    ctx: &NodeContext
+) -> Option<world_view::ElevatorContainer> 
 {
     println!("Starting backup-client...");
-    let mut current_wv = init::initialize_worldview(None).await;
+    let mut current_wv = init::initialize_worldview(ctx, None).await;
     let mut retries = 0;
     
     loop 
@@ -242,7 +248,7 @@ pub async fn run_as_backup() -> Option<world_view::ElevatorContainer>
                 {
                     print::err(format!("Master failed, promoting backup to master!"));
                     // Her kan failover-logikken setjast i gang, t.d. køyre master-logikken.
-                    match world_view::extract_self_elevator_container(&current_wv).to_owned() 
+                    match world_view::extract_self_elevator_container(&current_wv, ctx.id()).to_owned() 
                     {
                         Some(container) => return Some(container.to_owned()),
                         None => 
@@ -319,8 +325,8 @@ fn create_reusable_listener(
 
 /// Launches a new terminal window and starts the program in backup mode.
 ///
-/// Uses the current binary path and appends the `backup` argument, causing
-/// the program to run as a backup client.
+/// Uses the current binary path and the options the program was started with, and appends the `backup` argument,
+/// causing the program to run as a backup client. The backup keeps the options, e.g. the network key, when it takes over.
 ///
 /// This function checks the `BACKUP_STARTED` flag to ensure only one
 /// backup process is started.
@@ -335,6 +341,7 @@ fn start_backup_terminal() {
             .arg("--geometry=400x24")
             .arg("--")
             .arg(current_exe.to_str().unwrap())
+            .args(env::args().skip(1).filter(|arg| arg.to_lowercase() != "backup"))
             .arg("backup")
             .spawn()
             .expect("Feil ved å starte backupterminalen");
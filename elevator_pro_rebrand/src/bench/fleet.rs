@@ -0,0 +1,132 @@
+//! A simulated car, driven by the elevator FSM on a virtual clock
+
+use crate::config;
+use crate::elevator_logic::fsm::{self, FsmAction, FsmEvent, FsmState};
+use crate::elevator_logic::timer::ElevatorTimers;
+use crate::node::NodeConfig;
+use crate::world_view::{Dirn, ElevatorContainer};
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+
+/// A car in the simulated fleet
+///
+/// The car moves [config::SIM_TRAVEL_TIME] per floor in the direction of the motor, like the
+/// [in-process simulator](crate::elevio::simulator), but is stepped explicitly instead of following the wall clock.
+pub(super) struct SimulatedCar
+{
+    /// Always `Some` between calls, the FSM is taken out while it is stepped
+    fsm: Option<FsmState>,
+
+    /// The position of the car, in floors
+    position: f64,
+
+    /// The last motor direction set by the FSM
+    motor: Dirn,
+
+    /// The distance the car has travelled, in floors
+    pub distance: f64,
+}
+
+impl SimulatedCar
+{
+    /// Creates a car standing at `floor`, with the number of floors, timers and serving policy from `node_config`
+    pub fn new(
+        elevator_id: u8,
+        floor: u8,
+        node_config: &NodeConfig,
+        now: Instant
+    ) -> Self
+    {
+        let elevator = ElevatorContainer 
+        { 
+            elevator_id, 
+            last_floor_sensor: floor, 
+            ..ElevatorContainer::new(node_config.num_floors, node_config.served_floors.as_deref()) 
+        };
+
+        let timers = ElevatorTimers::from_config(&node_config.timers);
+        let (state, actions) = fsm::on_init(FsmState::new(elevator, timers, node_config.serving_policy), now);
+
+        let mut car = SimulatedCar { fsm: Some(state), position: floor as f64, motor: Dirn::Stop, distance: 0.0 };
+        car.execute(actions);
+        car
+    }
+
+    /// The elevator container of the car's FSM
+    pub fn elevator(&self) -> &ElevatorContainer
+    {
+        &self.fsm.as_ref().unwrap().elevator
+    }
+
+    /// Steps the car's FSM with one event
+    pub fn handle(
+        &mut self,
+        event: FsmEvent,
+        now: Instant
+    )
+    {
+        let (state, actions) = fsm::step(self.fsm.take().unwrap(), event, now);
+        self.fsm = Some(state);
+        self.execute(actions);
+    }
+
+    /// Moves the car `dt` in the direction of the motor, and steps the FSM
+    ///
+    /// A [FsmEvent::FloorArrived] is stepped when the car reaches a floor, followed by a [FsmEvent::Tick].
+    pub fn advance(
+        &mut self,
+        dt: Duration,
+        now: Instant
+    )
+    {
+        let step = dt.as_secs_f64() / config::SIM_TRAVEL_TIME.as_secs_f64();
+        let top = self.elevator().num_floors.saturating_sub(1) as f64;
+        let old = self.position;
+
+        let arrived = match self.motor
+        {
+            Dirn::Up =>
+            {
+                self.position = (old + step).min(top);
+                let next = old.floor() + 1.0;
+                (self.position >= next).then_some(next)
+            }
+            Dirn::Down =>
+            {
+                self.position = (old - step).max(0.0);
+                let next = old.ceil() - 1.0;
+                (self.position <= next).then_some(next)
+            }
+            Dirn::Stop => None,
+        };
+        self.distance += (self.position - old).abs();
+
+        if let Some(floor) = arrived
+        {
+            self.handle(FsmEvent::FloorArrived(floor as u8), now);
+        }
+        self.handle(FsmEvent::Tick, now);
+    }
+
+    /// Executes the FSM's actions on the car. Only the motor matters for the simulation.
+    fn execute(
+        &mut self,
+        actions: Vec<FsmAction>
+    )
+    {
+        for action in actions
+        {
+            if let FsmAction::Motor(dirn) = action
+            {
+                // The FSM stops the car at a floor, so it is placed exactly on it
+                if dirn == Dirn::Stop
+                {
+                    self.position = self.position.round();
+                }
+                self.motor = dirn;
+            }
+        }
+    }
+}
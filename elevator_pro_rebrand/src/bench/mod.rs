@@ -0,0 +1,343 @@
+//! ## Dispatch benchmark
+//!
+//! Runs synthetic [traffic] against the manager and a simulated fleet, to compare the hall assignment strategies
+//! in [crate::manager::assigner] on the same passengers.
+//!
+//! The fleet is made of the real [elevator FSM](crate::elevator_logic::fsm) with the [serving policy](crate::elevator_logic::request)
+//! in [NodeConfig::serving_policy], one per car. The hall assignment is done by [manager::get_elev_tasks], so stability,
+//! starvation and task timeouts are part of the result, as they are on the network.
+//! Everything runs on a virtual clock, so an hour of traffic takes seconds.
+//!
+//! The benchmark is run with the `dispatch_bench` binary:
+//! `cargo run --release --bin dispatch_bench -- traffic::up_peak rate::12 elevators::3`
+//!
+//! # Metrics
+//! - **Wait time**: From the passenger presses the hall button until a car opens the door for them.
+//! - **Journey time**: From the passenger presses the hall button until they get off at their floor.
+//! - **Travel distance**: The distance driven by all the cars together, in floors.
+//!
+//! Passengers board the car which served their hall button, and press the cab button for their floor right away.
+//! The cars are not limited in capacity.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use elevatorpro::bench::{self, BenchConfig};
+//! use elevatorpro::bench::traffic::TrafficProfile;
+//! use elevatorpro::manager::assigner::HallAssignerKind;
+//!
+//! let config = BenchConfig
+//! {
+//!     traffic: TrafficProfile::InterFloor,
+//!     duration: Duration::from_secs(300),
+//!     ..BenchConfig::default()
+//! };
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! let report = runtime.block_on(bench::run(&config, HallAssignerKind::NearestCar));
+//!
+//! assert!(report.passengers > 0);
+//! assert_eq!(report.served, report.passengers);
+//! assert!(report.wait.p95 >= report.wait.average);
+//! assert!(report.journey.average > report.wait.average);
+//! assert!(report.distance > 0.0);
+//! ```
+
+pub mod traffic;
+mod fleet;
+
+use crate::config;
+use crate::elevator_logic::fsm::FsmEvent;
+use crate::elevio::{CallButton, CallType};
+use crate::manager::{self, AssignmentInput};
+use crate::manager::assigner::HallAssignerKind;
+use crate::manager::task_timeout::TaskTracker;
+use crate::manager::trace::TraceWriter;
+use crate::node::NodeConfig;
+use crate::world_view::{self, ElevatorBehaviour, WorldView};
+
+use fleet::SimulatedCar;
+use traffic::{Passenger, TrafficProfile};
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+
+/// The time step of the simulation
+pub const TIME_STEP: Duration = Duration::from_millis(50);
+
+/// How long the simulation keeps running after the last passenger has arrived, for the cars to empty
+pub const DRAIN_TIME: Duration = Duration::from_secs(600);
+
+/// The traffic and fleet of a benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchConfig
+{
+    /// Where the passengers come from and go
+    pub traffic: TrafficProfile,
+
+    /// The average number of passengers per minute
+    pub rate_per_min: f64,
+
+    /// How long passengers keep arriving
+    pub duration: Duration,
+
+    /// The number of cars. They get IDs from 1, and all start at the ground floor.
+    pub num_elevators: u8,
+
+    /// Seed of the traffic
+    pub seed: u64,
+}
+
+impl Default for BenchConfig
+{
+    fn default() -> Self
+    {
+        BenchConfig
+        {
+            traffic: TrafficProfile::InterFloor,
+            rate_per_min: 10.0,
+            duration: Duration::from_secs(1800),
+            num_elevators: 3,
+            seed: 1,
+        }
+    }
+}
+
+/// The average and 95th percentile of a set of durations
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stats
+{
+    /// The average
+    pub average: Duration,
+
+    /// The 95th percentile, the smallest duration which at least 95 % of the set are at or below
+    pub p95: Duration,
+}
+
+impl Stats
+{
+    /// Computes the stats of `durations`. Zero for an empty set.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use elevatorpro::bench::Stats;
+    ///
+    /// let durations: Vec<Duration> = (1..=100).map(Duration::from_secs).collect();
+    /// let stats = Stats::from_durations(&durations);
+    /// assert_eq!(stats.average, Duration::from_millis(50_500));
+    /// assert_eq!(stats.p95, Duration::from_secs(95));
+    /// ```
+    pub fn from_durations(
+        durations: &[Duration]
+    ) -> Self
+    {
+        if durations.is_empty() {return Stats::default()}
+
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        Stats
+        {
+            average: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p95: sorted[rank.saturating_sub(1)],
+        }
+    }
+}
+
+/// The result of a benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchReport
+{
+    /// The strategy which assigned the hall requests
+    pub assigner: HallAssignerKind,
+
+    /// The traffic profile
+    pub traffic: TrafficProfile,
+
+    /// The number of passengers in the traffic
+    pub passengers: usize,
+
+    /// The number of passengers which got to their floor before the simulation ended
+    pub served: usize,
+
+    /// The wait times of the passengers which were picked up
+    pub wait: Stats,
+
+    /// The journey times of the passengers which got to their floor
+    pub journey: Stats,
+
+    /// The distance driven by all the cars together, in floors
+    pub distance: f64,
+}
+
+/// A passenger riding a car
+struct Rider
+{
+    passenger: Passenger,
+    car: usize,
+}
+
+/// Runs the traffic in `config` against a simulated fleet, with the hall requests assigned by `assigner`
+///
+/// ## Parameters
+/// `config`: The traffic and fleet
+/// `assigner`: The hall assignment strategy
+///
+/// ## Behavior
+/// - The node options are read from [config] with [NodeConfig::from_config], with the hall assigner replaced by `assigner`.
+/// - Each time step, new passengers press their hall buttons, the cars are moved and stepped, and passengers get on and off.
+/// - The master's worldview is then updated from the cars like [join_wv_from_container](crate::network::local_network) does, and
+///   the tasks are recomputed when the [AssignmentInput] changes, like in [manager::start_manager].
+/// - The simulation ends when every passenger has got to their floor, or [DRAIN_TIME] after the traffic ends.
+///
+/// ## Returns
+/// The metrics of the run
+///
+/// # Notes
+/// The age of the hall requests is written to [WorldView::hall_request_since] relative to the wall clock, so
+/// [starvation](manager::starvation) is measured in simulated time.
+pub async fn run(
+    config: &BenchConfig,
+    assigner: HallAssignerKind
+) -> BenchReport
+{
+    let node_config = NodeConfig { hall_assigner: assigner, ..NodeConfig::from_config() };
+    let num_floors = node_config.num_floors;
+    let passengers = traffic::generate(config.traffic, config.rate_per_min, config.duration, num_floors, config.seed);
+
+    let start = Instant::now();
+    let mut cars: Vec<SimulatedCar> = (1..=config.num_elevators).map(|id| SimulatedCar::new(id, 0, &node_config, start)).collect();
+
+    let mut wv = WorldView::new(num_floors);
+    for car in cars.iter()
+    {
+        wv.add_elev(car.elevator().clone());
+    }
+
+    let mut next_arrival = 0;
+    let mut waiting: Vec<Passenger> = Vec::new();
+    let mut riding: Vec<Rider> = Vec::new();
+    let mut hall_since: Vec<[Option<Duration>; 2]> = vec![[None; 2]; num_floors as usize];
+    let mut waits = Vec::new();
+    let mut journeys = Vec::new();
+
+    let mut tracker = TaskTracker::default();
+    let mut trace = TraceWriter::default();
+    let mut last_input: Option<AssignmentInput> = None;
+
+    let mut t = Duration::ZERO;
+    while t <= config.duration + DRAIN_TIME
+    {
+        let now = start + t;
+
+        // New passengers press their hall button
+        while let Some(passenger) = passengers.get(next_arrival).filter(|p| p.arrival <= t)
+        {
+            wv.hall_request[passenger.origin as usize][passenger.call()] = true;
+            waiting.push(*passenger);
+            next_arrival += 1;
+        }
+
+        for (i, car) in cars.iter_mut().enumerate()
+        {
+            car.advance(TIME_STEP, now);
+            if car.elevator().behaviour != ElevatorBehaviour::DoorOpen {continue}
+
+            // Passengers get off at their floor, and on if the car served their hall button
+            let floor = car.elevator().last_floor_sensor;
+            riding.retain(|rider| {
+                let arrived = rider.car == i && rider.passenger.destination == floor;
+                if arrived {journeys.push(t - rider.passenger.arrival)}
+                !arrived
+            });
+
+            let served = car.elevator().served_hall_request[floor as usize];
+            let mut boarding = Vec::new();
+            waiting.retain(|p| {
+                let boards = p.origin == floor && served[p.call()];
+                if boards {boarding.push(*p)}
+                !boards
+            });
+            for passenger in boarding
+            {
+                waits.push(t - passenger.arrival);
+                let cab = CallButton { floor: passenger.destination, call_type: CallType::INSIDE, elev_id: car.elevator().elevator_id };
+                car.handle(FsmEvent::ButtonPressed(cab), now);
+                riding.push(Rider { passenger, car: i });
+            }
+        }
+
+        // The master takes in the states of the cars, and clears the hall requests they have served
+        for (car, elev) in cars.iter().zip(wv.elevator_containers.iter_mut())
+        {
+            let tasks = std::mem::take(&mut elev.tasks);
+            *elev = car.elevator().clone();
+            elev.tasks = tasks;
+
+            for ((hall, task), served) in wv.hall_request.iter_mut().zip(elev.tasks.iter_mut()).zip(elev.served_hall_request.iter())
+            {
+                for call in 0..2
+                {
+                    if served[call]
+                    {
+                        hall[call] = false;
+                        task[call] = false;
+                    }
+                }
+            }
+        }
+
+        // The age of the hall requests, on the wall clock
+        let wall_now = world_view::unix_millis();
+        for ((hall, since), wall_since) in wv.hall_request.iter().zip(hall_since.iter_mut()).zip(wv.hall_request_since.iter_mut())
+        {
+            for call in 0..2
+            {
+                since[call] = if hall[call] {Some(since[call].unwrap_or(t))} else {None};
+                wall_since[call] = since[call].map(|since| wall_now.saturating_sub((t - since).as_millis() as u64));
+            }
+        }
+
+        if !tracker.update(&wv, now, config::TASK_TIMEOUT).is_empty()
+        {
+            last_input = None;
+        }
+
+        let input = AssignmentInput::from_wv(&wv, node_config.starvation_time);
+        if last_input.as_ref() != Some(&input)
+        {
+            last_input = Some(input);
+            let assignment = manager::get_elev_tasks(&wv, &tracker, &node_config, &mut trace).await;
+
+            for (car, elev) in cars.iter_mut().zip(wv.elevator_containers.iter_mut())
+            {
+                let mut tasks = assignment.tasks.get(&elev.elevator_id).cloned().unwrap_or_default();
+                tasks.resize(elev.num_floors as usize, [false; 2]);
+                elev.tasks = tasks.clone();
+
+                let event = FsmEvent::TasksUpdated
+                {
+                    tasks,
+                    cab_requests: car.elevator().cab_requests.clone(),
+                    unsent_hall_request: vec![[false; 2]; elev.num_floors as usize],
+                };
+                car.handle(event, now);
+            }
+        }
+
+        if t >= config.duration && next_arrival == passengers.len() && waiting.is_empty() && riding.is_empty() {break}
+        t += TIME_STEP;
+    }
+
+    BenchReport
+    {
+        assigner,
+        traffic: config.traffic,
+        passengers: passengers.len(),
+        served: journeys.len(),
+        wait: Stats::from_durations(&waits),
+        journey: Stats::from_durations(&journeys),
+        distance: cars.iter().map(|car| car.distance).sum(),
+    }
+}
@@ -0,0 +1,203 @@
+//! Synthetic passenger traffic
+//!
+//! Passengers arrive as a Poisson process, with the arrival rate given in passengers per minute.
+//! The [TrafficProfile] decides where they come from and where they go.
+//!
+//! The traffic only depends on the seed, so every strategy can be run against the same passengers.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use elevatorpro::bench::traffic::{self, TrafficProfile};
+//!
+//! let hour = Duration::from_secs(3600);
+//! let passengers = traffic::generate(TrafficProfile::UpPeak, 10.0, hour, 4, 1);
+//!
+//! // About 600 passengers, all from the ground floor and up
+//! assert!((500..700).contains(&passengers.len()));
+//! assert!(passengers.iter().all(|p| p.origin == 0 && p.destination > 0 && p.destination < 4));
+//! assert!(passengers.windows(2).all(|p| p[0].arrival <= p[1].arrival));
+//!
+//! // The same seed gives the same traffic
+//! assert_eq!(passengers, traffic::generate(TrafficProfile::UpPeak, 10.0, hour, 4, 1));
+//!
+//! // Bursts only arrive in the first quarter of every minute
+//! let passengers = traffic::generate(TrafficProfile::Burst, 10.0, hour, 4, 1);
+//! assert!(passengers.iter().all(|p| p.arrival.as_secs() % 60 < 15));
+//! ```
+
+use std::time::Duration;
+
+
+/// How often a burst starts, see [TrafficProfile::Burst]
+pub const BURST_PERIOD: Duration = Duration::from_secs(60);
+
+/// How long a burst lasts, see [TrafficProfile::Burst]
+pub const BURST_LENGTH: Duration = Duration::from_secs(15);
+
+/// Where the passengers come from and where they go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficProfile
+{
+    /// Morning traffic: every passenger arrives at the ground floor, and goes to a random floor above
+    UpPeak,
+    /// Evening traffic: every passenger arrives at a random floor above the ground floor, and goes down to it
+    DownPeak,
+    /// Traffic between two random floors
+    InterFloor,
+    /// Inter-floor traffic arriving in bursts: the passengers of a [BURST_PERIOD] all arrive in the first [BURST_LENGTH] of it
+    Burst,
+}
+
+impl TrafficProfile
+{
+    /// All the profiles, in the order they are reported
+    pub const ALL: [TrafficProfile; 4] = [TrafficProfile::UpPeak, TrafficProfile::DownPeak, TrafficProfile::InterFloor, TrafficProfile::Burst];
+
+    /// Parses a profile name as given on the command line: `up_peak`, `down_peak`, `inter_floor` or `burst`
+    pub fn from_name(
+        name: &str
+    ) -> Option<Self>
+    {
+        TrafficProfile::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+
+    /// The name of the profile, as given on the command line
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            TrafficProfile::UpPeak => "up_peak",
+            TrafficProfile::DownPeak => "down_peak",
+            TrafficProfile::InterFloor => "inter_floor",
+            TrafficProfile::Burst => "burst",
+        }
+    }
+}
+
+/// A passenger in the generated traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passenger
+{
+    /// When the passenger presses the hall button, from the start of the traffic
+    pub arrival: Duration,
+
+    /// The floor the passenger waits at
+    pub origin: u8,
+
+    /// The floor the passenger goes to. Never the same as `origin`.
+    pub destination: u8,
+}
+
+impl Passenger
+{
+    /// The hall button the passenger presses: 0 for up, 1 for down
+    pub fn call(&self) -> usize
+    {
+        if self.destination > self.origin {0} else {1}
+    }
+}
+
+/// Generates the passengers arriving during `duration`
+///
+/// ## Parameters
+/// `profile`: Where the passengers come from and go
+/// `rate_per_min`: The average number of passengers per minute. For [TrafficProfile::Burst], the average over a [BURST_PERIOD].
+/// `duration`: How long passengers keep arriving
+/// `num_floors`: The number of floors in the building, at least 2
+/// `seed`: Seed of the random generator
+///
+/// ## Returns
+/// The passengers, ordered on arrival. Empty if the rate is not positive or there are fewer than 2 floors.
+pub fn generate(
+    profile: TrafficProfile,
+    rate_per_min: f64,
+    duration: Duration,
+    num_floors: u8,
+    seed: u64
+) -> Vec<Passenger>
+{
+    let mut passengers = Vec::new();
+    if rate_per_min <= 0.0 || num_floors < 2 {return passengers}
+
+    // Bursts arrive at a higher rate, so the average over a period is the same
+    let burst_factor = BURST_PERIOD.as_secs_f64() / BURST_LENGTH.as_secs_f64();
+    let rate_per_sec = match profile
+    {
+        TrafficProfile::Burst => rate_per_min * burst_factor / 60.0,
+        _ => rate_per_min / 60.0,
+    };
+
+    let mut rng = Rng::new(seed);
+    let mut t = 0.0;
+    loop
+    {
+        t += -(1.0 - rng.next_f64()).ln() / rate_per_sec;
+        if t >= duration.as_secs_f64() {break}
+
+        let arrival = Duration::from_secs_f64(t);
+        if profile == TrafficProfile::Burst && (arrival.as_millis() % BURST_PERIOD.as_millis()) >= BURST_LENGTH.as_millis()
+        {
+            // Skip to the start of the next burst
+            t = (t / BURST_PERIOD.as_secs_f64()).ceil() * BURST_PERIOD.as_secs_f64();
+            continue;
+        }
+
+        let (origin, destination) = match profile
+        {
+            TrafficProfile::UpPeak => (0, rng.floor_in(1, num_floors)),
+            TrafficProfile::DownPeak => (rng.floor_in(1, num_floors), 0),
+            TrafficProfile::InterFloor | TrafficProfile::Burst =>
+            {
+                let origin = rng.floor_in(0, num_floors);
+                // Any other floor, with the same chance
+                let destination = ((origin as u16 + rng.floor_in(1, num_floors) as u16) % num_floors as u16) as u8;
+                (origin, destination)
+            }
+        };
+        passengers.push(Passenger { arrival, origin, destination });
+    }
+    passengers
+}
+
+
+/// A small xorshift64* random generator, so the traffic is the same on every platform
+struct Rng
+{
+    state: u64,
+}
+
+impl Rng
+{
+    fn new(
+        seed: u64
+    ) -> Self
+    {
+        // The state must never be zero
+        Rng { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    fn next_u64(&mut self) -> u64
+    {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `[0, 1)`
+    fn next_f64(&mut self) -> f64
+    {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A floor in `[low, high)`
+    fn floor_in(
+        &mut self,
+        low: u8,
+        high: u8
+    ) -> u8
+    {
+        low + (self.next_u64() % (high - low) as u64) as u8
+    }
+}
@@ -0,0 +1,154 @@
+//! Benchmark of the hall assignment strategies
+//!
+//! Runs synthetic traffic against a simulated fleet with each of the selected strategies, and prints
+//! the wait time, journey time and travel distance of each, see [elevatorpro::bench].
+//!
+//! Options are given as `key::value`, like for the elevator node:
+//! - `traffic::(up_peak/down_peak/inter_floor/burst/all)` &rarr; The traffic profile (default all)
+//! - `rate::(passengers per minute)` &rarr; The average arrival rate (default 10)
+//! - `duration::(s)` &rarr; How long passengers keep arriving (default 1800)
+//! - `elevators::(n)` &rarr; The number of cars (default 3)
+//! - `seed::(n)` &rarr; Seed of the traffic (default 1)
+//! - `hall_assigner::(optimal/external/nearest/eta/round_robin/all)` &rarr; The strategies to compare (default all but external)
+//!
+//! Any other option, e.g. `num_floors::8` or `stability_margin::2000`, is applied as for the elevator node, see [elevatorpro::init::parse_args].
+
+
+use elevatorpro::bench::{self, BenchConfig, BenchReport};
+use elevatorpro::bench::traffic::TrafficProfile;
+use elevatorpro::manager::assigner::HallAssignerKind;
+use elevatorpro::config;
+use elevatorpro::init;
+use elevatorpro::print;
+
+use std::env;
+use std::time::Duration;
+
+
+/// The strategies compared by default. External needs the hall assigner D program, and is only run when asked for.
+const DEFAULT_ASSIGNERS: [HallAssignerKind; 4] = [
+    HallAssignerKind::Optimal,
+    HallAssignerKind::NearestCar,
+    HallAssignerKind::EtaSimulation,
+    HallAssignerKind::RoundRobin,
+];
+
+#[tokio::main]
+async fn main()
+{
+    // Warnings from the manager would drown the report
+    *config::PRINT_WARN_ON.lock().unwrap() = false;
+    *config::PRINT_INFO_ON.lock().unwrap() = false;
+    *config::PRINT_OK_ON.lock().unwrap() = false;
+
+    let mut bench_config = BenchConfig::default();
+    let mut profiles = TrafficProfile::ALL.to_vec();
+    let mut assigners = DEFAULT_ASSIGNERS.to_vec();
+
+    for arg in env::args().skip(1)
+    {
+        if arg.to_lowercase() == "help"
+        {
+            println!("Tilgjengelige argument:");
+            println!("  traffic::up_peak/down_peak/inter_floor/burst/all (standard: all)");
+            println!("  rate::<passasjerar per minutt> (standard: {})", bench_config.rate_per_min);
+            println!("  duration::<s> (standard: {})", bench_config.duration.as_secs());
+            println!("  elevators::<antal heisar> (standard: {})", bench_config.num_elevators);
+            println!("  seed::<n> (standard: {})", bench_config.seed);
+            println!("  hall_assigner::optimal/external/nearest/eta/round_robin/all (standard: alle utanom external)");
+            println!("  I tillegg alle argument til heisen, t.d. num_floors::<n> og stability_margin::<ms>");
+            return;
+        }
+
+        let Some((key, raw_value)) = arg.split_once("::") else
+        {
+            print::err(format!("Unknown argument: {}", arg));
+            continue;
+        };
+        let value = raw_value.trim().to_lowercase();
+
+        match key.trim().to_lowercase().as_str()
+        {
+            "traffic" if value == "all" => profiles = TrafficProfile::ALL.to_vec(),
+            "traffic" => match TrafficProfile::from_name(&value)
+            {
+                Some(profile) => profiles = vec![profile],
+                None => print::err(format!("Invalid traffic profile: {}", value)),
+            },
+            "rate" => match value.parse::<f64>()
+            {
+                Ok(rate) if rate > 0.0 => bench_config.rate_per_min = rate,
+                _ => print::err(format!("Invalid rate: {}, expected passengers per minute", value)),
+            },
+            "duration" => match value.parse::<u64>()
+            {
+                Ok(s) if s > 0 => bench_config.duration = Duration::from_secs(s),
+                _ => print::err(format!("Invalid duration: {}, expected seconds", value)),
+            },
+            "elevators" => match value.parse::<u8>()
+            {
+                Ok(n) if n > 0 => bench_config.num_elevators = n,
+                _ => print::err(format!("Invalid number of elevators: {}", value)),
+            },
+            "seed" => match value.parse::<u64>()
+            {
+                Ok(seed) => bench_config.seed = seed,
+                Err(_) => print::err(format!("Invalid seed: {}", value)),
+            },
+            "hall_assigner" if value == "all" => assigners = DEFAULT_ASSIGNERS.to_vec(),
+            "hall_assigner" => match HallAssignerKind::from_name(&value)
+            {
+                Some(assigner) => assigners = vec![assigner],
+                None => print::err(format!("Invalid hall assigner: {}", value)),
+            },
+            _ => init::apply_arg(key, raw_value),
+        }
+    }
+
+    if assigners.contains(&HallAssignerKind::External)
+    {
+        init::build_cost_fn().await;
+    }
+
+    println!(
+        "{} heisar, {} etasjar, {} passasjerar/min i {} s, seed {}",
+        bench_config.num_elevators,
+        *config::NUM_FLOORS.lock().unwrap(),
+        bench_config.rate_per_min,
+        bench_config.duration.as_secs(),
+        bench_config.seed,
+    );
+
+    for profile in profiles
+    {
+        bench_config.traffic = profile;
+        println!();
+        println!("Trafikk: {}", profile.name());
+        println!(
+            "{:<14} {:>10} {:>10} {:>10} {:>12} {:>12} {:>12}",
+            "Strategi", "Levert", "Snitt vent", "p95 vent", "Snitt reise", "p95 reise", "Distanse"
+        );
+        for assigner in assigners.iter()
+        {
+            let report = bench::run(&bench_config, *assigner).await;
+            print_report(&report);
+        }
+    }
+}
+
+/// Prints one line of the report table
+fn print_report(
+    report: &BenchReport
+)
+{
+    println!(
+        "{:<14} {:>10} {:>9.1}s {:>9.1}s {:>11.1}s {:>11.1}s {:>12.1}",
+        format!("{:?}", report.assigner),
+        format!("{}/{}", report.served, report.passengers),
+        report.wait.average.as_secs_f64(),
+        report.wait.p95.as_secs_f64(),
+        report.journey.average.as_secs_f64(),
+        report.journey.p95.as_secs_f64(),
+        report.distance,
+    );
+}
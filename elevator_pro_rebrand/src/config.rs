@@ -7,10 +7,16 @@
 //! Some of these constants may no longer be used. Consider cleaning up unused values.
 
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::Duration;
 use once_cell::sync::Lazy;
 
+use crate::elevator_logic::request::ServingPolicyKind;
+use crate::elevator_logic::timer::TimerConfig;
+use crate::elevio::poll::PollConfig;
+use crate::manager::assigner::HallAssignerKind;
+
 //
 // ──────────────────────────────────────────────────────────────
 //   1. NETWORK SETTINGS
@@ -44,8 +50,14 @@ pub static OFFLINE_IP: Ipv4Addr = Ipv4Addr::new(69, 69, 69, 69);
 /// Localhost address used for visualization tools
 pub static LOCAL_ELEV_IP: &str = "localhost:15657";
 
-/// Broadcast key used to filter out invalid worldview messages
-pub const KEY_STR: &str = "Secret Key";
+/// Default pre-shared key the network messages are authenticated with.  
+/// It is public in the repository, so it keeps out no one, and a warning is printed at startup when it is used.
+pub const DEFAULT_NETWORK_KEY: &str = "Secret Key";
+
+/// Pre-shared key the network messages are authenticated with, set with `network_key::<key>` at startup,
+/// see [crate::network::protocol]. All nodes in a group must use the same key.  
+/// Default: [DEFAULT_NETWORK_KEY]
+pub static NETWORK_KEY: Lazy<Mutex<Vec<u8>>> = Lazy::new(|| Mutex::new(DEFAULT_NETWORK_KEY.as_bytes().to_vec()));
 
 /// SSH password
 pub const SSH_PASSWORD: &str = "Your_Password";
@@ -59,8 +71,46 @@ pub const SSH_PASSWORD: &str = "Your_Password";
 /// Default number of floors in Sanntidshallen setup
 pub const DEFAULT_NUM_FLOORS: u8 = 4;
 
-/// Duration between elevator hardware polls
-pub const ELEV_POLL: Duration = Duration::from_millis(25);
+/// Number of floors of the local elevator, set with `num_floors::<n>` at startup  
+/// Default: [DEFAULT_NUM_FLOORS]
+pub static NUM_FLOORS: Lazy<Mutex<u8>> = Lazy::new(|| Mutex::new(DEFAULT_NUM_FLOORS));
+
+/// Minimum number of floors an elevator can have
+pub const MIN_NUM_FLOORS: u8 = 2;
+
+/// The floors the local elevator serves, set with `served_floors::<f>,<f>,..` at startup  
+/// Default: None (all floors)
+pub static SERVED_FLOORS: Lazy<Mutex<Option<Vec<u8>>>> = Lazy::new(|| Mutex::new(None));
+
+/// How the local elevator serves requests, set with `serving_policy::<policy>` at startup  
+/// Default: [ServingPolicyKind::Collective]
+pub static SERVING_POLICY: Lazy<Mutex<ServingPolicyKind>> = Lazy::new(|| Mutex::new(ServingPolicyKind::Collective));
+
+/// Default duration the door stays open
+pub const DEFAULT_DOOR_TIME: Duration = Duration::from_secs(3);
+
+/// Default grace period for prioritizing cab calls after the elevator stops
+pub const DEFAULT_CAB_PRIORITY_TIME: Duration = Duration::from_secs(10);
+
+/// Default duration without progress before the elevator enters an error state
+pub const DEFAULT_ERROR_TIME: Duration = Duration::from_secs(7);
+
+/// Durations of the FSM timers, set with `door_time::<ms>`, `cab_priority_time::<ms>` and `error_time::<ms>` at startup  
+/// Default: [DEFAULT_DOOR_TIME], [DEFAULT_CAB_PRIORITY_TIME] and [DEFAULT_ERROR_TIME]
+pub static ELEV_TIMERS: Lazy<Mutex<TimerConfig>> = Lazy::new(|| Mutex::new(TimerConfig::default()));
+
+/// Default duration between elevator hardware polls
+pub const DEFAULT_ELEV_POLL: Duration = Duration::from_millis(25);
+
+/// Default number of equal polls in a row needed before a button, stop or obstruction change is accepted
+pub const DEFAULT_ELEV_DEBOUNCE_SAMPLES: u8 = 2;
+
+/// Default number of equal polls in a row needed before a new floor sensor value is accepted
+pub const DEFAULT_ELEV_FLOOR_SAMPLES: u8 = 2;
+
+/// Poll period and debouncing of the elevator inputs, set with `poll_period::<ms>`, `debounce_samples::<n>` and `floor_samples::<n>` at startup  
+/// Default: [DEFAULT_ELEV_POLL], [DEFAULT_ELEV_DEBOUNCE_SAMPLES] and [DEFAULT_ELEV_FLOOR_SAMPLES]
+pub static ELEV_POLL_CONFIG: Lazy<Mutex<PollConfig>> = Lazy::new(|| Mutex::new(PollConfig::default()));
 
 /// Special error ID used to mark invalid elevators
 pub const ERROR_ID: u8 = 255;
@@ -68,6 +118,51 @@ pub const ERROR_ID: u8 = 255;
 /// Position in serialized worldview used to extract the master ID
 pub const MASTER_IDX: usize = 1;
 
+/// Initial delay before reconnecting to the elevatorserver after a lost connection
+pub const ELEV_RECONNECT_MIN: Duration = Duration::from_millis(100);
+
+/// Maximum delay between reconnect attempts to the elevatorserver (backoff doubles up to this)
+pub const ELEV_RECONNECT_MAX: Duration = Duration::from_millis(5000);
+
+/// Time a connection to the elevatorserver must stay up before the reconnect backoff is reset to [ELEV_RECONNECT_MIN]
+pub const ELEV_RECONNECT_RESET: Duration = Duration::from_millis(5000);
+
+/// Time to wait for the elevatorserver to answer a poll before the connection is considered lost
+pub const ELEV_IO_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Run the in-process elevator simulator instead of starting the real elevatorserver
+pub static ELEV_SIMULATOR_ON: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Time the simulated elevator uses between two floors
+pub const SIM_TRAVEL_TIME: Duration = Duration::from_millis(2000);
+
+/// How the manager assigns hall requests, set with `hall_assigner::<strategy>` at startup  
+/// Default: [HallAssignerKind::Optimal]
+pub static HALL_ASSIGNER: Lazy<Mutex<HallAssignerKind>> = Lazy::new(|| Mutex::new(HallAssignerKind::Optimal));
+
+/// How much faster a new elevator must be before a hall request is moved from an elevator making progress on it,
+/// set with `stability_margin::<ms>` at startup, see [crate::manager::stability]  
+/// Default: None (hall requests are moved whenever the assignment changes)
+pub static ASSIGNMENT_STABILITY_MARGIN: Lazy<Mutex<Option<Duration>>> = Lazy::new(|| Mutex::new(None));
+
+/// Default time a hall request can wait before it is given to the nearest elevator
+pub const DEFAULT_STARVATION_TIME: Duration = Duration::from_secs(60);
+
+/// How long a hall request can wait before it is given to the nearest elevator, set with `starvation_time::<ms>` at startup,
+/// see [crate::manager::starvation]  
+/// Default: [DEFAULT_STARVATION_TIME]
+pub static STARVATION_TIME: Lazy<Mutex<Duration>> = Lazy::new(|| Mutex::new(DEFAULT_STARVATION_TIME));
+
+/// File the manager traces its assignments to, set with `assignment_trace::<path>` at startup, see [crate::manager::trace]  
+/// Default: None (no trace)
+pub static ASSIGNMENT_TRACE: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// Size an assignment trace file can reach before it is rotated
+pub const ASSIGNMENT_TRACE_MAX_BYTES: u64 = 1_000_000;
+
+/// Number of rotated assignment trace files kept in addition to the current one, as `<path>.1` (newest) to `<path>.<n>`
+pub const ASSIGNMENT_TRACE_FILES: u32 = 4;
+
 //
 // ──────────────────────────────────────────────────────────────
 //   3. TIMING & TIMEOUTS & INTERVALS
@@ -83,18 +178,44 @@ pub const TCP_PER_U64: u64 = 10;
 /// Time interval between UDP broadcast transmissions
 pub const UDP_PERIOD: Duration = Duration::from_millis(5);
 
+/// Default time between two full worldview snapshots from the master
+pub const DEFAULT_SNAPSHOT_PERIOD: Duration = Duration::from_millis(1000);
+
+/// Time between two full worldview snapshots from the master, set with `snapshot_period::<ms>` at startup.
+/// Only the changes are broadcast in between, see [crate::network::wv_delta]  
+/// Default: [DEFAULT_SNAPSHOT_PERIOD]
+pub static SNAPSHOT_PERIOD: Lazy<Mutex<Duration>> = Lazy::new(|| Mutex::new(DEFAULT_SNAPSHOT_PERIOD));
+
+/// Minimum time between two snapshot requests from a node missing the master's snapshot
+pub const SNAPSHOT_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How far the clock of another node can be from this node's before its messages are dropped, see [crate::network::protocol]
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+/// How far behind the newest message from a node an older one can arrive and still be accepted, see [crate::network::protocol]
+pub const REPLAY_WINDOW: Duration = Duration::from_secs(1);
+
 /// General polling frequency (10 ms)
 pub const POLL_PERIOD: Duration = Duration::from_millis(10);
 
+/// Time the manager waits after a change in the assignment input before recomputing, so bursts of changes give one assignment
+pub const MANAGER_DEBOUNCE: Duration = Duration::from_millis(20);
+
 /// Size of UDP receive buffer in bytes
 pub const UDP_BUFFER: usize = u16::MAX as usize;
 
-/// Timeout for individual elevator tasks before being marked failed
-pub const TASK_TIMEOUT: u64 = 100;
+/// Time an elevator can go without progress on a task before the task is given to another elevator
+pub const TASK_TIMEOUT: Duration = Duration::from_secs(20);
 
 /// Delay between slave retransmissions
 pub const SLAVE_TIMEOUT: Duration = Duration::from_millis(100);
 
+/// Time a slave waits for an ACK from the master before resending a container, increased by 5 ms on each resend
+pub const SLAVE_ACK_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Number of times a slave resends a container before the connection to the master is considered lost
+pub const SLAVE_SEND_RETRIES: u16 = 20;
+
 /// Time backup waits before taking over as master
 pub const MASTER_TIMEOUT: Duration = Duration::from_millis(50000);
 
@@ -1,8 +1,12 @@
 //! Elevator Finite State Machine (FSM) Module
 //!
 //! This module contains the core logic for the elevator's finite state machine (FSM).
-//! It is responsible for reacting to sensor inputs, handling requests, updating the internal
-//! elevator state, and controlling motor and lights accordingly.
+//! It is responsible for reacting to sensor inputs, handling requests and timers, and
+//! updating the internal elevator state.
+//!
+//! The FSM is pure: it never talks to the hardware, reads the clock or sleeps. Every input is an
+//! [`FsmEvent`], the current time is passed in as `now`, and every output is an [`FsmAction`] the caller
+//! executes on the hardware. This makes the FSM deterministic, and lets it be driven by a virtual clock.
 //!
 //! The FSM implements transitions between key elevator states, such as:
 //! - Moving
@@ -12,9 +16,10 @@
 //!
 //! # Main Responsibilities
 //! - Handling initialization from unknown position (`on_init`)
-//! - Managing floor arrivals and door timeout logic (`on_floor_arrival`, `on_door_timeout`)
-//! - Monitoring inactivity or fault conditions (`handle_error_timeout`)
-//! - Executing transitions from Idle state (`handle_idle_state`)
+//! - Managing floor arrivals and door timeout logic
+//! - Monitoring inactivity or fault conditions, through the error timer
+//! - Executing transitions from Idle state
+//! - Clearing the hall tasks it serves, and keeping them in `served_hall_request` until the master confirms them
 //!
 //! # Timers
 //! The FSM relies on three coordinated timers:
@@ -22,353 +27,605 @@
 //! - `cab_priority`: Gives passengers time to press cab buttons after door opens.
 //! - `error`: Tracks how long the system has been inactive or blocked.
 //!
-//! These timers are grouped into the [`ElevatorTimers`] struct and passed where needed.
+//! These timers are grouped into the [`ElevatorTimers`] struct, and are part of the [`FsmState`].
+//! They are evaluated on [`FsmEvent::Tick`], which should be stepped regularly (every [config::POLL_PERIOD](crate::config::POLL_PERIOD)).
+//!
+//! # Example
+//! ```
+//! use elevatorpro::elevator_logic::fsm::{self, FsmAction, FsmEvent, FsmState};
+//! use elevatorpro::elevator_logic::request::ServingPolicyKind;
+//! use elevatorpro::elevator_logic::timer::ElevatorTimers;
+//! use elevatorpro::world_view::{Dirn, ElevatorBehaviour, ElevatorContainer};
+//! use tokio::time::{Duration, Instant};
+//!
+//! let timers = ElevatorTimers::new(Duration::from_secs(3), Duration::from_secs(10), Duration::from_secs(7));
+//! let now = Instant::now();
+//!
+//! // The position is unknown, so the elevator drives down to the closest floor
+//! let (state, actions) = fsm::on_init(FsmState::new(ElevatorContainer::default(), timers, ServingPolicyKind::Collective), now);
+//! assert_eq!(actions, vec![FsmAction::Motor(Dirn::Down), FsmAction::DoorLight(false)]);
+//!
+//! // Arriving at a floor without requests stops the elevator and opens the door
+//! let (state, actions) = fsm::step(state, FsmEvent::FloorArrived(1), now);
+//! assert_eq!(actions, vec![FsmAction::FloorIndicator(1), FsmAction::Motor(Dirn::Stop), FsmAction::DoorLight(true)]);
+//! assert_eq!(state.elevator.behaviour, ElevatorBehaviour::DoorOpen);
 //!
-//! # Integration
-//! This module is called from the elevator runtime loop and reacts to:
-//! - New floor sensor values
-//! - Cab and hall call requests
-//! - Timer expirations
+//! // Once the door and cab priority timers run out, the door closes
+//! let (state, actions) = fsm::step(state, FsmEvent::Tick, now + Duration::from_secs(11));
+//! assert_eq!(actions, vec![FsmAction::DoorLight(false)]);
+//! assert_eq!(state.elevator.behaviour, ElevatorBehaviour::Idle);
+//! ```
 //!
+//! A served hall task is kept out of the tasks until the master confirms it, unless the call is pressed again:
+//! ```
+//! use elevatorpro::elevator_logic::fsm::{self, FsmEvent, FsmState};
+//! use elevatorpro::elevator_logic::request::ServingPolicyKind;
+//! use elevatorpro::elevator_logic::timer::ElevatorTimers;
+//! use elevatorpro::elevio::{CallButton, CallType};
+//! use elevatorpro::world_view::ElevatorContainer;
+//! use tokio::time::{Duration, Instant};
+//!
+//! let timers = ElevatorTimers::new(Duration::from_secs(3), Duration::from_secs(3), Duration::from_secs(7));
+//! let start = Instant::now();
+//! let at = |secs: u64| start + Duration::from_secs(secs);
+//! let elevator = ElevatorContainer { last_floor_sensor: 0, ..ElevatorContainer::new(4, None) };
+//! let (state, _) = fsm::on_init(FsmState::new(elevator, timers, ServingPolicyKind::Collective), at(0));
+//!
+//! // The elevator serves hall up on floor 2, and leaves for a cab request on floor 0
+//! let mut tasks = vec![[false; 2]; 4];
+//! tasks[2][0] = true;
+//! let assigned = FsmEvent::TasksUpdated { tasks, cab_requests: vec![false; 4], unsent_hall_request: vec![[false; 2]; 4] };
+//! let (state, _) = fsm::step(state, assigned.clone(), at(0));
+//! let (state, _) = fsm::step(state, FsmEvent::Tick, at(4));
+//! let (state, _) = fsm::step(state, FsmEvent::FloorArrived(1), at(5));
+//! let (state, _) = fsm::step(state, FsmEvent::FloorArrived(2), at(6));
+//! let cab = CallButton { floor: 0, call_type: CallType::INSIDE, elev_id: 0 };
+//! let (state, _) = fsm::step(state, FsmEvent::ButtonPressed(cab), at(6));
+//! let (state, _) = fsm::step(state, FsmEvent::Tick, at(10));
+//! assert!(state.elevator.served_hall_request[2][0]);
+//!
+//! // The master has not confirmed the service yet, so the task is not taken again
+//! let (state, _) = fsm::step(state, assigned.clone(), at(10));
+//! assert!(!state.elevator.tasks[2][0]);
+//!
+//! // Until someone presses the call again
+//! let hall = CallButton { floor: 2, call_type: CallType::UP, elev_id: 0 };
+//! let (state, _) = fsm::step(state, FsmEvent::ButtonPressed(hall), at(10));
+//! assert!(!state.elevator.served_hall_request[2][0]);
+//! let (state, _) = fsm::step(state, assigned, at(10));
+//! assert!(state.elevator.tasks[2][0]);
+//! ```
 //!
 //! # Related Modules
-//! - [`request`]: Direction and behaviour decision logic.
-//! - [`self_elevator`]: Local state updates from hardware events.
-//! - [`lights`]: Door and button light controls.
+//! - [`request`]: Direction and behaviour decision logic, and the [`ServingPolicy`] used by the FSM.
+//! - [`timer`](super::timer): The timers used by the FSM.
 //!
 //! # Note
 //! All function names follow snake_case naming for consistency.
 
 
-use super::self_elevator;
-use super::request;
-use super::lights;
-use super::timer::{ElevatorTimers, Timer};
+use super::request::{self, ServingPolicy, ServingPolicyKind};
+use super::timer::ElevatorTimers;
 
-use crate::print;
-use crate::config;
-use crate::elevio::{self, elev::Elevator}; 
+use crate::elevio::{self, CallButton};
 use crate::world_view::{Dirn, ElevatorBehaviour, ElevatorContainer};
 
-use tokio::time::sleep;
-use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+
+/// An input to the FSM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsmEvent
+{
+    /// The floor sensor detected a new floor
+    FloorArrived(u8),
+
+    /// The door timer ran out. Produced by [FsmEvent::Tick], but may be stepped directly.
+    DoorTimeout,
+
+    /// The error timer ran out. Produced by [FsmEvent::Tick], but may be stepped directly.
+    ErrorTimeout,
+
+    /// A call button was pressed
+    ButtonPressed(CallButton),
+
+    /// The obstruction switch changed
+    Obstruction(bool),
+
+    /// The stop button changed
+    Stop(bool),
+
+    /// New tasks and requests were received from the worldview
+    TasksUpdated
+    {
+        /// Hall requests delegated to this elevator
+        tasks: Vec<[bool; 2]>,
+        /// Cab requests, as agreed on by the network
+        cab_requests: Vec<bool>,
+        /// Hall requests not yet acknowledged by the master
+        unsent_hall_request: Vec<[bool; 2]>,
+    },
+
+    /// Time passed. Checks the timers, and steps [FsmEvent::DoorTimeout] and [FsmEvent::ErrorTimeout] when they are due.
+    Tick,
+}
+
+impl From<elevio::ElevMessage> for FsmEvent
+{
+    fn from(msg: elevio::ElevMessage) -> Self
+    {
+        match msg
+        {
+            elevio::ElevMessage::CallButton(call_btn) => FsmEvent::ButtonPressed(call_btn),
+            elevio::ElevMessage::FloorSensor(floor) => FsmEvent::FloorArrived(floor),
+            elevio::ElevMessage::StopButton(stop) => FsmEvent::Stop(stop),
+            elevio::ElevMessage::Obstruction(obs) => FsmEvent::Obstruction(obs),
+        }
+    }
+}
+
+/// An output from the FSM, to be executed on the elevator hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsmAction
+{
+    /// Set the motor direction
+    Motor(Dirn),
+
+    /// Set the floor indicator
+    FloorIndicator(u8),
+
+    /// Turn the door open light on or off
+    DoorLight(bool),
+
+    /// Turn the stop button light on or off
+    StopButtonLight(bool),
+}
+
+/// The complete state of the FSM
+#[derive(Debug, Clone)]
+pub struct FsmState
+{
+    /// The local elevator
+    pub elevator: ElevatorContainer,
+
+    /// The timers of the elevator
+    pub timers: ElevatorTimers,
+
+    /// How requests are served
+    pub policy: ServingPolicyKind,
+
+    /// Behaviour at the end of the previous [FsmEvent::Tick]
+    prev_behaviour: ElevatorBehaviour,
+
+    /// Last motor direction sent, `None` before the first one
+    motor: Option<Dirn>,
+
+    /// Last door light state sent, `None` before the first one
+    door_light: Option<bool>,
+}
+
+impl FsmState
+{
+    /// Creates a new FSM state from the elevator container, timers and serving policy
+    pub fn new(
+        elevator: ElevatorContainer,
+        timers: ElevatorTimers,
+        policy: ServingPolicyKind
+    ) -> Self
+    {
+        FsmState
+        {
+            prev_behaviour: elevator.behaviour,
+            elevator,
+            timers,
+            policy,
+            motor: None,
+            door_light: None,
+        }
+    }
+}
 
 
 /// Initializes the elevator by moving downward until a valid floor is reached.
-/// 
-/// This function sets the elevator in motion and waits until the floor sensor detects
-/// a valid floor. During this process, it updates the local elevator state with incoming messages,
-/// while tracking timeout conditions through the shared timer structure.
 ///
-/// # Parameters
-/// - `self_container`: Mutable reference to the elevator's internal state.
-/// - `e`: The hardware-facing elevator handle (for motor control).
-/// - `local_elev_rx`: Channel receiver for elevator sensor and button messages.
-/// - `timers`: Mutable reference to the shared `ElevatorTimers` instance.
-pub async fn on_init(
-    self_container: &mut ElevatorContainer,
-    e: Elevator,
-    local_elev_rx: &mut mpsc::Receiver<elevio::ElevMessage>,
-    timers: &mut ElevatorTimers,
-) 
+/// If the floor is unknown, the elevator is set in motion downwards, and the initialization finishes on
+/// the first [FsmEvent::FloorArrived]. If the floor is already known, it is handled as an arrival right away.
+///
+/// ## Parameters
+/// - `state`: The FSM state
+/// - `now`: The current time
+///
+/// ## Returns
+/// The new state, and the actions to execute
+pub fn on_init(
+    mut state: FsmState,
+    now: Instant
+) -> (FsmState, Vec<FsmAction>)
 {
-    e.motor_direction(Dirn::Down as u8);
-    self_container.behaviour = ElevatorBehaviour::Moving;
-    self_container.dirn = Dirn::Down;
+    let mut actions = Vec::new();
+
+    state.elevator.behaviour = ElevatorBehaviour::Moving;
+    state.elevator.dirn = Dirn::Down;
 
-    while self_container.last_floor_sensor == u8::MAX 
+    if state.elevator.last_floor_sensor != u8::MAX
     {
-        self_elevator::update_elev_container_from_msgs(
-            local_elev_rx,
-            self_container,
-            &mut timers.cab_priority,
-            &mut timers.error,
-        ).await;
-
-        sleep(config::POLL_PERIOD).await;
+        on_floor_arrival(&mut state, &mut actions, now);
     }
+    state.prev_behaviour = state.elevator.behaviour;
 
-    on_floor_arrival(
-        self_container,
-        e.clone(),
-        &mut timers.door,
-        &mut timers.cab_priority,
-    ).await;
+    finish(&mut state, &mut actions, now);
+    (state, actions)
 }
 
-/// Handles floor arrival updates when a new floor sensor reading is detected.
+/// Steps the FSM one event
 ///
-/// If the current floor (`last_floor_sensor`) is different from the previously known floor,
-/// this function triggers arrival-handling logic, restarts the error timer,
-/// and optionally releases the cab call timer if the stop was due to an inside request.
+/// ## Parameters
+/// - `state`: The FSM state
+/// - `event`: The event to handle
+/// - `now`: The current time
 ///
-/// # Parameters
-/// - `self_container`: Mutable reference to the local elevator state.
-/// - `e`: Elevator hardware interface for controlling motor and lights.
-/// - `prev_floor`: Mutable reference to the previous floor value (used for change detection).
-/// - `timers`: Mutable reference to the shared `ElevatorTimers` instance.
+/// ## Behavior
+/// - The event is applied to the state
+/// - If the elevator is idle after the event, pending requests are started
+/// - The motor and door light are updated if the new state requires it. Unchanged outputs are not repeated.
 ///
-/// # Behavior
-/// - Calls `on_floor_arrival()` if floor changed.
-/// - Starts the error timer on valid floor detection.
-/// - Releases the cab call timer if the stop was due to an inside (cab) request.
-pub async fn handle_floor_sensor_update(
-    self_container: &mut ElevatorContainer,
-    e: Elevator,
-    prev_floor: &mut u8,
-    timers: &mut ElevatorTimers,
-) 
+/// ## Returns
+/// The new state, and the actions to execute, in order
+pub fn step(
+    mut state: FsmState,
+    event: FsmEvent,
+    now: Instant
+) -> (FsmState, Vec<FsmAction>)
+{
+    let mut actions = Vec::new();
+    apply(&mut state, event, &mut actions, now);
+    finish(&mut state, &mut actions, now);
+    (state, actions)
+}
+
+/// Applies one event to the state
+fn apply(
+    state: &mut FsmState,
+    event: FsmEvent,
+    actions: &mut Vec<FsmAction>,
+    now: Instant
+)
 {
-    if *prev_floor != self_container.last_floor_sensor 
+    match event
     {
-        on_floor_arrival(self_container, e, &mut timers.door, &mut timers.cab_priority).await;
-        timers.error.timer_start();
+        FsmEvent::FloorArrived(floor) =>
+        {
+            state.elevator.last_floor_sensor = floor;
+            on_floor_arrival(state, actions, now);
+            state.timers.error.timer_start(now);
+
+            // Ignore cab call timeout if request came from inside button
+            if !request::was_outside(&state.elevator)
+            {
+                state.timers.cab_priority.release_timer();
+            }
+        }
 
-        // Ignore cab call timeout if request came from inside button
-        if !request::was_outside(self_container) 
+        FsmEvent::DoorTimeout => on_door_timeout(state, actions, now),
+
+        FsmEvent::ErrorTimeout => on_error_timeout(state, now),
+
+        FsmEvent::ButtonPressed(call_btn) => on_button_press(state, call_btn),
+
+        FsmEvent::Obstruction(obs) =>
         {
-            timers.cab_priority.release_timer();
+            state.elevator.obstruction = obs;
+            if !obs && state.timers.error.timer_timeouted(now)
+            {
+                state.timers.error.timer_start(now);
+                state.elevator.behaviour = ElevatorBehaviour::Idle;
+            }
         }
 
-        *prev_floor = self_container.last_floor_sensor;
+        // Stop button is not properly implemented. Pressing stop button will only put the elevator in `CosmicError`-state
+        FsmEvent::Stop(stop) =>
+        {
+            if state.elevator.stop == stop {return}
+            state.elevator.stop = stop;
+            actions.push(FsmAction::StopButtonLight(stop));
+            if stop
+            {
+                state.elevator.behaviour = ElevatorBehaviour::CosmicError;
+                state.elevator.dirn = Dirn::Stop;
+                set_motor(state, actions, Dirn::Stop);
+            }
+            else
+            {
+                state.elevator.behaviour = ElevatorBehaviour::Idle;
+            }
+        }
+
+        FsmEvent::TasksUpdated { tasks, cab_requests, unsent_hall_request } =>
+        {
+            state.elevator.tasks = tasks;
+            state.elevator.cab_requests = cab_requests;
+            state.elevator.unsent_hall_request = unsent_hall_request;
+            mask_served_tasks(&mut state.elevator);
+        }
+
+        FsmEvent::Tick => on_tick(state, actions, now),
     }
 }
 
-/// Function to handle stop button event
-/// 
-/// # Note
-/// Stop button is not properly implemented. Pressing stop button will only put the elevator in `CosmicError`-state
-pub async fn handle_stop_button(
-    self_container: &mut ElevatorContainer,
-    e: Elevator,
-    prev_stop_btn: &mut bool,
-) 
+/// Common handling after every event: leaves idle if there are requests, and updates the motor and door light
+fn finish(
+    state: &mut FsmState,
+    actions: &mut Vec<FsmAction>,
+    now: Instant
+)
 {
-    if *prev_stop_btn != self_container.stop 
+    if state.elevator.behaviour == ElevatorBehaviour::Idle
     {
-        if self_container.stop 
-        {
-            lights::set_stop_button_light(e.clone());
-            self_container.behaviour = ElevatorBehaviour::CosmicError; 
-            e.motor_direction(Dirn::Stop as u8);
-        } else 
+        let status_pair = state.policy.choose_direction(&state.elevator);
+
+        if status_pair.behaviour != ElevatorBehaviour::Idle
         {
-            lights::clear_stop_button_light(e.clone());
-            self_container.behaviour = ElevatorBehaviour::Idle;
+            state.elevator.dirn = status_pair.dirn;
+            state.elevator.behaviour = status_pair.behaviour;
+            state.timers.door.timer_start(now);
+            set_motor(state, actions, Dirn::Stop);
         }
-        *prev_stop_btn = self_container.stop;
+    }
+
+    // The motor is left alone while the door is open, it was stopped when the door opened
+    if state.elevator.behaviour != ElevatorBehaviour::DoorOpen
+    {
+        set_motor(state, actions, state.elevator.dirn);
+    }
+
+    let door_open = matches!(state.elevator.behaviour, ElevatorBehaviour::DoorOpen | ElevatorBehaviour::ObstructionError);
+    if state.door_light != Some(door_open)
+    {
+        state.door_light = Some(door_open);
+        actions.push(FsmAction::DoorLight(door_open));
     }
 }
 
-/// Handles door timeout logic when appropriate.
-///
-/// If the door timer has expired and no obstruction is detected. 
-/// If the elevator is moving toward a cab call, the cab call timer
-/// is released. If the cab call timer has also expired, the system proceeds to handle
-/// the door timeout state transition.
-///
-/// # Parameters
-/// - `self_container`: Mutable reference to the elevator's internal state.
-/// - `e`: Elevator identifier or hardware handle used to control lights and motors.
-/// - `door_timer`: Timer that tracks how long the door has been open.
+/// Pushes a motor action if the direction differs from the last one sent
+fn set_motor(
+    state: &mut FsmState,
+    actions: &mut Vec<FsmAction>,
+    dirn: Dirn
+)
+{
+    if state.motor != Some(dirn)
+    {
+        state.motor = Some(dirn);
+        actions.push(FsmAction::Motor(dirn));
+    }
+}
+
+/// Adds a pressed call button to the elevators requests. Calls on floors the elevator does not serve are ignored.
 ///
-/// # Behavior
-/// - Handles door-close logic via finite state machine if cab call timer is also expired.
-pub async fn handle_door_timeout(
-    self_container: &mut ElevatorContainer,
-    e: Elevator,
-    door_timer: &Timer,
-    cab_priority_timer: &mut Timer,
-) 
+/// A hall call pressed again before the master has confirmed it as served is no longer served, so the master
+/// keeps the new press and the elevator takes the task again, see [mask_served_tasks].
+fn on_button_press(
+    state: &mut FsmState,
+    call_btn: CallButton
+)
 {
-    if door_timer.timer_timeouted() && !self_container.obstruction 
+    let floor = call_btn.floor as usize;
+    if !state.elevator.serves_floor(floor) {return}
+
+    match call_btn.call_type
     {
-        if request::moving_towards_cab_call(&self_container.clone()) 
+        elevio::CallType::INSIDE =>
         {
-            cab_priority_timer.release_timer();
+            state.timers.cab_priority.release_timer();
+            if let Some(cab) = state.elevator.cab_requests.get_mut(floor) {*cab = true}
         }
-
-        if cab_priority_timer.timer_timeouted() 
+        elevio::CallType::UP =>
+        {
+            if let Some(hall) = state.elevator.unsent_hall_request.get_mut(floor) {hall[0] = true}
+            if let Some(served) = state.elevator.served_hall_request.get_mut(floor) {served[0] = false}
+        }
+        elevio::CallType::DOWN =>
         {
-            on_door_timeout(self_container, e.clone()).await;
+            if let Some(hall) = state.elevator.unsent_hall_request.get_mut(floor) {hall[1] = true}
+            if let Some(served) = state.elevator.served_hall_request.get_mut(floor) {served[1] = false}
         }
+        elevio::CallType::COSMIC_ERROR => {},
     }
 }
 
-/// Monitors elevator activity and triggers error behavior after a timeout period.
+/// Clears the requests at the current floor with the serving policy, and marks the hall tasks it cleared as served
 ///
-/// If no cab call has timed out or the elevator is idle, the error timer is restarted.
-/// If the error timer itself has expired and a cab call was previously active,
-/// the elevator enters an error state and logs a critical error message.
-///
-/// # Parameters
-/// - `self_container`: Reference to the elevator state being monitored.
-/// - `cab_priority_timer`: Timer tracking how long a cab call has been pending.
-/// - `error_timer`: Mutable timer for detecting inactivity or system faults.
-/// - `prev_cab_priority_timer_stat`: Whether the cab call timer had previously expired.
-///
-/// # Behavior
-/// - Triggers an error state if prolonged inactivity or failure is detected.
-pub fn handle_error_timeout(
-    self_container: &ElevatorContainer,
-    cab_priority_timer: &Timer,
-    error_timer: &mut Timer,
-    prev_cab_priority_timer_stat: bool,
-) 
+/// The served hall tasks are kept in `served_hall_request` until the master confirms them, see [mask_served_tasks].
+fn clear_at_current_floor(
+    state: &mut FsmState
+)
 {
-    if !cab_priority_timer.timer_timeouted() || self_container.behaviour == ElevatorBehaviour::Idle 
-    {
-        error_timer.timer_start();
-    }
+    let floor = state.elevator.last_floor_sensor as usize;
+    let before = state.elevator.tasks.get(floor).copied();
+    state.policy.clear_at_current_floor(&mut state.elevator);
 
-    if error_timer.timer_timeouted() && !prev_cab_priority_timer_stat 
+    let after = state.elevator.tasks.get(floor).copied();
+    if let (Some(before), Some(after), Some(served)) = (before, after, state.elevator.served_hall_request.get_mut(floor))
     {
-        if !self_container.obstruction && (self_container.behaviour == ElevatorBehaviour::DoorOpen) {
-            error_timer.timer_start();
-        } else {
-            print::err("Elevator entered error".to_string());
+        for call in 0..2
+        {
+            served[call] |= before[call] && !after[call];
         }
     }
 }
 
-/// Attempts to transition the elevator from idle to active movement if a request is pending.
+/// Applies the served hall tasks to new tasks from the worldview
 ///
-/// If the elevator is currently idle, the system chooses a new direction and behavior
-/// using the request logic. If a non-idle state is chosen, the elevator's direction
-/// and behavior are updated, the door timer is started, and the motor is stopped
-/// in preparation for movement or door logic.
-///
-/// # Parameters
-/// - `self_container`: Mutable reference to the elevator's current state.
-/// - `e`: Elevator handle or control interface.
-/// - `door_timer`: Timer used to delay transitions or prepare door actions.
-///
-/// # Behavior
-/// - Only operates when the elevator is in an idle state.
-/// - Initializes direction and behavior when transitioning out of idle.
-/// - Starts door timer and stops the motor to stabilize before further action.
-pub fn handle_idle_state(
-    self_container: &mut ElevatorContainer,
-    e: Elevator,
-    door_timer: &mut Timer,
-) 
+/// A served task is removed from the tasks while the master still assigns it, so it is not served twice.
+/// The master confirms the clearing by no longer assigning the task, which also forgets the served task.
+fn mask_served_tasks(
+    elevator: &mut ElevatorContainer
+)
 {
-    if self_container.behaviour == ElevatorBehaviour::Idle 
+    for (served, task) in elevator.served_hall_request.iter_mut().zip(elevator.tasks.iter_mut())
     {
-        let status_pair = request::choose_direction(&self_container.clone());
-
-        if status_pair.behaviour != ElevatorBehaviour::Idle 
+        for call in 0..2
         {
-            print::err(format!("Skal nå være: {:?}", status_pair.behaviour));
-            self_container.dirn = status_pair.dirn;
-            self_container.behaviour = status_pair.behaviour;
-            door_timer.timer_start();
-            e.motor_direction(Dirn::Stop as u8);
+            served[call] &= task[call];
+            task[call] &= !served[call];
         }
     }
 }
 
-
-
-
-
-
-
-
-
 /// Handles elevator behavior upon arrival at a new floor.
 ///
 /// If the elevator is currently moving or in an error state, this function checks
 /// whether it should stop at the current floor (e.g., due to a hall or cab request).
 /// If a stop is needed, it performs the following actions:
 /// - Stops the motor
-/// - Clears cab requests at the current floor
+/// - Clears the served requests at the current floor
 /// - Starts both the door timer and the cab call priority timer
 /// - Sets the elevator's behavior to `DoorOpen`
-///
-///
-/// # Parameters
-/// - `elevator`: Mutable reference to the elevator's internal state.
-/// - `e`: Elevator hardware interface, used to control motor and lights.
-/// - `door_timer`: Timer tracking how long the door should stay open.
-/// - `cab_priority_timer`: Timer giving priority to inside cab requests after door opens.
-async fn on_floor_arrival(
-    elevator: &mut ElevatorContainer,
-    e: Elevator,
-    door_timer: &mut Timer,
-    cab_priority_timer: &mut Timer,
-) 
+fn on_floor_arrival(
+    state: &mut FsmState,
+    actions: &mut Vec<FsmAction>,
+    now: Instant
+)
 {
+    let elevator = &mut state.elevator;
+
     // Fix startup case: sensor value is 255 when between floors → set to top floor
-    if elevator.last_floor_sensor > elevator.num_floors 
+    if elevator.last_floor_sensor >= elevator.num_floors
     {
         elevator.last_floor_sensor = elevator.num_floors - 1;
     }
 
-    // Turn on light for active cab request (if any)
-    lights::set_cab_light(e.clone(), elevator.last_floor_sensor);
+    actions.push(FsmAction::FloorIndicator(elevator.last_floor_sensor));
 
-    match elevator.behaviour 
+    let stopping = matches!(elevator.behaviour, ElevatorBehaviour::Moving | ElevatorBehaviour::ObstructionError | ElevatorBehaviour::TravelError);
+    if stopping && state.policy.should_stop(elevator)
     {
-        ElevatorBehaviour::Moving | ElevatorBehaviour::ObstructionError | ElevatorBehaviour::TravelError => 
-        {
-            if request::should_stop(&elevator.clone()) 
-            {
-                e.motor_direction(Dirn::Stop as u8);
-                request::clear_at_current_floor(elevator);
-                door_timer.timer_start();
-                cab_priority_timer.timer_start();
-                elevator.behaviour = ElevatorBehaviour::DoorOpen;
-            }
-        }
-        _ => {}
+        clear_at_current_floor(state);
+        state.elevator.behaviour = ElevatorBehaviour::DoorOpen;
+        state.timers.door.timer_start(now);
+        state.timers.cab_priority.timer_start(now);
+        set_motor(state, actions, Dirn::Stop);
     }
 }
 
-/// Handles the event when the door timeout has expired.
+/// Handles the event when the door timer has expired.
 ///
-/// This function is called after the door has been open for a certain time.
-/// It decides what the elevator should do next by:
-/// - Choosing a new direction and behaviour using the request logic
-/// - Updating the elevator's direction and behaviour accordingly
+/// The door is only closed if there is no obstruction, and the cab call priority timer has expired as well.
+/// The cab call priority timer is released if the elevator is moving toward a cab call.
 ///
+/// When the door closes, a new direction and behaviour is chosen using the request logic.
 /// If the elevator decides to stay in `DoorOpen`, it means there is still
 /// a request at the current floor and the door should remain open.
 /// Otherwise, the elevator starts moving in the chosen direction.
-///
-/// # Parameters
-/// - `elevator`: Mutable reference to the elevator's internal state.
-/// - `e`: Elevator hardware interface, used to control lights and motor.
-async fn on_door_timeout(
-    elevator: &mut ElevatorContainer, e: Elevator
-) 
+fn on_door_timeout(
+    state: &mut FsmState,
+    actions: &mut Vec<FsmAction>,
+    now: Instant
+)
 {
-    match elevator.behaviour 
+    if !state.timers.door.timer_timeouted(now) || state.elevator.obstruction {return}
+
+    if request::moving_towards_cab_call(&state.elevator)
+    {
+        state.timers.cab_priority.release_timer();
+    }
+
+    if !state.timers.cab_priority.timer_timeouted(now) || state.elevator.behaviour != ElevatorBehaviour::DoorOpen {return}
+
+    let state_pair = state.policy.choose_direction(&state.elevator);
+    state.elevator.behaviour = state_pair.behaviour;
+    state.elevator.dirn = state_pair.dirn;
+
+    match state.elevator.behaviour
     {
-        ElevatorBehaviour::DoorOpen => 
+        ElevatorBehaviour::DoorOpen =>
+        {
+            clear_at_current_floor(state);
+        }
+        _ =>
         {
-            let state_pair = request::choose_direction(&elevator.clone());
+            set_motor(state, actions, state.elevator.dirn);
+        }
+    }
+}
 
-            
-            elevator.behaviour = state_pair.behaviour;
-            elevator.dirn = state_pair.dirn;
+/// Handles the event when the error timer has expired.
+///
+/// An open door without obstruction is not an error, and restarts the error timer.
+/// Otherwise, the elevator enters an error state based on what it was doing:
+/// `ObstructionError` if the door was open, `TravelError` if it was moving, and `CosmicError` otherwise.
+fn on_error_timeout(
+    state: &mut FsmState,
+    now: Instant
+)
+{
+    if !state.timers.prev_cab_priority_timeout
+        && !state.elevator.obstruction
+        && state.elevator.behaviour == ElevatorBehaviour::DoorOpen
+    {
+        state.timers.error.timer_start(now);
+    }
 
-            match elevator.behaviour 
-            {
-                ElevatorBehaviour::DoorOpen => 
-                {
-                    request::clear_at_current_floor(elevator);
-                }
-                _ => 
-                {
-                    e.motor_direction(elevator.dirn as u8);
-                }
-            }
-        },
-        _ => {},
+    if !state.timers.error.timer_timeouted(now)
+    {
+        state.timers.prev_cab_priority_timeout = false;
+        return;
     }
+
+    if is_error(state.prev_behaviour) {return}
+
+    state.timers.prev_cab_priority_timeout = true;
+    state.elevator.behaviour = match state.prev_behaviour
+    {
+        ElevatorBehaviour::DoorOpen => ElevatorBehaviour::ObstructionError,
+        ElevatorBehaviour::Moving => ElevatorBehaviour::TravelError,
+        _ => ElevatorBehaviour::CosmicError,
+    };
 }
 
+/// Checks the timers, and tracks the behaviour between ticks
+///
+/// The error timer is held while the cab call priority timer is running, or the elevator is idle.
+fn on_tick(
+    state: &mut FsmState,
+    actions: &mut Vec<FsmAction>,
+    now: Instant
+)
+{
+    if state.timers.door.timer_timeouted(now) && !state.elevator.obstruction
+    {
+        on_door_timeout(state, actions, now);
+    }
 
+    if !state.timers.cab_priority.timer_timeouted(now) || state.elevator.behaviour == ElevatorBehaviour::Idle
+    {
+        state.timers.error.timer_start(now);
+    }
 
+    if state.timers.error.timer_timeouted(now)
+    {
+        on_error_timeout(state, now);
+    }
+    else
+    {
+        state.timers.prev_cab_priority_timeout = false;
+    }
+
+    let last_behaviour = state.prev_behaviour;
+    state.prev_behaviour = state.elevator.behaviour;
+    state.elevator.last_behaviour = last_behaviour;
 
+    // Force the motor to stop when the door is obstructed for too long
+    if last_behaviour == ElevatorBehaviour::DoorOpen && state.elevator.behaviour == ElevatorBehaviour::ObstructionError
+    {
+        state.elevator.dirn = Dirn::Stop;
+    }
+}
+
+/// Checks if the behaviour is one of the error states
+fn is_error(
+    behaviour: ElevatorBehaviour
+) -> bool
+{
+    matches!(behaviour, ElevatorBehaviour::ObstructionError | ElevatorBehaviour::TravelError | ElevatorBehaviour::CosmicError)
+}
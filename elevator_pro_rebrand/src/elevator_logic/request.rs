@@ -15,6 +15,8 @@
 //!
 //! # Primary Structs
 //! - [`DirnBehaviourPair`]: Return value combining direction and behaviour (e.g., Moving Up).
+//! - [`ServingPolicy`]: How requests are served, with the implementations [`Collective`], [`ClearAll`] and [`InDirection`].
+//! - [`ServingPolicyKind`]: Selects one of the policies, set per node with `serving_policy::<policy>` at startup.
 //!
 //! # Behaviour
 //! The logic is stateless and purely functional, based on snapshot data of the elevator's state.
@@ -213,13 +215,213 @@ pub fn clear_at_current_floor(
 }
 
 
+/// A policy for how the elevator serves the requests at a floor
+///
+/// The FSM asks the policy which way to go, whether to stop at a floor, and which requests are
+/// served when it stops. The default implementations of [ServingPolicy::choose_direction] and
+/// [ServingPolicy::should_stop] are the collective ones from [choose_direction] and [should_stop].
+pub trait ServingPolicy 
+{
+    /// Chooses the next direction and behaviour, see [choose_direction]
+    fn choose_direction(
+        &self, 
+        elevator: &ElevatorContainer
+    ) -> DirnBehaviourPair 
+    {
+        choose_direction(elevator)
+    }
+
+    /// Checks if the elevator should stop at the current floor, see [should_stop]
+    fn should_stop(
+        &self, 
+        elevator: &ElevatorContainer
+    ) -> bool 
+    {
+        should_stop(elevator)
+    }
+
+    /// Clears the requests which are served when the elevator stops at the current floor
+    fn clear_at_current_floor(
+        &self, 
+        elevator: &mut ElevatorContainer
+    );
+}
+
+/// The collective policy: the cab request is cleared, together with the hall request in the direction the elevator departs in.
+/// If there is nothing more to serve in the direction of travel, the elevator turns, and the opposite hall request is cleared instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Collective;
+
+/// "Everyone enters": all requests at the floor are cleared when the elevator stops
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClearAll;
+
+/// Only the requests in the direction of travel are cleared when the elevator stops.
+/// The opposite hall request is left until the elevator has turned, and opens the door for it again.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InDirection;
+
+impl ServingPolicy for Collective 
+{
+    fn clear_at_current_floor(
+        &self, 
+        elevator: &mut ElevatorContainer
+    ) 
+    {
+        clear_at_current_floor(elevator);
+        let floor = elevator.last_floor_sensor as usize;
+        let (above, below) = (above(elevator), below(elevator));
+        let Some(hall) = elevator.tasks.get_mut(floor) else {return};
+
+        match elevator.dirn 
+        {
+            Dirn::Up => 
+            {
+                if !above && !hall[0] {hall[1] = false}
+                hall[0] = false;
+            }
+            Dirn::Down => 
+            {
+                if !below && !hall[1] {hall[0] = false}
+                hall[1] = false;
+            }
+            Dirn::Stop => *hall = [false; 2],
+        }
+    }
+}
+
+impl ServingPolicy for ClearAll 
+{
+    fn clear_at_current_floor(
+        &self, 
+        elevator: &mut ElevatorContainer
+    ) 
+    {
+        clear_at_current_floor(elevator);
+        if let Some(hall) = elevator.tasks.get_mut(elevator.last_floor_sensor as usize) 
+        {
+            *hall = [false; 2];
+        }
+    }
+}
+
+impl ServingPolicy for InDirection 
+{
+    fn clear_at_current_floor(
+        &self, 
+        elevator: &mut ElevatorContainer
+    ) 
+    {
+        clear_at_current_floor(elevator);
+        let Some(hall) = elevator.tasks.get_mut(elevator.last_floor_sensor as usize) else {return};
 
+        match elevator.dirn 
+        {
+            Dirn::Up => hall[0] = false,
+            Dirn::Down => hall[1] = false,
+            Dirn::Stop => *hall = [false; 2],
+        }
+    }
+}
 
+/// Selects which [ServingPolicy] the local elevator uses
+///
+/// # Example
+/// ```
+/// use elevatorpro::elevator_logic::request::{ServingPolicy, ServingPolicyKind};
+/// use elevatorpro::world_view::{Dirn, ElevatorContainer};
+///
+/// let mut elevator = ElevatorContainer::default();
+/// elevator.last_floor_sensor = 1;
+/// elevator.dirn = Dirn::Up;
+/// elevator.tasks[1] = [true, true];
+/// elevator.tasks[3] = [false, true];
+///
+/// let mut collective = elevator.clone();
+/// ServingPolicyKind::Collective.clear_at_current_floor(&mut collective);
+/// assert_eq!(collective.tasks[1], [false, true]);
+///
+/// let mut clear_all = elevator.clone();
+/// ServingPolicyKind::ClearAll.clear_at_current_floor(&mut clear_all);
+/// assert_eq!(clear_all.tasks[1], [false, false]);
+///
+/// // Arriving for a down call with nothing above, the collective elevator turns and takes it right away
+/// elevator.tasks[1] = [false, true];
+/// elevator.tasks[3] = [false, false];
+/// let mut collective = elevator.clone();
+/// ServingPolicyKind::Collective.clear_at_current_floor(&mut collective);
+/// assert_eq!(collective.tasks[1], [false, false]);
+///
+/// ServingPolicyKind::InDirection.clear_at_current_floor(&mut elevator);
+/// assert_eq!(elevator.tasks[1], [false, true]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServingPolicyKind 
+{
+    /// See [Collective]
+    #[default]
+    Collective,
+    /// See [ClearAll]
+    ClearAll,
+    /// See [InDirection]
+    InDirection,
+}
 
+impl ServingPolicyKind 
+{
+    /// Parses a policy name as given on the command line: `collective`, `clear_all` or `in_direction`
+    pub fn from_name(
+        name: &str
+    ) -> Option<Self> 
+    {
+        match name 
+        {
+            "collective" => Some(ServingPolicyKind::Collective),
+            "clear_all" => Some(ServingPolicyKind::ClearAll),
+            "in_direction" => Some(ServingPolicyKind::InDirection),
+            _ => None,
+        }
+    }
 
+    fn policy(
+        &self
+    ) -> &'static dyn ServingPolicy 
+    {
+        match self 
+        {
+            ServingPolicyKind::Collective => &Collective,
+            ServingPolicyKind::ClearAll => &ClearAll,
+            ServingPolicyKind::InDirection => &InDirection,
+        }
+    }
+}
 
+impl ServingPolicy for ServingPolicyKind 
+{
+    fn choose_direction(
+        &self, 
+        elevator: &ElevatorContainer
+    ) -> DirnBehaviourPair 
+    {
+        self.policy().choose_direction(elevator)
+    }
 
+    fn should_stop(
+        &self, 
+        elevator: &ElevatorContainer
+    ) -> bool 
+    {
+        self.policy().should_stop(elevator)
+    }
 
+    fn clear_at_current_floor(
+        &self, 
+        elevator: &mut ElevatorContainer
+    ) 
+    {
+        self.policy().clear_at_current_floor(elevator)
+    }
+}
 
 
 /// Checks if there are any hall or cab requests above the elevator's current floor.
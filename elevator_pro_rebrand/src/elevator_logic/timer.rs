@@ -4,6 +4,7 @@
 //! - [`Timer`]: A general-purpose timer that supports both soft (elapsed time) and hard (manual) timeouts.
 //! - [`ElevatorTimers`]: A struct that bundles all timers used in the elevator FSM, including door timeout,
 //!   cab call priority grace period, and general error detection.
+//! - [`TimerConfig`]: The durations of the [`ElevatorTimers`], set in the runtime config.
 //!
 //! These components are used throughout the elevator state machine to control behavior based on timeouts,
 //! such as how long to keep doors open, how long to prioritize internal cab calls, or when to enter an error state
@@ -15,32 +16,39 @@
 //! - Grace period for cab button prioritization (`cab_priority` timer)
 //! - Communication or logic errors (`error` timer)
 //!
+//! # Time
+//! The timers never read the clock themselves. Every method that depends on time takes the current
+//! time as `now`, which keeps the [`fsm`](crate::elevator_logic::fsm) free of I/O and lets it run on a virtual clock.
+//!
 //! # Example (using Timer standalone)
-//! ```rust,no_run
-//! use tokio::time::Duration;
-//! use crate::elevator_logic::timer::Timer;
+//! ```
+//! use tokio::time::{Duration, Instant};
+//! use elevatorpro::elevator_logic::timer::Timer;
 //!
+//! let start = Instant::now();
 //! let mut door_timer = Timer::new(Duration::from_secs(3));
-//! door_timer.timer_start();
+//! door_timer.timer_start(start);
 //!
-//! // In FSM loop:
-//! if door_timer.timer_timeouted() {
-//!     // Trigger door close logic
-//! }
+//! assert!(!door_timer.timer_timeouted(start + Duration::from_secs(1)));
+//! assert!(door_timer.timer_timeouted(start + Duration::from_secs(4)));
 //! ```
 //!
 //! # ElevatorTimers Usage
 //! ElevatorTimers simplifies timer management by grouping all related timers into one struct:
 //!
-//! ```rust,no_run
+//! ```
+//! use tokio::time::{Duration, Instant};
+//! use elevatorpro::elevator_logic::timer::ElevatorTimers;
+//!
 //! let mut timers = ElevatorTimers::new(
 //!     Duration::from_secs(3),   // door
 //!     Duration::from_secs(10),  // cab priority
 //!     Duration::from_secs(7),   // error
 //! );
 //!
-//! timers.door.timer_start();
-//! if timers.cab_priority.timer_timeouted() {
+//! let now = Instant::now();
+//! timers.door.timer_start(now);
+//! if timers.cab_priority.timer_timeouted(now) {
 //!     // Prioritization window over
 //! }
 //! ```
@@ -55,19 +63,20 @@
 //! Used heavily in the [`fsm`](crate::elevator_logic::fsm) module.
 
 
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
 /// A simple timer utility for managing soft and hard timeouts in asynchronous contexts.
 ///
 /// The timer can be started and queried to check whether the timeout duration has been exceeded.
 /// In addition to the regular (soft) timeout based on elapsed time, a "hard timeout" flag can be manually triggered
 /// to force the timer into a timeout state regardless of elapsed time.
+#[derive(Debug, Clone)]
 pub struct Timer 
 {
     hard_timeout: bool,
     timer_active: bool,
-    timeout_duration: tokio::time::Duration,
-    start_time: tokio::time::Instant,
+    timeout_duration: Duration,
+    start_time: Option<Instant>,
 }
 
 impl Timer 
@@ -82,24 +91,27 @@ impl Timer
     /// # Returns
     /// A new `Timer` instance with the specified timeout duration.
     pub fn new(
-        timeout_duration: tokio::time::Duration
+        timeout_duration: Duration
     ) -> Timer {
         Timer
         {
             hard_timeout: false,
             timer_active: false,
             timeout_duration: timeout_duration,
-            start_time: tokio::time::Instant::now(),
+            start_time: None,
         }
     }
     /// Starts the timer by setting it as active and resetting the start time.
     ///
     /// This also clears any manually set hard timeout.
-    pub fn timer_start(&mut self) 
+    pub fn timer_start(
+        &mut self, 
+        now: Instant
+    ) 
     {
         self.hard_timeout = false;
         self.timer_active = true;
-        self.start_time = tokio::time::Instant::now();
+        self.start_time = Some(now);
     }
 
     /// Forces the timer into a timeout state, regardless of elapsed time.
@@ -112,13 +124,22 @@ impl Timer
 
     /// Returns the duration elapsed since the timer was last started.
     ///
-    /// This does not check whether the timer is active or has timed out.
-    pub fn get_wall_time(&mut self) -> tokio::time::Duration 
+    /// This does not check whether the timer is active or has timed out. A timer which has never been started returns zero.
+    pub fn get_wall_time(
+        &self, 
+        now: Instant
+    ) -> Duration 
     {
-        return tokio::time::Instant::now() - self.start_time
+        self.start_time.map_or(Duration::ZERO, |start| now.saturating_duration_since(start))
     }
 
 
+    /// Returns the duration after which the timer times out once started
+    pub fn timeout_duration(&self) -> Duration 
+    {
+        self.timeout_duration
+    }
+
     /// Checks if the timer has timed out.
     ///
     /// The timer is considered timed out if:
@@ -127,9 +148,12 @@ impl Timer
     ///
     /// # Returns
     /// `true` if the timer is considered to have timed out; `false` otherwise.
-    pub fn timer_timeouted(&self) -> bool 
+    pub fn timer_timeouted(
+        &self, 
+        now: Instant
+    ) -> bool 
     {
-        return (self.timer_active && (tokio::time::Instant::now() - self.start_time) > self.timeout_duration) || self.hard_timeout;
+        return (self.timer_active && self.get_wall_time(now) > self.timeout_duration) || self.hard_timeout;
     }
 }
 
@@ -139,6 +163,7 @@ impl Timer
 /// This struct encapsulates all timers that track different timeout conditions
 /// such as door closing, inside call priority window, and general error state.
 /// Also includes state tracking related to inside call grace period.
+#[derive(Debug, Clone)]
 pub struct ElevatorTimers 
 {
     /// Timer for automatic door closing.
@@ -186,4 +211,90 @@ impl ElevatorTimers
             prev_cab_priority_timeout: false,
         }
     }
+
+    /// Creates a new `ElevatorTimers` instance with the durations from a [TimerConfig].
+    ///
+    /// The config is expected to be validated with [TimerConfig::validate] first.
+    pub fn from_config(
+        config: &TimerConfig
+    ) -> Self {
+        ElevatorTimers::new(config.door, config.cab_priority, config.error)
+    }
+
+    /// Returns the durations of the timers as a [TimerConfig]
+    pub fn config(&self) -> TimerConfig 
+    {
+        TimerConfig 
+        {
+            door: self.door.timeout_duration(),
+            cab_priority: self.cab_priority.timeout_duration(),
+            error: self.error.timeout_duration(),
+        }
+    }
+}
+
+
+/// The durations of the [ElevatorTimers]
+///
+/// Set at startup with `door_time::<ms>`, `cab_priority_time::<ms>` and `error_time::<ms>`, and stored in [config::ELEV_TIMERS](crate::config::ELEV_TIMERS).
+///
+/// # Example
+/// ```
+/// use tokio::time::Duration;
+/// use elevatorpro::elevator_logic::timer::{ElevatorTimers, TimerConfig};
+///
+/// let config = TimerConfig { door: Duration::from_secs(2), ..TimerConfig::default() };
+/// assert!(config.validate().is_ok());
+/// assert_eq!(ElevatorTimers::from_config(&config).config(), config);
+///
+/// // An error timer shorter than the door timer would report every door opening as an error
+/// let config = TimerConfig { error: Duration::from_secs(1), ..TimerConfig::default() };
+/// assert!(config.validate().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerConfig 
+{
+    /// Duration the door stays open
+    pub door: Duration,
+
+    /// Grace period for prioritizing cab calls after stopping
+    pub cab_priority: Duration,
+
+    /// Duration without progress before the elevator enters an error state
+    pub error: Duration,
+}
+
+impl Default for TimerConfig 
+{
+    fn default() -> Self 
+    {
+        TimerConfig 
+        {
+            door: crate::config::DEFAULT_DOOR_TIME,
+            cab_priority: crate::config::DEFAULT_CAB_PRIORITY_TIME,
+            error: crate::config::DEFAULT_ERROR_TIME,
+        }
+    }
+}
+
+impl TimerConfig 
+{
+    /// Checks that the durations make sense together
+    ///
+    /// ## Returns
+    /// `Err` with a description if:
+    /// - Any duration is zero
+    /// - The error timer is not longer than the door timer
+    pub fn validate(&self) -> Result<(), String> 
+    {
+        if self.door.is_zero() || self.cab_priority.is_zero() || self.error.is_zero() 
+        {
+            return Err(format!("Timer durations must be larger than zero: {:?}", self));
+        }
+        if self.error <= self.door 
+        {
+            return Err(format!("The error timer ({:?}) must be longer than the door timer ({:?})", self.error, self.door));
+        }
+        Ok(())
+    }
 }
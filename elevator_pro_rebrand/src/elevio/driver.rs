@@ -0,0 +1,357 @@
+//! ## Asynchronous, reconnecting elevator driver
+//!
+//! The handed out [`super::elev::Elevator`] owns a blocking `TcpStream` and unwraps every read and write,
+//! which makes the whole node panic if the elevatorserver restarts.
+//!
+//! [`AsyncElevator`] speaks the same 4-byte protocol, but the connection is owned by a single tokio task:
+//! - Output commands (motor, lights) are queued for the task, so writing never blocks or panics. Only the newest command
+//!   per output is kept in the queue, so it does not grow while the elevator is disconnected.
+//! - Inputs (buttons, floor sensor, stop, obstruction) are polled by the task every poll period,
+//!   and reads through [`ElevatorIo`] return the last polled snapshot. [`ElevatorIo::next_sweep`] waits for the
+//!   next poll, so [`super::poll::poll_inputs`] debounces on fresh hardware reads.
+//! - If the connection drops, or a connection attempt takes longer than [`config::ELEV_RECONNECT_MAX`], the task reconnects with exponential backoff between
+//!   [`config::ELEV_RECONNECT_MIN`] and [`config::ELEV_RECONNECT_MAX`]. The backoff is only reset when a connection
+//!   has stayed up for [`config::ELEV_RECONNECT_RESET`], so a server which accepts and then closes is not hammered.
+//!   After a reconnect, the last value of every output is sent again so the hardware matches the state of the FSM.
+//!
+//! While disconnected, [`ElevatorIo::is_connected`] returns `false`, which is surfaced to the rest of the system
+//! through [`crate::world_view::ElevatorContainer::hardware_available`].
+//!
+//! ## Example
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! use elevatorpro::elevio::ElevatorIo;
+//! use elevatorpro::elevio::driver::AsyncElevator;
+//! use elevatorpro::elevio::simulator::{ElevatorSimulator, SimConfig};
+//! use std::time::Duration;
+//!
+//! let sim = ElevatorSimulator::start("127.0.0.1:0", SimConfig::default()).unwrap();
+//! let e = AsyncElevator::connect(&sim.addr().to_string(), 4, Duration::from_millis(25));
+//!
+//! tokio::time::sleep(Duration::from_millis(200)).await;
+//! assert!(e.is_connected());
+//! assert_eq!(e.floor_sensor(), Some(0));
+//!
+//! // Every sweep is handed out once
+//! let (first, _) = e.next_sweep(0, Duration::ZERO);
+//! let (second, _) = e.next_sweep(first, Duration::ZERO);
+//! assert!(first > 0 && second == first + 1);
+//!
+//! e.door_light(true);
+//! tokio::time::sleep(Duration::from_millis(100)).await;
+//! assert!(sim.door_light());
+//!
+//! // Outputs set while disconnected are written, newest value only, when the elevatorserver comes up
+//! let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+//! let e = AsyncElevator::connect(&addr.to_string(), 4, Duration::from_millis(25));
+//! for on in [true, false, true]
+//! {
+//!     e.stop_button_light(on);
+//! }
+//! tokio::time::sleep(Duration::from_millis(200)).await;
+//! assert!(!e.is_connected());
+//!
+//! let sim = ElevatorSimulator::start(&addr.to_string(), SimConfig::default()).unwrap();
+//! tokio::time::sleep(Duration::from_millis(1000)).await;
+//! assert!(e.is_connected());
+//! assert!(sim.stop_button_light());
+//! # }
+//! ```
+
+use super::{ElevatorIo, ElevInputs};
+
+use crate::config;
+use crate::print;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::time::{interval, sleep, timeout, Instant};
+
+
+/// Handle to the asynchronous elevator driver
+///
+/// Cloning the handle is cheap, and all clones share the same connection.
+#[derive(Clone, Debug)]
+pub struct AsyncElevator
+{
+    /// Output commands not yet written, at most one per [output_key]
+    outputs: Arc<Mutex<Vec<[u8; 4]>>>,
+    /// Wakes the connection task when `outputs` has changed
+    outputs_tx: mpsc::Sender<()>,
+    inputs: Arc<Sweeps>,
+    connected: Arc<AtomicBool>,
+    num_floors: u8,
+}
+
+/// The last polled inputs, numbered so each sweep can be told apart
+#[derive(Debug)]
+struct Sweeps
+{
+    /// The number of the last sweep, `0` before the first one, and its inputs
+    latest: Mutex<(u64, ElevInputs)>,
+    /// Notified on every new sweep
+    new_sweep: Condvar,
+}
+
+impl AsyncElevator
+{
+    /// Starts the driver towards the elevatorserver on `addr`
+    ///
+    /// ## Parameters
+    /// `addr`: Address of the elevatorserver, e.g. [config::LOCAL_ELEV_IP]
+    /// `num_floors`: Number of floors of the elevator
+    /// `poll_period`: Time between two polls of the inputs, see [super::poll::PollConfig::period]
+    ///
+    /// ## Returns
+    /// A handle to the driver. The handle is returned immediately, the connection is established in the background.
+    ///
+    /// ## Note
+    /// Must be called from within a tokio runtime
+    pub fn connect(
+        addr: &str,
+        num_floors: u8,
+        poll_period: Duration
+    ) -> AsyncElevator
+    {
+        let (outputs_tx, outputs_rx) = mpsc::channel::<()>(1);
+        let elevator = AsyncElevator
+        {
+            outputs: Arc::new(Mutex::new(Vec::new())),
+            outputs_tx,
+            inputs: Arc::new(Sweeps
+            {
+                latest: Mutex::new((0, ElevInputs
+                {
+                    call_buttons: vec![[false; 3]; num_floors as usize],
+                    floor_sensor: None,
+                    stop_button: false,
+                    obstruction: false,
+                })),
+                new_sweep: Condvar::new(),
+            }),
+            connected: Arc::new(AtomicBool::new(false)),
+            num_floors,
+        };
+
+        {
+            let inputs = elevator.inputs.clone();
+            let connected = elevator.connected.clone();
+            let outputs = elevator.outputs.clone();
+            let addr = addr.to_string();
+            tokio::spawn(async move {
+                run_connection(addr, num_floors, poll_period, inputs, connected, outputs, outputs_rx).await;
+            });
+        }
+
+        elevator
+    }
+
+    fn send(&self, cmd: [u8; 4])
+    {
+        set_output(&mut self.outputs.lock().unwrap(), cmd);
+        // Fails if a wake-up is already pending, or if the connection task is gone, in which case there is nothing to write to
+        let _ = self.outputs_tx.try_send(());
+    }
+}
+
+impl ElevatorIo for AsyncElevator
+{
+    fn num_floors(&self) -> u8 {self.num_floors}
+    fn motor_direction(&self, dirn: u8) {self.send([1, dirn, 0, 0])}
+    fn call_button_light(&self, floor: u8, call: u8, on: bool) {self.send([2, call, floor, on as u8])}
+    fn floor_indicator(&self, floor: u8) {self.send([3, floor, 0, 0])}
+    fn door_light(&self, on: bool) {self.send([4, on as u8, 0, 0])}
+    fn stop_button_light(&self, on: bool) {self.send([5, on as u8, 0, 0])}
+
+    fn call_button(&self, floor: u8, call: u8) -> bool
+    {
+        self.inputs.latest.lock().unwrap().1.call_buttons
+            .get(floor as usize)
+            .and_then(|buttons| buttons.get(call as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn floor_sensor(&self) -> Option<u8> {self.inputs.latest.lock().unwrap().1.floor_sensor}
+    fn stop_button(&self) -> bool {self.inputs.latest.lock().unwrap().1.stop_button}
+    fn obstruction(&self) -> bool {self.inputs.latest.lock().unwrap().1.obstruction}
+    fn read_inputs(&self) -> ElevInputs {self.inputs.latest.lock().unwrap().1.clone()}
+
+    /// Waits for the connection task to poll the inputs, at most [config::ELEV_IO_TIMEOUT]. `period` is not used,
+    /// as the task polls every poll period on its own.
+    fn next_sweep(&self, after: u64, _period: Duration) -> (u64, ElevInputs)
+    {
+        let latest = self.inputs.latest.lock().unwrap();
+        let (latest, _) = self.inputs.new_sweep
+            .wait_timeout_while(latest, config::ELEV_IO_TIMEOUT, |(sweep, _)| *sweep == after)
+            .unwrap();
+        latest.clone()
+    }
+    fn is_connected(&self) -> bool {self.connected.load(Ordering::Relaxed)}
+}
+
+
+/// Owns the connection: connects, replays outputs, writes outputs and polls inputs. Reconnects on failure.
+/// Returns when every [AsyncElevator] handle is dropped.
+async fn run_connection(
+    addr: String,
+    num_floors: u8,
+    poll_period: Duration,
+    inputs: Arc<Sweeps>,
+    connected: Arc<AtomicBool>,
+    outputs: Arc<Mutex<Vec<[u8; 4]>>>,
+    mut outputs_rx: mpsc::Receiver<()>
+)
+{
+    // Last value of every output, at most one per output_key, in the order they were set
+    let mut last_outputs: Vec<[u8; 4]> = Vec::new();
+    let mut backoff = config::ELEV_RECONNECT_MIN;
+
+    loop
+    {
+        if outputs_rx.try_recv() == Err(TryRecvError::Disconnected) {return}
+
+        // A host which drops the SYN would otherwise hold the loop for the OS connect timeout
+        let connect = timeout(config::ELEV_RECONNECT_MAX, TcpStream::connect(&addr)).await
+            .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()));
+        let mut stream = match connect
+        {
+            Ok(stream) => stream,
+            Err(e) =>
+            {
+                print::warn(format!("Failed to connect to elevator at {}: {}. Retrying in {:?}", addr, e, backoff));
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(config::ELEV_RECONNECT_MAX);
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+
+        // Replay the outputs so the hardware matches the FSM after a restart, including the ones set while disconnected
+        take_outputs(&outputs, &mut last_outputs);
+        let connected_at = Instant::now();
+        let result = match stream.write_all(&last_outputs.concat()).await
+        {
+            Ok(()) =>
+            {
+                print::ok(format!("Connected to elevator at {}", addr));
+                connected.store(true, Ordering::Relaxed);
+                let result = serve_connection(&mut stream, num_floors, poll_period, &inputs, &outputs, &mut outputs_rx, &mut last_outputs).await;
+                connected.store(false, Ordering::Relaxed);
+                result
+            }
+            Err(e) => Err(e),
+        };
+
+        match result
+        {
+            Ok(()) => return,
+            Err(e) => print::err(format!("Lost connection to elevator at {}: {}. Reconnecting in {:?}", addr, e, backoff)),
+        }
+
+        if connected_at.elapsed() >= config::ELEV_RECONNECT_RESET
+        {
+            backoff = config::ELEV_RECONNECT_MIN;
+        }
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(config::ELEV_RECONNECT_MAX);
+    }
+}
+
+/// Writes outputs and polls inputs on a connected stream
+///
+/// ## Returns
+/// `Ok` when every [AsyncElevator] handle is dropped, or the error which made the connection fail
+async fn serve_connection(
+    stream: &mut TcpStream,
+    num_floors: u8,
+    poll_period: Duration,
+    inputs: &Sweeps,
+    outputs: &Mutex<Vec<[u8; 4]>>,
+    outputs_rx: &mut mpsc::Receiver<()>,
+    last_outputs: &mut Vec<[u8; 4]>
+) -> std::io::Result<()>
+{
+    let mut poll = interval(poll_period);
+    loop
+    {
+        tokio::select! {
+            wake = outputs_rx.recv() =>
+            {
+                if wake.is_none() {return Ok(())}
+                let cmds = take_outputs(outputs, last_outputs);
+                stream.write_all(&cmds.concat()).await?;
+            }
+            _ = poll.tick() =>
+            {
+                match timeout(config::ELEV_IO_TIMEOUT, poll_inputs(stream, num_floors)).await
+                {
+                    Ok(Ok(snapshot)) => 
+                    {
+                        let mut latest = inputs.latest.lock().unwrap();
+                        *latest = (latest.0.wrapping_add(1), snapshot);
+                        inputs.new_sweep.notify_all();
+                    }
+                    Ok(Err(e)) => return Err(e),
+                    Err(_) => return Err(std::io::ErrorKind::TimedOut.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Takes the queued outputs, and records them in `last_outputs`
+fn take_outputs(
+    outputs: &Mutex<Vec<[u8; 4]>>,
+    last_outputs: &mut Vec<[u8; 4]>
+) -> Vec<[u8; 4]>
+{
+    let cmds = std::mem::take(&mut *outputs.lock().unwrap());
+    for cmd in cmds.iter()
+    {
+        set_output(last_outputs, *cmd);
+    }
+    cmds
+}
+
+/// Sets an output in a list of outputs, replacing the earlier value of the same output, see [output_key]
+fn set_output(
+    outputs: &mut Vec<[u8; 4]>,
+    cmd: [u8; 4]
+)
+{
+    outputs.retain(|output| output_key(*output) != output_key(cmd));
+    outputs.push(cmd);
+}
+
+/// Outputs overwrite each other per command, and for lights also per call and floor
+fn output_key(
+    cmd: [u8; 4]
+) -> (u8, u8, u8)
+{
+    match cmd[0]
+    {
+        2 => (cmd[0], cmd[1], cmd[2]),
+        _ => (cmd[0], 0, 0),
+    }
+}
+
+/// Polls all inputs in one pipelined write, and reads all replies
+async fn poll_inputs(
+    stream: &mut TcpStream,
+    num_floors: u8
+) -> std::io::Result<ElevInputs>
+{
+    let requests = ElevInputs::poll_request(num_floors);
+    stream.write_all(&requests).await?;
+
+    let mut replies = vec![0u8; requests.len()];
+    stream.read_exact(&mut replies).await?;
+    Ok(ElevInputs::from_replies(&replies, num_floors))
+}
@@ -84,6 +84,16 @@ impl Elevator {
         buf[1] != 0
     }
 
+    pub fn read_inputs(&self) -> super::ElevInputs {
+        // All requests are written at once, and all replies read back under the same lock
+        let buf = super::ElevInputs::poll_request(self.num_floors);
+        let mut replies = vec![0; buf.len()];
+        let mut sock = self.socket.lock().unwrap();
+        sock.write_all(&buf).unwrap();
+        sock.read_exact(&mut replies).unwrap();
+        super::ElevInputs::from_replies(&replies, self.num_floors)
+    }
+
     pub fn obstruction(&self) -> bool {
         let mut buf = [9, 0, 0, 0];
         let mut sock = self.socket.lock().unwrap();
@@ -2,6 +2,8 @@
 //! 
 //! This module is mostly consisting of handed out resources, but some functionality were added.
 //! The handed out functionality is placed in the submodules [`elev`] and [`poll`].
+//! The submodule [`driver`] contains an asynchronous driver which reconnects if the elevatorserver restarts,
+//! and [`simulator`] contains an in-process replacement for the elevatorserver.
 //! 
 //! Additional functionality includes message handling for elevator events, 
 //! call button state management, and conversion utilities for call types.
@@ -10,11 +12,13 @@
 //! This module provides data structures and utilities for handling elevator 
 //! input/output operations. It includes:
 //! 
-//! - `ElevMsgType`: Enum representing different elevator events.
-//! - `ElevMessage`: Struct for wrapping elevator messages.
+//! - `ElevMessage`: Enum representing the different elevator events.
+//! - `ElevInputs`: Struct holding a snapshot of all elevator inputs.
 //! - `CallType`: Enum for representing call button types.
 //! - `CallButton`: Struct for representing call button presses, including 
 //!    floor, call type, and elevator ID.
+//! - `ElevatorIo`: Trait abstracting the elevator hardware driver. The TCP driver in [`elev`]
+//!    is one implementation, and mocks or other backends can implement it as well.
 //! 
 //! These components allow structured handling of elevator input events, ensuring 
 //! that different types of messages (such as button presses and sensor activations) 
@@ -23,42 +27,105 @@
 #[doc(hidden)]
 pub mod elev;
 pub mod poll;
+pub mod driver;
+pub mod simulator;
 
 use crate::print;
 use crate::config;
 
 use serde::{Serialize, Deserialize};
 use std::hash::{Hash, Hasher};
+use std::thread;
+use std::time::Duration;
 
 
-/// Represents different types of elevator messages.
+/// Represents an event from the elevator hardware.
+///
+/// The events are emitted by [poll::poll_inputs] when a debounced input changes.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ElevMsgType 
+pub enum ElevMessage 
 {
-    /// Call button press event.
-    CALLBTN,
-    /// Floor sensor event.
-    FLOORSENS,
-    /// Stop button press event.
-    STOPBTN,
-    /// Obstruction detected event.
-    OBSTRX,
+    /// A call button was pressed.
+    CallButton(CallButton),
+    /// The elevator arrived at a floor.
+    FloorSensor(u8),
+    /// The stop button changed state (`true` if pressed).
+    StopButton(bool),
+    /// The obstruction switch changed state (`true` if obstruction detected).
+    Obstruction(bool),
 }
 
-/// Represents a message related to elevator events.
-#[derive(Debug, Clone)]
-pub struct ElevMessage 
+/// A snapshot of all elevator inputs, read in one sweep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElevInputs 
 {
-    /// The type of elevator message.
-    pub msg_type: ElevMsgType,
-    /// Optional call button information, if applicable.
-    pub call_button: Option<CallButton>,
-    /// Optional floor sensor reading, indicating the current floor.
+    /// Call button states, indexed on floor and [CallType] (`UP`, `DOWN`, `INSIDE`).
+    pub call_buttons: Vec<[bool; 3]>,
+    /// The floor sensor, `None` if the elevator is between floors.
     pub floor_sensor: Option<u8>,
-    /// Optional stop button state (`true` if pressed).
-    pub stop_button: Option<bool>,
-    /// Optional obstruction status (`true` if obstruction detected).
-    pub obstruction: Option<bool>,
+    /// Whether the stop button is pressed.
+    pub stop_button: bool,
+    /// Whether the obstruction switch is active.
+    pub obstruction: bool,
+}
+
+impl ElevInputs 
+{
+    /// Builds all read requests of a sweep as one buffer, so they can be written to the elevatorserver at once.
+    ///
+    /// The replies come back in the same order, and are parsed by [ElevInputs::from_replies].
+    pub fn poll_request(
+        num_floors: u8
+    ) -> Vec<u8> 
+    {
+        let mut requests: Vec<u8> = Vec::with_capacity((num_floors as usize * 3 + 3) * 4);
+        for floor in 0..num_floors 
+        {
+            for call in 0..3 
+            {
+                requests.extend_from_slice(&[6, call, floor, 0]);
+            }
+        }
+        requests.extend_from_slice(&[7, 0, 0, 0]);
+        requests.extend_from_slice(&[8, 0, 0, 0]);
+        requests.extend_from_slice(&[9, 0, 0, 0]);
+        requests
+    }
+
+    /// Parses the replies to a [ElevInputs::poll_request].
+    ///
+    /// # Examples
+    /// ```
+    /// use elevatorpro::elevio::ElevInputs;
+    ///
+    /// let request = ElevInputs::poll_request(2);
+    /// let mut replies = vec![0u8; request.len()];
+    /// replies[4 * 4 + 1] = 1;                                 // floor 1, hall up pressed
+    /// replies[6 * 4..6 * 4 + 4].copy_from_slice(&[7, 1, 1, 0]); // at floor 1
+    ///
+    /// let inputs = ElevInputs::from_replies(&replies, 2);
+    /// assert_eq!(inputs.call_buttons, vec![[false; 3], [false, true, false]]);
+    /// assert_eq!(inputs.floor_sensor, Some(1));
+    /// ```
+    pub fn from_replies(
+        replies: &[u8], 
+        num_floors: u8
+    ) -> ElevInputs 
+    {
+        let replies: Vec<&[u8]> = replies.chunks_exact(4).collect();
+        let n = num_floors as usize * 3;
+
+        ElevInputs 
+        {
+            call_buttons: replies[..n]
+                .chunks_exact(3)
+                .map(|floor| [floor[0][1] != 0, floor[1][1] != 0, floor[2][1] != 0])
+                .collect(),
+            floor_sensor: (replies[n][1] != 0).then_some(replies[n][2]),
+            stop_button: replies[n + 1][1] != 0,
+            obstruction: replies[n + 2][1] != 0,
+        }
+    }
 }
 
 /// Represents the type of call for an elevator.
@@ -199,3 +266,117 @@ impl Hash for CallButton
     }
 }
 
+
+/// Abstraction of the elevator hardware driver.
+///
+/// The elevator logic (FSM, lights and pollers) only talks to the hardware through this trait,
+/// which lets mocks, recorders and alternative hardware backends be plugged in without changing the logic.
+/// [`elev::Elevator`] (the TCP driver speaking to the elevatorserver) is the default implementation.
+///
+/// The method signatures follow the elevatorserver protocol:
+/// - `dirn`: `0` = stop, `1` = up, `u8::MAX` = down (see [`crate::world_view::Dirn`])
+/// - `call`: `0` = hall up, `1` = hall down, `2` = cab (see [`CallType`])
+///
+/// Implementors are cloned freely and shared between tasks, so a clone should refer to the same hardware.
+///
+/// # Examples
+/// ```
+/// use elevatorpro::elevio::ElevatorIo;
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(Clone, Default)]
+/// struct Recorder { motor: Arc<Mutex<Vec<u8>>> }
+///
+/// impl ElevatorIo for Recorder {
+///     fn num_floors(&self) -> u8 { 4 }
+///     fn motor_direction(&self, dirn: u8) { self.motor.lock().unwrap().push(dirn); }
+///     fn call_button_light(&self, _floor: u8, _call: u8, _on: bool) {}
+///     fn floor_indicator(&self, _floor: u8) {}
+///     fn door_light(&self, _on: bool) {}
+///     fn stop_button_light(&self, _on: bool) {}
+///     fn call_button(&self, _floor: u8, _call: u8) -> bool { false }
+///     fn floor_sensor(&self) -> Option<u8> { Some(0) }
+///     fn stop_button(&self) -> bool { false }
+///     fn obstruction(&self) -> bool { false }
+/// }
+///
+/// let e = Recorder::default();
+/// e.motor_direction(1);
+/// assert_eq!(*e.motor.lock().unwrap(), vec![1]);
+/// ```
+pub trait ElevatorIo: Clone + Send + Sync + 'static
+{
+    /// Number of floors served by the elevator
+    fn num_floors(&self) -> u8;
+
+    /// Sets the motor direction
+    fn motor_direction(&self, dirn: u8);
+
+    /// Sets the call button light of `call` on `floor`
+    fn call_button_light(&self, floor: u8, call: u8, on: bool);
+
+    /// Sets the floor indicator
+    fn floor_indicator(&self, floor: u8);
+
+    /// Sets the door open light
+    fn door_light(&self, on: bool);
+
+    /// Sets the stop button light
+    fn stop_button_light(&self, on: bool);
+
+    /// Reads whether the call button `call` on `floor` is pressed
+    fn call_button(&self, floor: u8, call: u8) -> bool;
+
+    /// Reads the floor sensor, `None` if the elevator is between floors
+    fn floor_sensor(&self) -> Option<u8>;
+
+    /// Reads whether the stop button is pressed
+    fn stop_button(&self) -> bool;
+
+    /// Reads whether the obstruction switch is active
+    fn obstruction(&self) -> bool;
+
+    /// Reads all inputs in one sweep.  
+    /// The default implementation calls the single reads. Drivers should override it if they can read more efficiently.
+    fn read_inputs(&self) -> ElevInputs 
+    {
+        ElevInputs 
+        {
+            call_buttons: (0..self.num_floors())
+                .map(|floor| [self.call_button(floor, 0), self.call_button(floor, 1), self.call_button(floor, 2)])
+                .collect(),
+            floor_sensor: self.floor_sensor(),
+            stop_button: self.stop_button(),
+            obstruction: self.obstruction(),
+        }
+    }
+
+    /// Waits for a sweep of all inputs newer than sweep number `after`, and returns its number and the inputs.  
+    /// Returns `after` and the last sweep if no new sweep was made in time, e.g. while the driver is disconnected.  
+    /// The default implementation waits `period` and calls [ElevatorIo::read_inputs], so every call is a new sweep.
+    /// Drivers which sweep the hardware on their own should override it, so [poll::poll_inputs] never debounces the same sweep twice.
+    fn next_sweep(&self, after: u64, period: Duration) -> (u64, ElevInputs) 
+    {
+        thread::sleep(period);
+        (after.wrapping_add(1), self.read_inputs())
+    }
+
+    /// Whether the driver currently has a connection to the hardware.  
+    /// Drivers which can not detect a lost connection keep the default, `true`.
+    fn is_connected(&self) -> bool {true}
+}
+
+impl ElevatorIo for elev::Elevator
+{
+    fn num_floors(&self) -> u8 {self.num_floors}
+    fn motor_direction(&self, dirn: u8) {elev::Elevator::motor_direction(self, dirn)}
+    fn call_button_light(&self, floor: u8, call: u8, on: bool) {elev::Elevator::call_button_light(self, floor, call, on)}
+    fn floor_indicator(&self, floor: u8) {elev::Elevator::floor_indicator(self, floor)}
+    fn door_light(&self, on: bool) {elev::Elevator::door_light(self, on)}
+    fn stop_button_light(&self, on: bool) {elev::Elevator::stop_button_light(self, on)}
+    fn call_button(&self, floor: u8, call: u8) -> bool {elev::Elevator::call_button(self, floor, call)}
+    fn floor_sensor(&self) -> Option<u8> {elev::Elevator::floor_sensor(self)}
+    fn stop_button(&self) -> bool {elev::Elevator::stop_button(self)}
+    fn obstruction(&self) -> bool {elev::Elevator::obstruction(self)}
+    fn read_inputs(&self) -> ElevInputs {elev::Elevator::read_inputs(self)}
+}
@@ -1,69 +1,266 @@
 //! Listens for events from the elevator.
+//!
+//! All inputs are read in one sweep per period through [`ElevatorIo::next_sweep`], and every input is
+//! debounced before it is reported: a new value must be read in [`PollConfig::debounce_samples`]
+//! consecutive sweeps (or [`PollConfig::floor_samples`] for the floor sensor) before it is accepted.
+//! This filters out single-sweep glitches, such as a flickering floor sensor between two floors.
+//! Floor sensor readings outside of the elevator's floors are ignored.
+//!
+//! Changes are emitted as typed [`ElevMessage`] events:
+//! - [`ElevMessage::CallButton`] when a call button is pressed (not when it is released)
+//! - [`ElevMessage::FloorSensor`] when the elevator arrives at a new floor
+//! - [`ElevMessage::StopButton`] and [`ElevMessage::Obstruction`] on every change
+//!
+//! ## Cost
+//! A sweep writes the requests for all inputs at once and reads all the replies back, so it waits for one round trip
+//! to the elevatorserver, where reading the inputs one by one waits for one per input, `3 * floors + 3`:
+//! ```
+//! use elevatorpro::elevio::elev::Elevator;
+//! use elevatorpro::elevio::simulator::{ElevatorSimulator, SimConfig};
+//! use std::time::Instant;
+//!
+//! let num_floors = 20;
+//! let sim = ElevatorSimulator::start("127.0.0.1:0", SimConfig { num_floors, ..Default::default() }).unwrap();
+//! let e = Elevator::init(&sim.addr().to_string(), num_floors).unwrap();
+//!
+//! let start = Instant::now();
+//! for _ in 0..20
+//! {
+//!     e.read_inputs();
+//! }
+//! let sweep = start.elapsed();
+//!
+//! let start = Instant::now();
+//! for _ in 0..20
+//! {
+//!     for floor in 0..num_floors
+//!     {
+//!         (0..3).for_each(|call| {e.call_button(floor, call);});
+//!     }
+//!     e.floor_sensor();
+//!     e.stop_button();
+//!     e.obstruction();
+//! }
+//! let one_by_one = start.elapsed();
+//!
+//! // About twice as fast against the simulator on loopback, more over a real network
+//! assert!(sweep * 5 < one_by_one * 4, "sweeps: {:?}, one by one: {:?}", sweep, one_by_one);
+//! ```
 
+use crate::config;
+use crate::elevio::{CallButton, CallType, ElevatorIo, ElevMessage};
 
+use std::time;
+use tokio::sync::mpsc;
 
-use crate::network;
-use crate::elevio::{CallButton, CallType, elev};
 
-use crossbeam_channel as cbc;
-use std::thread;
-use std::time;
+/// Configuration of [poll_inputs], set at startup in [config::ELEV_POLL_CONFIG]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollConfig
+{
+    /// Time between two sweeps
+    pub period: time::Duration,
 
+    /// Number of consecutive equal sweeps needed before a button, stop or obstruction change is accepted
+    pub debounce_samples: u8,
 
+    /// Number of consecutive equal sweeps needed before a new floor sensor value is accepted
+    pub floor_samples: u8,
+}
 
-#[doc(hidden)]
-pub fn call_buttons(elev: elev::Elevator, ch: cbc::Sender<CallButton>, period: time::Duration) {
-    let mut prev = vec![[false; 3]; elev.num_floors.into()];
-    loop {
-        for f in 0..elev.num_floors {
-            for c in 0..3 {
-                let v = elev.call_button(f, c);
-                if v && prev[f as usize][c as usize] != v {
-                    ch.send(CallButton { floor: f, call_type: CallType::from(c), elev_id: network::read_self_id()}).unwrap();
-                }
-                prev[f as usize][c as usize] = v;
-            }
+impl Default for PollConfig
+{
+    fn default() -> Self
+    {
+        PollConfig
+        {
+            period: config::DEFAULT_ELEV_POLL,
+            debounce_samples: config::DEFAULT_ELEV_DEBOUNCE_SAMPLES,
+            floor_samples: config::DEFAULT_ELEV_FLOOR_SAMPLES,
         }
-        thread::sleep(period)
     }
 }
 
-#[doc(hidden)]
-pub fn floor_sensor(elev: elev::Elevator, ch: cbc::Sender<u8>, period: time::Duration) {
-    let mut prev = u8::MAX;
-    loop {
-        if let Some(f) = elev.floor_sensor() {
-            if f != prev {
-                ch.send(f).unwrap();
-                prev = f;
-            }
+
+/// An input which only changes value after the new value has been sampled `samples` times in a row
+#[derive(Debug, Clone, Copy)]
+struct Debounced<T>
+{
+    stable: T,
+    candidate: T,
+    count: u8,
+}
+
+impl<T: Copy + PartialEq> Debounced<T>
+{
+    fn new(
+        value: T
+    ) -> Self
+    {
+        Debounced { stable: value, candidate: value, count: 0 }
+    }
+
+    /// Feeds a new sample. Returns the new stable value if it changed.
+    fn update(
+        &mut self,
+        sample: T,
+        samples: u8
+    ) -> Option<T>
+    {
+        if sample == self.stable
+        {
+            self.count = 0;
+            return None;
         }
-        thread::sleep(period)
+
+        if sample != self.candidate
+        {
+            self.candidate = sample;
+            self.count = 0;
+        }
+        self.count += 1;
+
+        if self.count >= samples.max(1)
+        {
+            self.stable = sample;
+            self.count = 0;
+            return Some(sample);
+        }
+        None
     }
 }
 
-#[doc(hidden)]
-pub fn stop_button(elev: elev::Elevator, ch: cbc::Sender<bool>, period: time::Duration) {
-    let mut prev = false;
-    loop {
-        let v = elev.obstruction();
-        if prev != v {
-            ch.send(v).unwrap();
-            prev = v;
+
+/// The debounced state of all inputs, and the logic turning sweeps into [ElevMessage]s
+struct InputFilter
+{
+    call_buttons: Vec<[Debounced<bool>; 3]>,
+    floor_sensor: Debounced<Option<u8>>,
+    stop_button: Debounced<bool>,
+    obstruction: Debounced<bool>,
+    elev_id: u8,
+    config: PollConfig,
+}
+
+impl InputFilter
+{
+    fn new(
+        num_floors: u8,
+        elev_id: u8,
+        config: PollConfig
+    ) -> Self
+    {
+        InputFilter
+        {
+            call_buttons: vec![[Debounced::new(false); 3]; num_floors as usize],
+            floor_sensor: Debounced::new(None),
+            stop_button: Debounced::new(false),
+            obstruction: Debounced::new(false),
+            elev_id,
+            config,
         }
-        thread::sleep(period)
+    }
+
+    /// Feeds one sweep, and returns the events it caused
+    fn update(
+        &mut self,
+        inputs: &crate::elevio::ElevInputs
+    ) -> Vec<ElevMessage>
+    {
+        let mut events = Vec::new();
+        let samples = self.config.debounce_samples;
+
+        for (floor, (buttons, sampled)) in self.call_buttons.iter_mut().zip(inputs.call_buttons.iter()).enumerate()
+        {
+            for (call, button) in buttons.iter_mut().enumerate()
+            {
+                if button.update(sampled[call], samples) == Some(true)
+                {
+                    events.push(ElevMessage::CallButton(CallButton
+                    {
+                        floor: floor as u8,
+                        call_type: CallType::from(call as u8),
+                        elev_id: self.elev_id,
+                    }));
+                }
+            }
+        }
+
+        // Readings outside the shaft are glitches, and are treated like being between floors
+        let num_floors = self.call_buttons.len() as u8;
+        let floor = inputs.floor_sensor.filter(|f| *f < num_floors);
+        if let Some(Some(f)) = self.floor_sensor.update(floor, self.config.floor_samples)
+        {
+            events.push(ElevMessage::FloorSensor(f));
+        }
+
+        if let Some(stop) = self.stop_button.update(inputs.stop_button, samples)
+        {
+            events.push(ElevMessage::StopButton(stop));
+        }
+
+        if let Some(obstruction) = self.obstruction.update(inputs.obstruction, samples)
+        {
+            events.push(ElevMessage::Obstruction(obstruction));
+        }
+
+        events
     }
 }
 
-#[doc(hidden)]
-pub fn obstruction(elev: elev::Elevator, ch: cbc::Sender<bool>, period: time::Duration) {
-    let mut prev = false;
-    loop {
-        let v = elev.stop_button();
-        if prev != v {
-            ch.send(v).unwrap();
-            prev = v;
+
+/// Polls all inputs of the elevator, and sends debounced events on `ch`
+///
+/// ## Parameters
+/// `elev`: The elevator to poll
+/// `elev_id`: The ID of the elevator, put in the [CallButton]s
+/// `ch`: Channel the [ElevMessage]s are sent on
+/// `config`: Period and debounce configuration
+///
+/// ## Behavior
+/// The function is blocking, and should be run on its own thread. It returns when the receiver of `ch` is dropped.
+///
+/// ## Example
+/// ```
+/// use elevatorpro::elevio::{ElevatorIo, ElevMessage};
+/// use elevatorpro::elevio::elev::Elevator;
+/// use elevatorpro::elevio::poll::{poll_inputs, PollConfig};
+/// use elevatorpro::elevio::simulator::{ElevatorSimulator, SimConfig};
+/// use tokio::sync::mpsc;
+///
+/// let sim = ElevatorSimulator::start("127.0.0.1:0", SimConfig::default()).unwrap();
+/// let e = Elevator::init(&sim.addr().to_string(), 4).unwrap();
+/// let (tx, mut rx) = mpsc::channel(10);
+/// std::thread::spawn(move || poll_inputs(e, 0, tx, PollConfig::default()));
+///
+/// assert_eq!(rx.blocking_recv(), Some(ElevMessage::FloorSensor(0)));
+///
+/// sim.set_obstruction(true);
+/// assert_eq!(rx.blocking_recv(), Some(ElevMessage::Obstruction(true)));
+/// ```
+pub fn poll_inputs<E: ElevatorIo>(
+    elev: E,
+    elev_id: u8,
+    ch: mpsc::Sender<ElevMessage>,
+    config: PollConfig
+)
+{
+    let mut filter = InputFilter::new(elev.num_floors(), elev_id, config);
+    let mut sweep = 0;
+    loop
+    {
+        let (next, inputs) = elev.next_sweep(sweep, config.period);
+        if next == sweep
+        {
+            // No new sweep, e.g. while the driver is disconnected
+            if ch.is_closed() {return}
+            continue;
+        }
+        sweep = next;
+
+        for event in filter.update(&inputs)
+        {
+            if ch.blocking_send(event).is_err() {return}
         }
-        thread::sleep(period)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,406 @@
+//! ## In-process elevator simulator
+//!
+//! This module implements an elevator simulator speaking the same 4-byte TCP protocol as the
+//! `elevatorserver` used in Sanntidshallen. It lets the node run on a laptop or in CI without
+//! the real hardware, and without starting the server over `sshpass`.
+//!
+//! ## Protocol
+//! Every command is exactly 4 bytes. Commands 1-5 are writes, commands 6-9 are reads
+//! and are answered with 4 bytes.
+//!
+//! | Cmd | Request                      | Reply                        |
+//! |-----|------------------------------|------------------------------|
+//! | 1   | `[1, dirn, 0, 0]`            | –                            |
+//! | 2   | `[2, call, floor, on]`       | –                            |
+//! | 3   | `[3, floor, 0, 0]`           | –                            |
+//! | 4   | `[4, on, 0, 0]`              | –                            |
+//! | 5   | `[5, on, 0, 0]`              | –                            |
+//! | 6   | `[6, call, floor, 0]`        | `[6, pressed, 0, 0]`         |
+//! | 7   | `[7, 0, 0, 0]`               | `[7, at_floor, floor, 0]`    |
+//! | 8   | `[8, 0, 0, 0]`               | `[8, pressed, 0, 0]`         |
+//! | 9   | `[9, 0, 0, 0]`               | `[9, obstructed, 0, 0]`      |
+//!
+//! ## Car model
+//! The car position is tracked as a floating point floor number, and is moved by the
+//! motor direction with a configurable travel time between two floors. The floor sensor
+//! reports a floor while the car is within [`SimConfig::sensor_width`] of it.
+//! The car stops at the bottom and top floor instead of crashing into the shaft.
+//!
+//! ## Buttons
+//! Buttons are pressed through the [`ElevatorSimulator`] handle, and are held for
+//! [`SimConfig::button_hold`] so the pollers see them as a real button press.
+//! [`ElevatorSimulator::spawn_keyboard_controller`] lets a user press buttons from the terminal.
+//!
+//! ## Example
+//! ```
+//! use elevatorpro::elevio::elev::Elevator;
+//! use elevatorpro::elevio::simulator::{ElevatorSimulator, SimConfig};
+//! use std::time::Duration;
+//!
+//! let config = SimConfig { travel_time: Duration::from_millis(100), ..SimConfig::default() };
+//! let sim = ElevatorSimulator::start("127.0.0.1:0", config).unwrap();
+//! let e = Elevator::init(&sim.addr().to_string(), 4).unwrap();
+//!
+//! assert_eq!(e.floor_sensor(), Some(0));
+//!
+//! e.motor_direction(1);
+//! std::thread::sleep(Duration::from_millis(150));
+//! e.motor_direction(0);
+//! assert!(sim.position() > 1.0);
+//!
+//! sim.press_call_button(2, 0);
+//! assert!(e.call_button(2, 0));
+//! ```
+
+use crate::print;
+
+use std::io::{self, BufRead, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+/// Configuration of the simulated elevator
+#[derive(Debug, Clone)]
+pub struct SimConfig
+{
+    /// Number of floors in the simulated shaft
+    pub num_floors: u8,
+
+    /// Time the car uses between two neighbouring floors
+    pub travel_time: Duration,
+
+    /// How far from a floor (in floors) the floor sensor still detects it
+    pub sensor_width: f64,
+
+    /// How long a button press from the handle is held down
+    pub button_hold: Duration,
+
+    /// The position (in floors) the car starts at
+    pub start_position: f64,
+}
+
+impl Default for SimConfig
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            num_floors: 4,
+            travel_time: Duration::from_millis(2000),
+            sensor_width: 0.1,
+            button_hold: Duration::from_millis(200),
+            start_position: 0.0,
+        }
+    }
+}
+
+
+/// The internal state of the simulated elevator
+struct SimState
+{
+    config: SimConfig,
+    position: f64,
+    motor: i8,
+    last_update: Instant,
+    call_buttons: Vec<[Option<Instant>; 3]>,
+    call_lights: Vec<[bool; 3]>,
+    floor_indicator: u8,
+    door_light: bool,
+    stop_light: bool,
+    stop_button: bool,
+    obstruction: bool,
+}
+
+impl SimState
+{
+    fn new(
+        config: SimConfig
+    ) -> Self
+    {
+        let num_floors = config.num_floors as usize;
+        let position = config.start_position.clamp(0.0, config.num_floors.saturating_sub(1) as f64);
+        Self
+        {
+            config,
+            position,
+            motor: 0,
+            last_update: Instant::now(),
+            call_buttons: vec![[None; 3]; num_floors],
+            call_lights: vec![[false; 3]; num_floors],
+            floor_indicator: 0,
+            door_light: false,
+            stop_light: false,
+            stop_button: false,
+            obstruction: false,
+        }
+    }
+
+    /// Moves the car according to the motor direction and the time since last update
+    fn update(&mut self)
+    {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        let travel = self.config.travel_time.as_secs_f64().max(f64::EPSILON);
+        let top = self.config.num_floors.saturating_sub(1) as f64;
+        self.position = (self.position + self.motor as f64 * elapsed / travel).clamp(0.0, top);
+    }
+
+    fn floor_sensor(&mut self) -> Option<u8>
+    {
+        self.update();
+        let nearest = self.position.round();
+        ((self.position - nearest).abs() <= self.config.sensor_width).then_some(nearest as u8)
+    }
+
+    fn call_button(&self, floor: u8, call: u8) -> bool
+    {
+        self.call_buttons
+            .get(floor as usize)
+            .and_then(|buttons| buttons.get(call as usize))
+            .and_then(|pressed| *pressed)
+            .is_some_and(|at| at.elapsed() < self.config.button_hold)
+    }
+
+    /// Executes one 4-byte command, returning the reply if the command is a read
+    fn handle_command(&mut self, cmd: [u8; 4]) -> Option<[u8; 4]>
+    {
+        match cmd[0]
+        {
+            1 =>
+            {
+                self.update();
+                self.motor = match cmd[1]
+                {
+                    1 => 1,
+                    u8::MAX => -1,
+                    _ => 0,
+                };
+                None
+            }
+            2 =>
+            {
+                if let Some(light) = self.call_lights.get_mut(cmd[2] as usize).and_then(|l| l.get_mut(cmd[1] as usize))
+                {
+                    *light = cmd[3] != 0;
+                }
+                None
+            }
+            3 =>
+            {
+                self.floor_indicator = cmd[1];
+                None
+            }
+            4 =>
+            {
+                self.door_light = cmd[1] != 0;
+                None
+            }
+            5 =>
+            {
+                self.stop_light = cmd[1] != 0;
+                None
+            }
+            6 => Some([6, self.call_button(cmd[2], cmd[1]) as u8, 0, 0]),
+            7 => match self.floor_sensor()
+            {
+                Some(floor) => Some([7, 1, floor, 0]),
+                None => Some([7, 0, 0, 0]),
+            },
+            8 => Some([8, self.stop_button as u8, 0, 0]),
+            9 => Some([9, self.obstruction as u8, 0, 0]),
+            _ => None,
+        }
+    }
+}
+
+
+/// Handle to a running in-process elevator simulator
+///
+/// The handle is cheap to clone, and every clone controls the same simulated elevator.
+#[derive(Clone)]
+pub struct ElevatorSimulator
+{
+    state: Arc<Mutex<SimState>>,
+    addr: SocketAddr,
+}
+
+impl ElevatorSimulator
+{
+    /// Starts the simulator, listening for elevio connections on `addr`
+    ///
+    /// ## Parameters
+    /// `addr`: Address to listen on, e.g. [crate::config::LOCAL_ELEV_IP]. Port 0 picks a free port.
+    /// `config`: The [SimConfig] of the simulated elevator
+    ///
+    /// ## Returns
+    /// A handle to the simulator, or an error if the address could not be bound
+    ///
+    /// ## Behavior
+    /// Spawns a thread accepting connections, and one thread per connected client.
+    /// All clients share the same simulated elevator.
+    pub fn start(
+        addr: &str,
+        config: SimConfig
+    ) -> io::Result<ElevatorSimulator>
+    {
+        let listener = TcpListener::bind(addr)?;
+        let sim = ElevatorSimulator
+        {
+            state: Arc::new(Mutex::new(SimState::new(config))),
+            addr: listener.local_addr()?,
+        };
+
+        let state = sim.state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming()
+            {
+                match stream
+                {
+                    Ok(stream) =>
+                    {
+                        let state = state.clone();
+                        thread::spawn(move || serve_client(stream, state));
+                    }
+                    Err(e) => print::warn(format!("Simulator failed to accept connection: {}", e)),
+                }
+            }
+        });
+
+        Ok(sim)
+    }
+
+    /// Returns the address the simulator is listening on
+    pub fn addr(&self) -> SocketAddr
+    {
+        self.addr
+    }
+
+    /// Presses a call button (`call`: 0 = up, 1 = down, 2 = cab) on `floor`
+    pub fn press_call_button(&self, floor: u8, call: u8)
+    {
+        let mut state = self.state.lock().unwrap();
+        if let Some(button) = state.call_buttons.get_mut(floor as usize).and_then(|b| b.get_mut(call as usize))
+        {
+            *button = Some(Instant::now());
+        }
+    }
+
+    /// Sets the state of the obstruction switch
+    pub fn set_obstruction(&self, on: bool)
+    {
+        self.state.lock().unwrap().obstruction = on;
+    }
+
+    /// Sets the state of the stop button
+    pub fn set_stop_button(&self, pressed: bool)
+    {
+        self.state.lock().unwrap().stop_button = pressed;
+    }
+
+    /// Returns the current position of the car, in floors
+    pub fn position(&self) -> f64
+    {
+        let mut state = self.state.lock().unwrap();
+        state.update();
+        state.position
+    }
+
+    /// Returns the current motor direction as sent on the protocol (0 = stop, 1 = up, 255 = down)
+    pub fn motor_direction(&self) -> u8
+    {
+        self.state.lock().unwrap().motor as u8
+    }
+
+    /// Returns whether the call button light (`call`: 0 = up, 1 = down, 2 = cab) on `floor` is on
+    pub fn call_button_light(&self, floor: u8, call: u8) -> bool
+    {
+        let state = self.state.lock().unwrap();
+        state.call_lights
+            .get(floor as usize)
+            .and_then(|lights| lights.get(call as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Returns the floor currently shown on the floor indicator
+    pub fn floor_indicator(&self) -> u8
+    {
+        self.state.lock().unwrap().floor_indicator
+    }
+
+    /// Returns whether the door open light is on
+    pub fn door_light(&self) -> bool
+    {
+        self.state.lock().unwrap().door_light
+    }
+
+    /// Returns whether the stop button light is on
+    pub fn stop_button_light(&self) -> bool
+    {
+        self.state.lock().unwrap().stop_light
+    }
+
+    /// Lets the user operate the simulator from the terminal
+    ///
+    /// ## Behavior
+    /// Spawns a thread reading lines from stdin. Available commands:
+    /// - `u<floor>` / `d<floor>` / `c<floor>` &rarr; Presses hall up, hall down or cab button on `<floor>`
+    /// - `o` &rarr; Toggles the obstruction switch
+    /// - `s` &rarr; Toggles the stop button
+    pub fn spawn_keyboard_controller(&self)
+    {
+        let sim = self.clone();
+        print::info("Simulator keys: u<floor>, d<floor>, c<floor>, o (obstruction), s (stop)".to_string());
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines()
+            {
+                let Ok(line) = line else { break };
+                let line = line.trim();
+                let mut chars = line.chars();
+                let key = chars.next();
+                let floor = chars.as_str().parse::<u8>().ok();
+
+                match (key, floor)
+                {
+                    (Some('u'), Some(f)) => sim.press_call_button(f, 0),
+                    (Some('d'), Some(f)) => sim.press_call_button(f, 1),
+                    (Some('c'), Some(f)) => sim.press_call_button(f, 2),
+                    (Some('o'), None) =>
+                    {
+                        let obstruction = !sim.state.lock().unwrap().obstruction;
+                        sim.set_obstruction(obstruction);
+                    }
+                    (Some('s'), None) =>
+                    {
+                        let stop = !sim.state.lock().unwrap().stop_button;
+                        sim.set_stop_button(stop);
+                    }
+                    _ => print::warn(format!("Unknown simulator command: {}", line)),
+                }
+            }
+        });
+    }
+}
+
+
+/// Serves one connected elevio client until the connection is closed
+fn serve_client(
+    mut stream: TcpStream,
+    state: Arc<Mutex<SimState>>
+)
+{
+    let _ = stream.set_nodelay(true);
+    let mut cmd = [0u8; 4];
+    while stream.read_exact(&mut cmd).is_ok()
+    {
+        let reply = state.lock().unwrap().handle_command(cmd);
+        if let Some(reply) = reply
+        {
+            if stream.write_all(&reply).is_err() {break}
+        }
+    }
+}
@@ -10,18 +10,22 @@
 //!   verbosity and enable debug or backup modes.
 //! - **Terminal Command Execution**: Provides platform-specific commands for opening new 
 //!   terminal windows.
-//! - **Cost Function Build Execution**: Runs a build script for the hall request assigner 
-//!   cost function.
+//! - **Cost Function Build Execution**: Runs a build script for the external hall request assigner 
+//!   cost function, when it is used instead of the native assigner.
 //!
 //! ### Overview of Functions:
 //! - `initialize_worldview` – Creates an initial worldview and merges with the network if possible.
-//! - `parse_args` – Parses command-line arguments to configure logging settings and modes.
+//! - `parse_args` – Parses command-line arguments (or a config file) to configure logging settings, modes and the local elevator.
 //! - `get_terminal_command` – Returns the appropriate terminal command for different operating systems.
-//! - `build_cost_fn` – Executes a build script for the hall request assigner cost function.
+//! - `build_cost_fn` – Executes a build script for the external hall request assigner cost function.
 
 use crate::config; 
+use crate::elevator_logic::request::ServingPolicyKind;
+use crate::elevator_logic::timer::TimerConfig;
+use crate::manager::assigner::HallAssignerKind;
 use crate::ip_help_functions::ip2id;
 use crate::network;
+use crate::node::NodeContext;
 use crate::print; 
 use crate::world_view::{self, ElevatorContainer, WorldView};
 
@@ -29,7 +33,7 @@ use std::env;
 use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::net::UdpSocket; 
-use tokio::time::{sleep, timeout, Instant};
+use tokio::time::{timeout, Instant};
 use tokio::process::Command;
 use socket2::{Domain, Socket, Type};
 use local_ip_address::local_ip;
@@ -41,30 +45,35 @@ use local_ip_address::local_ip;
 /// ## Steps:
 /// 1. **Create an empty worldview and elevator container.**
 /// 2. **Add an initial placeholder task** to both the task queue and task status list.
-/// 3. **Retrieve the local machine's IP address** to determine its unique ID.
-/// 4. **Set the elevator ID and master ID** using the extracted IP-based identifier.
+/// 3. **Retrieve the local machine's IP address** to determine its unique ID, if the ID in `ctx` is not set already.
+/// 4. **Set the node's ID in `ctx`, the elevator ID and master ID** using the extracted IP-based identifier.
 /// 5. **Listen for UDP messages** for a brief period to detect other nodes on the network.
 /// 6. **If no nodes are found**, return the current worldview as is, with self id as the network master.
 /// 7. **If other elevators are detected**, merge their worldview with the local elevator's data.
 /// 8. **Check if the master ID should be updated** based on the smallest ID present.
 /// 9. **Return the serialized worldview**, ready to be used for network synchronization.
 ///
+/// ## Parameters:
+/// - `ctx`: The node being initialized. The number of floors and served floors of a new elevator are taken from its config.
+/// - `self_container`: The node's elevator container, if it is restarted with one
+///
 /// ## Returns:
 /// - A `Vec<u8>` containing the serialized worldview data.
 /// 
 /// ## Panics:
-/// - No internet connection on start-up will result in a panic!
+/// - No internet connection on start-up will result in a panic, unless the ID is already set!
 ///
 /// ## Example Usage:
 /// ```rust
-/// let worldview_data: Vec<u8> = initialize_worldview().await;
+/// let worldview_data: Vec<u8> = initialize_worldview(&ctx, None).await;
 /// let worldview: worldview::WorldView = worldview::deserialize(&worldview_data);
 /// ```
 pub async fn initialize_worldview(
+    ctx: &NodeContext,
     self_container : Option<&world_view::ElevatorContainer>
 ) -> WorldView 
 {
-    let mut worldview = WorldView::default();
+    let mut worldview = WorldView::new(ctx.config.num_floors);
     
     let elev_container: &mut ElevatorContainer = if let Some(container) = self_container 
     {
@@ -72,30 +81,34 @@ pub async fn initialize_worldview(
     } else 
     {
         // Opprett ein standard ElevatorContainer med ein initial placeholder-task
-        let container = ElevatorContainer::default();
+        let container = ElevatorContainer::new(ctx.config.num_floors, ctx.config.served_floors.as_deref());
         &mut container.clone()
     };
 
 
-    // Retrieve local IP address
-    let ip = match local_ip() 
+    // The ID is kept once it is set, also when the node comes back online, so several nodes can run in one process
+    if ctx.id() == config::ERROR_ID 
     {
-        Ok(ip) => ip,
-        Err(e) => 
+        // Retrieve local IP address
+        let ip = match local_ip() 
         {
-            print::err(format!("Failed to get local IP at startup: {}", e));
-            panic!();
-        }
-    };
+            Ok(ip) => ip,
+            Err(e) => 
+            {
+                print::err(format!("Failed to get local IP at startup: {}", e));
+                panic!();
+            }
+        };
 
-    // Extract self ID from IP address (last segment of IP)
-    network::set_self_id(ip2id(ip));
-    elev_container.elevator_id = network::read_self_id();
-    worldview.master_id = network::read_self_id();
+        // Extract self ID from IP address (last segment of IP)
+        ctx.set_id(ip2id(ip));
+    }
+    elev_container.elevator_id = ctx.id();
+    worldview.take_over(ctx.id());
     worldview.add_elev(elev_container.clone());
 
     // Listen for UDP messages for a short time to detect other elevators
-    let mut wv_from_udp = match check_for_udp().await 
+    let mut wv_from_udp = match check_for_udp(ctx).await 
     {
         Some(wv) => wv,
         None => 
@@ -110,14 +123,15 @@ pub async fn initialize_worldview(
     if let Some(saved_requests) = saved_cab_requests.get(&elev_container.elevator_id) 
     {
         elev_container.cab_requests = saved_requests.clone();
+        elev_container.cab_requests.resize(elev_container.num_floors as usize, false);
     }
     // Add your elevator to the worldview
     wv_from_udp.add_elev(elev_container.clone());
 
     // Set self as master if the current master has a higher ID
-    if wv_from_udp.master_id > network::read_self_id() 
+    if wv_from_udp.master_id > ctx.id() 
     {
-        wv_from_udp.master_id = network::read_self_id();
+        wv_from_udp.take_over(ctx.id());
     }
 
     // Serialize and return the updated worldview
@@ -138,6 +152,7 @@ pub async fn initialize_worldview(
 /// 4. **If a message is received**, attempt to decode it as a UTF-8 string.
 /// 5. **Filter out messages that do not contain the expected key**.
 /// 6. **Extract the relevant data** and convert it into a `Vec<u8>`.
+///    A delta is only usable with the master's last snapshot, so a snapshot is requested when one arrives first, see [network::wv_delta].
 /// 7. **Return the parsed data or an empty vector** if no valid message was received.
 ///
 /// ## Returns:
@@ -146,14 +161,16 @@ pub async fn initialize_worldview(
 ///
 /// ## Example Usage:
 /// ```rust
-/// let udp_data = check_for_udp().await;
+/// let udp_data = check_for_udp(&ctx).await;
 /// if !udp_data.is_empty() {
 ///     println!("Received worldview data: {:?}", udp_data);
 /// } else {
 ///     println!("No UDP message received within 1 second.");
 /// }
 /// ```
-async fn check_for_udp() -> Option<WorldView> 
+async fn check_for_udp(
+    ctx: &NodeContext
+) -> Option<WorldView> 
 {
     // Construct the UDP broadcast listening address
     let broadcast_listen_addr = format!("{}:{}", config::BC_LISTEN_ADDR, config::BROADCAST_PORT);
@@ -175,6 +192,8 @@ async fn check_for_udp() -> Option<WorldView>
     // Buffer for receiving UDP data
     let mut buf = [0; config::UDP_BUFFER];
     let mut read_wv: Option<WorldView>;
+    let mut decoder = network::wv_delta::DeltaDecoder::default();
+    let mut snapshot_requested = false;
     
 
     // Start the timer for 1-second listening duration
@@ -190,8 +209,18 @@ async fn check_for_udp() -> Option<WorldView>
         {
             Ok(Ok((len, _))) => 
             {
-                // Convert the received bytes into a string
-                read_wv = network::udp_broadcast::parse_message(&buf[..len]);
+                // Convert the received bytes into a worldview, asking for a snapshot if a delta comes first
+                read_wv = match network::udp_broadcast::parse_message(ctx, &buf[..len]).map(|msg| decoder.decode(msg)) 
+                {
+                    Some(Ok(wv)) => Some(wv),
+                    Some(Err(network::wv_delta::Undecoded::Gap { .. })) if !snapshot_requested => 
+                    {
+                        snapshot_requested = true;
+                        let _ = network::udp_broadcast::send_snapshot_request(ctx, &socket).await;
+                        None
+                    }
+                    _ => None,
+                };
             }
             Ok(Err(e)) => 
             {
@@ -230,7 +259,7 @@ async fn check_for_udp() -> Option<WorldView>
 
 /// ### Reads arguments from `cargo run`
 /// 
-/// Used to modify what is printed during runtime. Available options:
+/// Used to modify what is printed during runtime, and to configure the local elevator. Available options:
 /// 
 /// `print_wv::(true/false)` &rarr; Prints the worldview twice per second  
 /// `print_err::(true/false)` &rarr; Prints error messages  
@@ -238,13 +267,32 @@ async fn check_for_udp() -> Option<WorldView>
 /// `print_ok::(true/false)` &rarr; Prints OK messages  
 /// `print_info::(true/false)` &rarr; Prints informational messages  
 /// `print_else::(true/false)` &rarr; Prints other messages, including master, slave, and color messages  
+/// `sim::(true/false)` &rarr; Runs the local elevator against the in-process simulator instead of elevatorserver  
+/// `num_floors::(n)` &rarr; Sets the number of floors of the local elevator (default [config::DEFAULT_NUM_FLOORS])  
+/// `served_floors::(f,f,..)` &rarr; Sets which floors the local elevator serves, e.g. `served_floors::1,2,3` for a car skipping the basement (default all)  
+/// `serving_policy::(collective/clear_all/in_direction)` &rarr; Sets which requests are served when the local elevator stops at a floor (default collective), see [crate::elevator_logic::request::ServingPolicy]  
+/// `hall_assigner::(optimal/external/nearest/eta/round_robin)` &rarr; Sets how the manager assigns hall requests (default optimal), see [crate::manager::assigner]  
+/// `stability_margin::(ms)` &rarr; Only moves a hall request from an elevator making progress on it when another elevator is faster by the margin (default off), see [crate::manager::stability]  
+/// `starvation_time::(ms)` &rarr; Gives hall requests which have waited longer to the nearest elevator (default [config::DEFAULT_STARVATION_TIME]), see [crate::manager::starvation]  
+/// `assignment_trace::(path)` &rarr; Writes every hall assignment to a rotating trace file (default off), see [crate::manager::trace]  
+/// `network_key::(key)` &rarr; Sets the pre-shared key the network messages are authenticated with (default [config::DEFAULT_NETWORK_KEY]), see [crate::network::protocol]  
+/// `snapshot_period::(ms)` &rarr; Sets how often the master broadcasts its full worldview (default [config::DEFAULT_SNAPSHOT_PERIOD]), see [crate::network::wv_delta]  
+/// `poll_period::(ms)` &rarr; Sets the time between two polls of the elevator inputs (default [config::DEFAULT_ELEV_POLL]), see [crate::elevio::poll]  
+/// `debounce_samples::(n)` &rarr; Sets how many equal polls in a row are needed before a button, stop or obstruction change is accepted (default [config::DEFAULT_ELEV_DEBOUNCE_SAMPLES])  
+/// `floor_samples::(n)` &rarr; Sets how many equal polls in a row are needed before a new floor is accepted (default [config::DEFAULT_ELEV_FLOOR_SAMPLES])  
+/// `door_time::(ms)` &rarr; Sets how long the door stays open (default [config::DEFAULT_DOOR_TIME])  
+/// `cab_priority_time::(ms)` &rarr; Sets how long cab calls are prioritized after a stop (default [config::DEFAULT_CAB_PRIORITY_TIME])  
+/// `error_time::(ms)` &rarr; Sets how long the elevator can be without progress before it enters an error state (default [config::DEFAULT_ERROR_TIME])  
+/// `config_file::(path)` &rarr; Reads options from a file, one `key::value` per line. Empty lines and lines starting with `#` are ignored  
 /// `debug::` &rarr; Disables all prints except error messages  
 /// `help` &rarr; Displays all possible arguments without starting the program  
 /// 
-/// If no arguments are provided, all prints are enabled by default.
+/// If no arguments are provided, all prints are enabled by default.  
+/// The timer durations are validated with [crate::elevator_logic::timer::TimerConfig::validate] after all options are read. Invalid durations are replaced by the defaults.
+/// A warning is printed if no network key is given, since [config::DEFAULT_NETWORK_KEY] is public.
 /// 
 /// Secret options:  
-/// `backup` &rarr; Starts the program in backup-mode.
+/// `backup` &rarr; Starts the program in backup-mode. The other options are read as well, since the backup is started with the options of the main process.
 /// 
 pub fn parse_args() -> bool 
 {
@@ -253,34 +301,13 @@ pub fn parse_args() -> bool
     // Hvis det ikke finnes argumenter, returner false
     if args.len() <= 0 {return false}
 
+    let mut is_backup = false;
     for arg in &args[1..] 
     {
         let parts: Vec<&str> = arg.split("::").collect();
         if parts.len() == 2 
         {
-            let key = parts[0].to_lowercase();
-            let value = parts[1].to_lowercase();
-            let is_true = value == "true";
-
-           
-            match key.as_str() 
-            {
-                "print_wv" => *config::PRINT_WV_ON.lock().unwrap() = is_true,
-                "print_err" => *config::PRINT_ERR_ON.lock().unwrap() = is_true,
-                "print_warn" => *config::PRINT_WARN_ON.lock().unwrap() = is_true,
-                "print_ok" => *config::PRINT_OK_ON.lock().unwrap() = is_true,
-                "print_info" => *config::PRINT_INFO_ON.lock().unwrap() = is_true,
-                "print_else" => *config::PRINT_ELSE_ON.lock().unwrap() = is_true,
-                "debug" => { // Debug modus: Kun error-meldingar
-                    *config::PRINT_WV_ON.lock().unwrap() = false;
-                    *config::PRINT_WARN_ON.lock().unwrap() = false;
-                    *config::PRINT_OK_ON.lock().unwrap() = false;
-                    *config::PRINT_INFO_ON.lock().unwrap() = false;
-                    *config::PRINT_ELSE_ON.lock().unwrap() = false;
-                }
-                _ => {}
-            }
-            
+            apply_arg(parts[0], parts[1]);
         } else if arg.to_lowercase() == "help" 
         {
             println!("Tilgjengelige argument:");
@@ -290,17 +317,197 @@ pub fn parse_args() -> bool
             println!("  print_ok::true/false");
             println!("  print_info::true/false");
             println!("  print_else::true/false");
+            println!("  sim::true/false (køyrer heisen mot innebygd simulator)");
+            println!("  num_floors::<antal etasjar> (standard: {})", config::DEFAULT_NUM_FLOORS);
+            println!("  served_floors::<etasje>,<etasje>,.. (standard: alle etasjar)");
+            println!("  serving_policy::collective/clear_all/in_direction (standard: collective)");
+            println!("  hall_assigner::optimal/external/nearest/eta/round_robin (standard: optimal)");
+            println!("  stability_margin::<ms> (flytt berre hall-ordrar når ny heis er så mykje raskare, standard: av)");
+            println!("  starvation_time::<ms> (hall-ordrar som ventar lenger går til næraste heis, standard: {})", config::DEFAULT_STARVATION_TIME.as_millis());
+            println!("  assignment_trace::<sti> (skriv kvar hall-fordeling til ei roterande loggfil, standard: av)");
+            println!("  network_key::<nøkkel> (felles nøkkel for å autentisere nettverksmeldingar, må vere lik på alle nodar)");
+            println!("  snapshot_period::<ms> (tid mellom fulle worldview-sendingar frå master, standard: {})", config::DEFAULT_SNAPSHOT_PERIOD.as_millis());
+            println!("  poll_period::<ms> (tid mellom kvar avlesing av heisen, standard: {})", config::DEFAULT_ELEV_POLL.as_millis());
+            println!("  debounce_samples::<n> (like avlesingar på rad før ein knapp vert godteken, standard: {})", config::DEFAULT_ELEV_DEBOUNCE_SAMPLES);
+            println!("  floor_samples::<n> (like avlesingar på rad før ein ny etasje vert godteken, standard: {})", config::DEFAULT_ELEV_FLOOR_SAMPLES);
+            println!("  door_time::<ms> (standard: {})", config::DEFAULT_DOOR_TIME.as_millis());
+            println!("  cab_priority_time::<ms> (standard: {})", config::DEFAULT_CAB_PRIORITY_TIME.as_millis());
+            println!("  error_time::<ms> (standard: {})", config::DEFAULT_ERROR_TIME.as_millis());
+            println!("  config_file::<sti> (les argument frå fil, eitt key::value per linje)");
             println!("  debug (kun error-meldingar vises)");
             println!("  backup (starter backup-prosess)");
             std::process::exit(0);
         } else if arg.to_lowercase() == "backup" 
         {
-            return true;
+            is_backup = true;
         }
     }
 
-    // If no arguments was backup, return false
-    false
+    let mut timers = config::ELEV_TIMERS.lock().unwrap();
+    if let Err(e) = timers.validate() 
+    {
+        print::warn(format!("Invalid timer config: {}, using default timers", e));
+        *timers = TimerConfig::default();
+    }
+
+    if *config::NETWORK_KEY.lock().unwrap() == config::DEFAULT_NETWORK_KEY.as_bytes() 
+    {
+        print::err("No network key is set! The network messages are authenticated with the default key, which is public, so anyone can send messages the nodes act on. Set a key with network_key::<key>".to_string());
+    }
+
+    is_backup
+}
+
+/// Applies one `key::value` option, see [parse_args]
+pub fn apply_arg(
+    key: &str, 
+    value: &str
+) 
+{
+    let key = key.trim().to_lowercase();
+    if key == "config_file" 
+    {
+        read_config_file(value.trim());
+        return;
+    }
+    // The network key keeps its case
+    if key == "network_key" 
+    {
+        let network_key = value.trim();
+        if network_key.is_empty() 
+        {
+            print::warn("Empty network key, using the default".to_string());
+            return;
+        }
+        *config::NETWORK_KEY.lock().unwrap() = network_key.as_bytes().to_vec();
+        return;
+    }
+    // Paths keep their case
+    if key == "assignment_trace" 
+    {
+        let path = value.trim();
+        *config::ASSIGNMENT_TRACE.lock().unwrap() = (!path.is_empty()).then(|| path.into());
+        return;
+    }
+
+    let value = value.trim().to_lowercase();
+    let is_true = value == "true";
+
+    match key.as_str() 
+    {
+        "print_wv" => *config::PRINT_WV_ON.lock().unwrap() = is_true,
+        "print_err" => *config::PRINT_ERR_ON.lock().unwrap() = is_true,
+        "print_warn" => *config::PRINT_WARN_ON.lock().unwrap() = is_true,
+        "print_ok" => *config::PRINT_OK_ON.lock().unwrap() = is_true,
+        "print_info" => *config::PRINT_INFO_ON.lock().unwrap() = is_true,
+        "print_else" => *config::PRINT_ELSE_ON.lock().unwrap() = is_true,
+        "sim" => *config::ELEV_SIMULATOR_ON.lock().unwrap() = is_true,
+        "served_floors" => match value.split(',').map(|f| f.trim().parse::<u8>()).collect::<Result<Vec<u8>, _>>() 
+        {
+            Ok(floors) if !floors.is_empty() => *config::SERVED_FLOORS.lock().unwrap() = Some(floors),
+            _ => print::warn(format!("Invalid served floors: {}, serving all floors", value)),
+        },
+        "num_floors" => match value.parse::<u8>() 
+        {
+            Ok(n) if n >= config::MIN_NUM_FLOORS => *config::NUM_FLOORS.lock().unwrap() = n,
+            _ => print::warn(format!("Invalid number of floors: {}, using {}", value, config::DEFAULT_NUM_FLOORS)),
+        },
+        "serving_policy" => match ServingPolicyKind::from_name(&value) 
+        {
+            Some(policy) => *config::SERVING_POLICY.lock().unwrap() = policy,
+            None => print::warn(format!("Invalid serving policy: {}, using collective", value)),
+        },
+        "hall_assigner" => match HallAssignerKind::from_name(&value) 
+        {
+            Some(assigner) => *config::HALL_ASSIGNER.lock().unwrap() = assigner,
+            None => print::warn(format!("Invalid hall assigner: {}, using optimal", value)),
+        },
+        "stability_margin" => match value.parse::<u64>() 
+        {
+            Ok(ms) => *config::ASSIGNMENT_STABILITY_MARGIN.lock().unwrap() = Some(Duration::from_millis(ms)),
+            Err(_) => print::warn(format!("Invalid stability margin: {}, expected milliseconds", value)),
+        },
+        "starvation_time" => match value.parse::<u64>() 
+        {
+            Ok(ms) if ms > 0 => *config::STARVATION_TIME.lock().unwrap() = Duration::from_millis(ms),
+            _ => print::warn(format!("Invalid starvation time: {}, expected milliseconds", value)),
+        },
+        "snapshot_period" => match value.parse::<u64>() 
+        {
+            Ok(ms) if ms > 0 => *config::SNAPSHOT_PERIOD.lock().unwrap() = Duration::from_millis(ms),
+            _ => print::warn(format!("Invalid snapshot period: {}, expected milliseconds", value)),
+        },
+        "poll_period" => match value.parse::<u64>() 
+        {
+            Ok(ms) if ms > 0 => config::ELEV_POLL_CONFIG.lock().unwrap().period = Duration::from_millis(ms),
+            _ => print::warn(format!("Invalid poll period: {}, expected milliseconds", value)),
+        },
+        "debounce_samples" | "floor_samples" => match value.parse::<u8>() 
+        {
+            Ok(n) if n > 0 => 
+            {
+                let mut poll_config = config::ELEV_POLL_CONFIG.lock().unwrap();
+                match key.as_str() 
+                {
+                    "debounce_samples" => poll_config.debounce_samples = n,
+                    _ => poll_config.floor_samples = n,
+                }
+            }
+            _ => print::warn(format!("Invalid {}: {}, expected a number of polls", key, value)),
+        },
+        "door_time" | "cab_priority_time" | "error_time" => match value.parse::<u64>() 
+        {
+            Ok(ms) => 
+            {
+                let mut timers = config::ELEV_TIMERS.lock().unwrap();
+                let duration = Duration::from_millis(ms);
+                match key.as_str() 
+                {
+                    "door_time" => timers.door = duration,
+                    "cab_priority_time" => timers.cab_priority = duration,
+                    _ => timers.error = duration,
+                }
+            }
+            Err(_) => print::warn(format!("Invalid {}: {}, expected milliseconds", key, value)),
+        },
+        "debug" => { // Debug modus: Kun error-meldingar
+            *config::PRINT_WV_ON.lock().unwrap() = false;
+            *config::PRINT_WARN_ON.lock().unwrap() = false;
+            *config::PRINT_OK_ON.lock().unwrap() = false;
+            *config::PRINT_INFO_ON.lock().unwrap() = false;
+            *config::PRINT_ELSE_ON.lock().unwrap() = false;
+        }
+        _ => {}
+    }
+}
+
+/// Reads options from a config file, one `key::value` per line
+/// 
+/// Empty lines and lines starting with `#` are ignored. Lines which are not on the `key::value` form are warned about and skipped.
+fn read_config_file(
+    path: &str
+) 
+{
+    let content = match std::fs::read_to_string(path) 
+    {
+        Ok(content) => content,
+        Err(e) => 
+        {
+            print::warn(format!("Could not read config file {}: {}", path, e));
+            return;
+        }
+    };
+
+    for line in content.lines().map(str::trim) 
+    {
+        if line.is_empty() || line.starts_with('#') {continue}
+
+        match line.split_once("::") 
+        {
+            Some((key, value)) if key.trim().to_lowercase() != "config_file" => apply_arg(key, value),
+            _ => print::warn(format!("Ignoring line in config file {}: {}", path, line)),
+        }
+    }
 }
 
 
@@ -343,6 +550,8 @@ pub fn get_terminal_command() -> (String, Vec<String>)
 /// error output, suggest manual build steps for debugging, and then terminate the process
 /// by panicking.
 ///
+/// Only needed with `hall_assigner::external`, see [config::HALL_ASSIGNER].
+///
 /// # Panics
 /// Panics if the script fails to execute or if it exits with a non-zero status code.
 /// This ensures the caller is alerted early to any build issues.
@@ -368,7 +577,6 @@ pub async fn build_cost_fn()
         eprintln!("2. bash build.sh");
         panic!("Failed to build hall_request_assigner.");
     }
-    sleep(Duration::from_millis(2000)).await;
 }
 
 
@@ -9,10 +9,12 @@
 //! - **init**: System initialization.
 //! - **manager**: Allocates available tasks to the connected nodes
 //! - **network**: Communication between nodes via UDP and TCP, and updateing the worldview locally via mpsc-channels and watch-channels.
+//! - **node**: The ID, network status and configuration of a node, passed to every task
 //! - **world_view**: The local WorldView
 //! - **elevio**: Interface for elevator I/O.
 //! - **elevator_logic**: Task execution and reading from the local elevator.
 //! - **backup**: Creating, monitoring and running a backup, ready to overtake if the main program crashes
+//! - **bench**: Synthetic traffic, and a benchmark comparing the hall assignment strategies on a simulated fleet
 
 pub mod config;
 
@@ -26,6 +28,8 @@ pub mod manager;
 
 pub mod network;
 
+pub mod node;
+
 pub mod world_view;
 
 pub mod elevio;
@@ -34,3 +38,4 @@ pub mod elevator_logic;
 
 pub mod backup;
 
+pub mod bench;
@@ -23,6 +23,8 @@ use elevatorpro::network::{self, local_network, udp_broadcast};
 use elevatorpro::backup;
 use elevatorpro::elevator_logic;
 use elevatorpro::manager;
+use elevatorpro::manager::assigner::HallAssignerKind;
+use elevatorpro::node::{NodeConfig, NodeContext};
 use elevatorpro::world_view;
 use elevatorpro::init;
 use elevatorpro::print;
@@ -35,17 +37,20 @@ async fn main()
 {
     // Determine if this instance should run in backup mode (via CLI argument)
     let is_backup = init::parse_args();
+    let ctx = NodeContext::new(NodeConfig::from_config());
     
     let mut self_container: Option<world_view::ElevatorContainer> = None;
     if is_backup 
     {
         print::info(format!("Starting backup-process..."));
-        self_container = backup::run_as_backup().await;
+        self_container = backup::run_as_backup(&ctx).await;
     }    
     
-    // Initializes the cost function used for task delegation between elevators.
-    // This step is necessary before any scheduling decisions are made.
-    init::build_cost_fn().await;
+    // The external cost function is only built when it is the selected hall assigner
+    if ctx.config.hall_assigner == HallAssignerKind::External 
+    {
+        init::build_cost_fn().await;
+    }
     print::info("Starting master process...".to_string());
 
     
@@ -58,7 +63,7 @@ async fn main()
     // In all other cases, the active network of elevators maintains and synchronizes your state.
     // If you crash and restart normally, your previous tasks will be remembered and reassigned by others.
 
-    let mut worldview = init::initialize_worldview(self_container.as_ref()).await;
+    let mut worldview = init::initialize_worldview(&ctx, self_container.as_ref()).await;
     print::worldview(&worldview, Some(network::ConnectionStatus::new()));
     
     
@@ -109,10 +114,11 @@ async fn main()
         // This allows the system to detect network failures and trigger operation mode
         // when network conditions change.
 
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Starting to monitor internet".to_string());
-            network::watch_ethernet(wv_watch_rx, network_watch_tx, new_wv_after_offline_tx).await;
+            network::watch_ethernet(ctx, wv_watch_rx, network_watch_tx, new_wv_after_offline_tx).await;
         });
     }
     /* END ----------- Task to watch over the internet connection ---------------------- */
@@ -122,25 +128,28 @@ async fn main()
     /* START ----------- Critical tasks tasks ----------- */
     {
         // Continously updates the local worldview
+        let ctx = ctx.clone();
         tokio::spawn(async move {
             print::info("Starting to update worldview".to_string());
-            local_network::update_wv_watch(mpsc_rxs, wv_watch_tx, &mut worldview).await;
+            local_network::update_wv_watch(ctx, mpsc_rxs, wv_watch_tx, &mut worldview).await;
         });
     }
     {
         // Task handling the elevator
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Starting to run local elevator".to_string());
-            elevator_logic::run_local_elevator(wv_watch_rx, elevator_states_tx).await;
+            elevator_logic::run_local_elevator(ctx, wv_watch_rx, elevator_states_tx).await;
         });
     }
     {
         // Starting the task manager, responsible for delegating tasks
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Staring task manager".to_string());
-            manager::start_manager(wv_watch_rx, delegated_tasks_tx).await;
+            manager::start_manager(ctx, wv_watch_rx, delegated_tasks_tx).await;
         });
     }
     /* END ----------- Critical tasks tasks ----------- */
@@ -175,19 +184,21 @@ async fn main()
         // Listens for incoming UDP broadcasts from other nodes containing their `WorldView`.
         //
         // Received data is forwarded to the worldview updater via mpsc.
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Starting to listen for UDP-broadcast".to_string());
-            let _ = udp_broadcast::start_udp_listener(wv_watch_rx, udp_wv_tx).await;
+            let _ = udp_broadcast::start_udp_listener(ctx, wv_watch_rx, udp_wv_tx).await;
         });
     }
 
     {
         // If master, Periodically broadcasts the `WorldView` to all over UDP.
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Starting UDP-broadcaster".to_string());
-            let _ = udp_broadcast::start_udp_broadcaster(wv_watch_rx).await;
+            let _ = udp_broadcast::start_udp_broadcaster(ctx, wv_watch_rx).await;
         });
     }
 
@@ -200,10 +211,12 @@ async fn main()
         // - Detecting dropped slaves/master
         // - Reacting to master loss
         // - Handling connection failover
+        let ctx = ctx.clone();
         let wv_watch_rx = wv_watch_rx.clone();
         tokio::spawn(async move {
             print::info("Starting UDP direct network".to_string());
             network::udp_direct::start_direct_udp_broadcast(
+                ctx,
                 wv_watch_rx,
                 container_tx,
                 packetloss_rx,
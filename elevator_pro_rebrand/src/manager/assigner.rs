@@ -0,0 +1,297 @@
+//! Hall request assignment strategies
+//!
+//! The manager gives the hall requests to the elevators through a [HallAssigner]. Which one is used is selected per node with
+//! `hall_assigner::<strategy>` at startup, see [crate::config::HALL_ASSIGNER], so strategies can be compared without recompiling.
+//!
+//! # Strategies
+//! - [Optimal]: The native port of the hall request assigner, see [super::optimal_hall_requests]. This is the default.
+//! - [External]: The hall request assigner D program under project resources.
+//! - [NearestCar]: Each request goes to the elevator closest to its floor.
+//! - [EtaSimulation]: Each request goes to the elevator which would serve it first, see [super::eta].
+//! - [RoundRobin]: The requests are dealt out to the elevators in turn.
+//!
+//! Every strategy only gives requests to elevators which [can take hall requests](crate::world_view::ElevatorContainer::can_take_hall_requests).
+//! An empty map means the assignment failed.
+//!
+//! # Example
+//! ```
+//! use elevatorpro::manager::assigner::{HallAssigner, HallAssignerKind};
+//! use elevatorpro::world_view::WorldView;
+//!
+//! let mut wv = WorldView::with_elevators(4, &[(1, 0), (2, 3)]);
+//! wv.hall_request = vec![[true, false], [false, false], [false, false], [false, true]];
+//!
+//! for name in ["optimal", "nearest", "eta"]
+//! {
+//!     let tasks = HallAssignerKind::from_name(name).unwrap().assign(&wv);
+//!     assert_eq!(tasks[&1], vec![[true, false], [false, false], [false, false], [false, false]], "{}", name);
+//!     assert_eq!(tasks[&2], vec![[false, false], [false, false], [false, false], [false, true]], "{}", name);
+//! }
+//!
+//! // Round robin does not care where the elevators are
+//! let tasks = HallAssignerKind::RoundRobin.assign(&wv);
+//! assert_eq!(tasks[&1][0], [true, false]);
+//! assert_eq!(tasks[&2][3], [false, true]);
+//! ```
+
+use crate::print;
+use crate::world_view::{ElevatorContainer, WorldView};
+
+use super::eta;
+use super::json_serial;
+use super::optimal_hall_requests::{self, AssignerConfig};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+
+/// A strategy for assigning the hall requests in the worldview to the elevators
+pub trait HallAssigner
+{
+    /// Assigns the hall requests in `wv`
+    ///
+    /// ## Returns
+    /// The hall requests given to each elevator which can take hall requests, `[up, down]` per floor.
+    /// An empty map if the assignment failed.
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>;
+}
+
+/// The native port of the hall request assigner, see [optimal_hall_requests::assign_from_wv]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Optimal;
+
+/// The hall request assigner D program, run with `sudo`. It is built at startup when selected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct External;
+
+/// Gives each hall request to the elevator closest to its floor, ties going to the lowest ID
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearestCar;
+
+/// Gives the hall requests, one at a time, to the elevator with the lowest [estimated time of arrival](eta::time_to_serve),
+/// counting the requests it has already been given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EtaSimulation;
+
+/// Deals the hall requests out to the elevators in turn, from the bottom floor and up, ordered on ID
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl HallAssigner for Optimal
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        optimal_hall_requests::assign_from_wv(wv)
+    }
+}
+
+impl HallAssigner for External
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        let Some(json_str) = json_serial::create_hall_request_json(wv) else
+        {
+            print::err("create_hall_request_json returned None.".to_string());
+            return HashMap::new();
+        };
+
+        let json_cost_str = json_serial::run_cost_algorithm(json_str);
+        if json_cost_str.trim().is_empty()
+        {
+            print::err("run_cost_algorithm returned an empty string".to_string());
+            return HashMap::new();
+        }
+
+        serde_json::from_str(&json_cost_str).unwrap_or_else(|e| {
+            print::err(format!("Failed to parse JSON from cost algorithm: {}", e));
+            HashMap::new()
+        })
+    }
+}
+
+impl HallAssigner for NearestCar
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        let elevators = positioned_elevators(wv);
+        let mut tasks = empty_tasks(&elevators, wv.hall_request.len());
+
+        for (floor, call) in active_hall_requests(wv)
+        {
+            let nearest = elevators.iter()
+                .min_by_key(|elev| ((elev.last_floor_sensor as i32 - floor as i32).abs(), elev.elevator_id));
+            if let Some(elev) = nearest
+            {
+                tasks.get_mut(&elev.elevator_id).unwrap()[floor][call] = true;
+            }
+        }
+        tasks
+    }
+}
+
+impl HallAssigner for EtaSimulation
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        let config = AssignerConfig::default();
+        let num_floors = wv.hall_request.len();
+
+        // The elevators are simulated with only the requests given to them here
+        let mut elevators: Vec<ElevatorContainer> = positioned_elevators(wv).into_iter()
+            .cloned()
+            .map(|mut elev| {
+                elev.tasks = vec![[false; 2]; num_floors];
+                elev.cab_requests.resize(num_floors, false);
+                elev
+            })
+            .collect();
+
+        for (floor, call) in active_hall_requests(wv)
+        {
+            let fastest = elevators.iter_mut()
+                .map(|elev| (eta::time_to_serve(elev, floor, call, &config).unwrap_or(Duration::MAX), elev))
+                .min_by_key(|(eta, elev)| (*eta, elev.elevator_id));
+            if let Some((_, elev)) = fastest
+            {
+                elev.tasks[floor][call] = true;
+            }
+        }
+
+        elevators.into_iter().map(|elev| (elev.elevator_id, elev.tasks)).collect()
+    }
+}
+
+impl HallAssigner for RoundRobin
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        let mut ids: Vec<u8> = wv.elevator_containers.iter()
+            .filter(|elev| elev.can_take_hall_requests())
+            .map(|elev| elev.elevator_id)
+            .collect();
+        ids.sort();
+
+        let mut tasks: HashMap<u8, Vec<[bool; 2]>> = ids.iter()
+            .map(|id| (*id, vec![[false; 2]; wv.hall_request.len()]))
+            .collect();
+        if ids.is_empty() {return tasks}
+
+        for (i, (floor, call)) in active_hall_requests(wv).enumerate()
+        {
+            tasks.get_mut(&ids[i % ids.len()]).unwrap()[floor][call] = true;
+        }
+        tasks
+    }
+}
+
+
+/// Selects which [HallAssigner] the manager uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HallAssignerKind
+{
+    /// See [Optimal]
+    #[default]
+    Optimal,
+    /// See [External]
+    External,
+    /// See [NearestCar]
+    NearestCar,
+    /// See [EtaSimulation]
+    EtaSimulation,
+    /// See [RoundRobin]
+    RoundRobin,
+}
+
+impl HallAssignerKind
+{
+    /// Parses a strategy name as given on the command line: `optimal`, `external`, `nearest`, `eta` or `round_robin`
+    pub fn from_name(
+        name: &str
+    ) -> Option<Self>
+    {
+        match name
+        {
+            "optimal" => Some(HallAssignerKind::Optimal),
+            "external" => Some(HallAssignerKind::External),
+            "nearest" => Some(HallAssignerKind::NearestCar),
+            "eta" => Some(HallAssignerKind::EtaSimulation),
+            "round_robin" => Some(HallAssignerKind::RoundRobin),
+            _ => None,
+        }
+    }
+
+    fn assigner(
+        &self
+    ) -> &'static dyn HallAssigner
+    {
+        match self
+        {
+            HallAssignerKind::Optimal => &Optimal,
+            HallAssignerKind::External => &External,
+            HallAssignerKind::NearestCar => &NearestCar,
+            HallAssignerKind::EtaSimulation => &EtaSimulation,
+            HallAssignerKind::RoundRobin => &RoundRobin,
+        }
+    }
+}
+
+impl HallAssigner for HallAssignerKind
+{
+    fn assign(
+        &self,
+        wv: &WorldView
+    ) -> HashMap<u8, Vec<[bool; 2]>>
+    {
+        self.assigner().assign(wv)
+    }
+}
+
+
+/// The elevators which can take hall requests and are at a known floor
+fn positioned_elevators(
+    wv: &WorldView
+) -> Vec<&ElevatorContainer>
+{
+    wv.elevator_containers.iter()
+        .filter(|elev| elev.can_take_hall_requests() && (elev.last_floor_sensor as usize) < wv.hall_request.len())
+        .collect()
+}
+
+/// An empty task list for each of the elevators
+fn empty_tasks(
+    elevators: &[&ElevatorContainer],
+    num_floors: usize
+) -> HashMap<u8, Vec<[bool; 2]>>
+{
+    elevators.iter()
+        .map(|elev| (elev.elevator_id, vec![[false; 2]; num_floors]))
+        .collect()
+}
+
+/// The active hall requests as `(floor, call)`, from the bottom floor and up
+fn active_hall_requests(
+    wv: &WorldView
+) -> impl Iterator<Item = (usize, usize)> + '_
+{
+    wv.hall_request.iter()
+        .enumerate()
+        .flat_map(|(floor, calls)| (0..2).filter(move |call| calls[*call]).map(move |call| (floor, call)))
+}
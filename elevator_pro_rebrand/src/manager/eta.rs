@@ -0,0 +1,246 @@
+//! Estimated time of arrival for hall requests
+//!
+//! The elevator is simulated forward from its current state, with the collective [ServingPolicy] the FSM uses by default,
+//! until it has nothing more to do. The time at which each hall task is cleared is its estimated time of arrival.
+//!
+//! The door and travel durations are the ones in [AssignerConfig], the same as [super::optimal_hall_requests] uses.
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use elevatorpro::manager::eta;
+//! use elevatorpro::manager::optimal_hall_requests::AssignerConfig;
+//! use elevatorpro::world_view::{Dirn, ElevatorBehaviour, ElevatorContainer};
+//!
+//! let mut elevator = ElevatorContainer::default();
+//! elevator.last_floor_sensor = 0;
+//! elevator.behaviour = ElevatorBehaviour::Idle;
+//! elevator.dirn = Dirn::Stop;
+//!
+//! // Three floors to travel, then the door opens
+//! let eta = eta::time_to_serve(&elevator, 3, 1, &AssignerConfig::default());
+//! assert_eq!(eta, Some(Duration::from_millis(3 * 2500)));
+//!
+//! // A cab request on the way adds a stop
+//! elevator.cab_requests[1] = true;
+//! let eta = eta::time_to_serve(&elevator, 3, 1, &AssignerConfig::default());
+//! assert_eq!(eta, Some(Duration::from_millis(3 * 2500 + 3000)));
+//!
+//! // An elevator without a known floor can not be estimated
+//! elevator.last_floor_sensor = 255;
+//! assert_eq!(eta::time_to_serve(&elevator, 3, 1, &AssignerConfig::default()), None);
+//! ```
+
+use crate::elevator_logic::request::{ServingPolicy, ServingPolicyKind};
+use crate::world_view::{Dirn, ElevatorBehaviour, ElevatorContainer, HallCallEta, WorldView};
+
+use super::optimal_hall_requests::AssignerConfig;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+
+/// Estimates when each of the elevator's hall tasks is served
+///
+/// ## Parameters
+/// `elevator`: The elevator, with its tasks and cab requests
+/// `config`: Door and travel durations
+///
+/// ## Returns
+/// The time until each hall task is served, `[up, down]` per floor, from now.
+/// `None` for floors without a task, and for every floor if the elevator's floor is unknown.
+///
+/// # Notes
+/// The simulation is bounded, so a task the elevator would never serve is also `None`.
+pub fn hall_service_times(
+    elevator: &ElevatorContainer,
+    config: &AssignerConfig
+) -> Vec<[Option<Duration>; 2]>
+{
+    let num_floors = elevator.tasks.len();
+    let mut times = vec![[None; 2]; num_floors];
+
+    let mut e = elevator.clone();
+    e.cab_requests.resize(num_floors, false);
+    if e.last_floor_sensor as usize >= num_floors {return times}
+
+    let policy = ServingPolicyKind::Collective;
+    let mut t = Duration::ZERO;
+    let mut moving = false;
+
+    match e.behaviour
+    {
+        ElevatorBehaviour::Moving if e.dirn != Dirn::Stop =>
+        {
+            if !step_floor(&mut e) {return times}
+            t += config.travel_duration / 2;
+            moving = true;
+        }
+        ElevatorBehaviour::DoorOpen =>
+        {
+            clear(&policy, &mut e, t, &mut times);
+            t += config.door_open_duration / 2;
+        }
+        _ => {}
+    }
+
+    // Every floor can be passed a few times before the elevator runs out of things to do
+    for _ in 0..4 * (num_floors + 1)
+    {
+        if moving
+        {
+            if !policy.should_stop(&e)
+            {
+                if !step_floor(&mut e) {break}
+                t += config.travel_duration;
+                continue;
+            }
+            clear(&policy, &mut e, t, &mut times);
+            t += config.door_open_duration;
+            moving = false;
+        }
+
+        let pair = policy.choose_direction(&e);
+        e.dirn = pair.dirn;
+        match pair.behaviour
+        {
+            ElevatorBehaviour::DoorOpen =>
+            {
+                clear(&policy, &mut e, t, &mut times);
+                t += config.door_open_duration;
+            }
+            ElevatorBehaviour::Moving =>
+            {
+                if !step_floor(&mut e) {break}
+                t += config.travel_duration;
+                moving = true;
+            }
+            _ => break,
+        }
+    }
+
+    times
+}
+
+/// Estimates when the elevator would serve a hall request, if it was given the request in addition to its tasks
+///
+/// ## Parameters
+/// `elevator`: The elevator, with its tasks and cab requests
+/// `floor`: The floor of the hall request
+/// `call`: 0 for up, 1 for down
+/// `config`: Door and travel durations
+///
+/// ## Returns
+/// The time until the request is served, or `None` if it can not be estimated
+pub fn time_to_serve(
+    elevator: &ElevatorContainer,
+    floor: usize,
+    call: usize,
+    config: &AssignerConfig
+) -> Option<Duration>
+{
+    if floor >= elevator.tasks.len() || call > 1 {return None}
+
+    let mut e = elevator.clone();
+    e.tasks[floor][call] = true;
+    hall_service_times(&e, config)[floor][call]
+}
+
+/// Estimates when each hall request in the worldview is served by the elevator it is assigned to
+///
+/// ## Parameters
+/// `wv`: The worldview, with the hall requests and the elevators' states
+/// `tasks`: The hall requests assigned to each elevator
+/// `config`: Door and travel durations
+/// `now_ms`: The time of the assignment, in milliseconds since the Unix epoch, see [crate::world_view::unix_millis]
+///
+/// ## Returns
+/// The assigned elevator and when it is expected to arrive, `[up, down]` per floor of the hall requests.
+/// `None` for requests which are not active, not assigned, or can not be estimated.
+///
+/// ## Example
+/// ```
+/// use std::collections::HashMap;
+/// use elevatorpro::manager::eta;
+/// use elevatorpro::manager::optimal_hall_requests::AssignerConfig;
+/// use elevatorpro::world_view::{ElevatorContainer, WorldView};
+///
+/// let mut wv = WorldView::default();
+/// wv.hall_request[2] = [true, false];
+/// let mut elev = ElevatorContainer::default();
+/// elev.elevator_id = 12;
+/// elev.last_floor_sensor = 0;
+/// wv.add_elev(elev);
+///
+/// let tasks = HashMap::from([(12, vec![[false, false], [false, false], [true, false], [false, false]])]);
+/// let etas = eta::assigned_hall_etas(&wv, &tasks, &AssignerConfig::default(), 1_000_000);
+///
+/// let floor_2_up = etas[2][0].unwrap();
+/// assert_eq!(floor_2_up.elevator_id, 12);
+/// assert_eq!(floor_2_up.arrival_ms, 1_000_000 + 2 * 2500);
+/// assert_eq!(etas[2][1], None);
+/// ```
+pub fn assigned_hall_etas(
+    wv: &WorldView,
+    tasks: &HashMap<u8, Vec<[bool; 2]>>,
+    config: &AssignerConfig,
+    now_ms: u64
+) -> Vec<[Option<HallCallEta>; 2]>
+{
+    let mut etas = vec![[None; 2]; wv.hall_request.len()];
+
+    for elev in wv.elevator_containers.iter()
+    {
+        let Some(elev_tasks) = tasks.get(&elev.elevator_id) else {continue};
+        let mut e = elev.clone();
+        e.tasks = elev_tasks.clone();
+        e.tasks.resize(wv.hall_request.len(), [false; 2]);
+
+        let times = hall_service_times(&e, config);
+        for (floor, calls) in wv.hall_request.iter().enumerate()
+        {
+            for call in (0..2).filter(|call| calls[*call] && e.tasks[floor][*call])
+            {
+                if let Some(eta) = times[floor][call]
+                {
+                    let arrival_ms = now_ms + eta.as_millis() as u64;
+                    etas[floor][call] = Some(HallCallEta { elevator_id: elev.elevator_id, arrival_ms });
+                }
+            }
+        }
+    }
+    etas
+}
+
+
+/// Moves the simulated elevator one floor in its direction. Returns false if that would leave the shaft.
+fn step_floor(
+    e: &mut ElevatorContainer
+) -> bool
+{
+    let next = e.last_floor_sensor as i32 + e.dirn as i32;
+    if next < 0 || next as usize >= e.tasks.len() {return false}
+    e.last_floor_sensor = next as u8;
+    true
+}
+
+/// Clears the requests at the current floor, and records the hall tasks which were cleared at time `t`
+fn clear(
+    policy: &ServingPolicyKind,
+    e: &mut ElevatorContainer,
+    t: Duration,
+    times: &mut [[Option<Duration>; 2]]
+)
+{
+    let floor = e.last_floor_sensor as usize;
+    let before = e.tasks[floor];
+    policy.clear_at_current_floor(e);
+
+    for call in 0..2
+    {
+        if before[call] && !e.tasks[floor][call] && times[floor][call].is_none()
+        {
+            times[floor][call] = Some(t);
+        }
+    }
+}
@@ -6,10 +6,8 @@ use crate::world_view::{ElevatorBehaviour, WorldView};
 
 use std::collections::HashMap; 
 use std::env;
-use std::fs::File;
-use std::io::Write;
 use serde::{Serialize, Deserialize};
-use tokio::process::Command;
+use std::process::Command;
 
 
 
@@ -32,7 +30,9 @@ struct HallRequests
 }
 
 /// This function executes the cost algorithm, and returns the output
-pub async fn run_cost_algorithm(
+///
+/// Blocks until the algorithm has finished, so it must not be called directly from an async task.
+pub fn run_cost_algorithm(
     json_str: String
 ) -> String 
 {
@@ -49,14 +49,13 @@ pub async fn run_cost_algorithm(
         .arg("--input")
         .arg(json_str)
         .output()
-        .await
         .expect("Failed to start algorithm");
 
     String::from_utf8_lossy(&output.stdout).into_owned()
 }
 
 /// This function creates the input to the cost function algorithm based on the worldview
-pub async fn create_hall_request_json(
+pub fn create_hall_request_json(
     wv: &WorldView
 ) -> Option<String> 
 {
@@ -64,7 +63,7 @@ pub async fn create_hall_request_json(
     for elev in wv.elevator_containers.iter() 
     {
         let key = elev.elevator_id.to_string();
-        if elev.behaviour != ElevatorBehaviour::TravelError && elev.behaviour != ElevatorBehaviour::ObstructionError && elev.behaviour != ElevatorBehaviour::CosmicError 
+        if elev.can_take_hall_requests() 
         {
             states.insert(
                 key,
@@ -91,7 +90,12 @@ pub async fn create_hall_request_json(
                     },
 
                     direction: format!("{:?}", elev.dirn.clone()).to_lowercase(),
-                    cabRequests: elev.cab_requests.clone(),
+                    // The cost function requires every cab request vector to be as long as the hall requests
+                    cabRequests: {
+                        let mut cab_requests = elev.cab_requests.clone();
+                        cab_requests.resize(wv.hall_request.len(), false);
+                        cab_requests
+                    },
                 },
             );
         }
@@ -105,10 +109,6 @@ pub async fn create_hall_request_json(
         states,
     };
 
-    let s = serde_json::to_string_pretty(&request).expect("Failed to serialize");
-    
-    let mut file = File::create("hall_request.json").expect("Failed to create file");
-    file.write_all(s.as_bytes()).expect("Failed to write to file");
-    Some(s)
+    serde_json::to_string_pretty(&request).ok()
 }
 
@@ -1,106 +1,404 @@
 //! ## Manager Module
 //! 
 //! This module is responsible for allocating tasks on the network.
-//! It executes the hall assigner script give under project resources.
+//! Hall requests are assigned by a [HallAssigner](assigner::HallAssigner), selected with `hall_assigner::<strategy>` at startup.
+//! The default is [optimal_hall_requests], a native port of the hall assigner given under project resources.
+//!
+//! Elevators may serve different floors. The hall requests are therefore split into groups
+//! by which elevators can reach them, and the hall assigner is run once per group with only
+//! those elevators. This way, a hall request is never given to an elevator which can not reach its floor.
 
+pub mod assigner;
+pub mod eta;
 mod json_serial;
+pub mod optimal_hall_requests;
+pub mod stability;
+pub mod starvation;
+pub mod task_timeout;
+pub mod trace;
 
 
 use crate::config;
-use crate::world_view::{self, WorldView};
+use crate::manager::assigner::{HallAssigner, HallAssignerKind};
+use crate::manager::optimal_hall_requests::AssignerConfig;
+use crate::manager::task_timeout::TaskTracker;
+use crate::manager::trace::TraceWriter;
+use crate::node::{NodeConfig, NodeContext};
+use crate::world_view::{self, Dirn, ElevatorBehaviour, HallCallEta, WorldView};
 use crate::print;
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, watch}; 
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 
 
+/// The strategies tried, in order, when the selected hall assigner fails
+pub const FALLBACK_ASSIGNERS: [HallAssignerKind; 2] = [HallAssignerKind::NearestCar, HallAssignerKind::RoundRobin];
+
+/// The hall requests given to each elevator by the manager
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TaskAssignment 
+{
+    /// The hall requests given to each elevator, `[up, down]` per floor
+    pub tasks: HashMap<u8, Vec<[bool; 2]>>,
+
+    /// Whether any of the hall requests were assigned by a fallback, see [FALLBACK_ASSIGNERS]
+    pub degraded: bool,
+
+    /// The assigned elevator and estimated time of arrival for each hall request, see [eta::assigned_hall_etas]
+    pub eta: Vec<[Option<HallCallEta>; 2]>,
+
+    /// The elevators which have timed out on a task since the last assignment, once per task, see [task_timeout]
+    pub timed_out: Vec<u8>,
+}
 
 /// Main task for managing elevator coordination.
 /// 
-/// Continuously listens for updates in the global world view (`wv_watch_rx`).
+/// Waits for updates in the global world view (`wv_watch_rx`).
 /// If the current node is the designated master, it computes and distributes tasks
 /// to all known elevators using a cost-based assignment algorithm.
-/// If the node is not the master, it waits for a defined slave timeout before checking again.
 /// 
 /// Behavior:
-/// - Master nodes actively calculate and delegate hall requests.
-/// - Slave nodes remain idle and periodically check for changes in master status.
+/// - The tasks are only recomputed when the [AssignmentInput] changes, not on every worldview change.
+/// - Bursts of changes are coalesced: after a change, the manager waits [config::MANAGER_DEBOUNCE] and uses the newest worldview.
+/// - The manager also wakes up when a hall request starts starving, see [starvation].
+/// - Tasks the elevators make no progress on are tracked, and given to other elevators when they time out, see [task_timeout].
+/// - Slave nodes remain idle, and recompute as soon as they become master.
 /// 
 /// Parameters:
+/// - `ctx`: The node, which only assigns tasks while it is master, with the hall assigner and its options in [NodeContext::config].
 /// - `wv_watch_rx`: A watch channel providing updates to the shared world view state.
 /// - `delegated_tasks_tx`: A channel used to send the delegated hall tasks to other modules.
 pub async fn start_manager(
-    wv_watch_rx: watch::Receiver<WorldView>, 
-    delegated_tasks_tx: mpsc::Sender<HashMap<u8, Vec<[bool; 2]>>>
+    ctx: Arc<NodeContext>,
+    mut wv_watch_rx: watch::Receiver<WorldView>, 
+    delegated_tasks_tx: mpsc::Sender<TaskAssignment>
 ) 
 {
-    let mut wv = world_view::get_wv(wv_watch_rx.clone());
+    let mut last_input: Option<AssignmentInput> = None;
+    let mut tracker = TaskTracker::default();
+    let mut trace = TraceWriter::default();
+    let mut timed_out: Vec<u8> = Vec::new();
 
     loop 
     {
-        // Update local copy of the world view
-        if world_view::update_wv(wv_watch_rx.clone(), &mut wv).await 
-        {
-            // Check if this node is the master
-            if world_view::is_master(&wv) 
+        let input = {
+            let wv = wv_watch_rx.borrow_and_update();
+            if world_view::is_master(&wv, ctx.id()) 
             {
-                // Calculate and send out delegated hall requests
-                let _ = delegated_tasks_tx.send(get_elev_tasks(&wv).await).await;
-            } else 
+                // A task timing out does not change the input, so it forces a recomputation
+                let new_timeouts = tracker.update(&wv, Instant::now(), config::TASK_TIMEOUT);
+                for id in new_timeouts.iter() 
+                {
+                    print::warn(format!("Elevator {} made no progress on a task for {:?}, reassigning it", id, config::TASK_TIMEOUT));
+                    last_input = None;
+                }
+                timed_out.extend(new_timeouts);
+                Some(AssignmentInput::from_wv(&wv, ctx.config.starvation_time))
+            }
+            else 
             {
-                // If not master, wait before checking again
-                sleep(config::SLAVE_TIMEOUT).await;
+                tracker = TaskTracker::default();
+                None
             }
+        };
+
+        match input 
+        {
+            // Not master: forget the last input, so the tasks are recomputed on takeover
+            None => last_input = None,
+            Some(input) if last_input.as_ref() != Some(&input) => 
+            {
+                // Let a burst of changes settle before recomputing
+                sleep(config::MANAGER_DEBOUNCE).await;
+                let wv = wv_watch_rx.borrow_and_update().clone();
+
+                if world_view::is_master(&wv, ctx.id()) 
+                {
+                    last_input = Some(AssignmentInput::from_wv(&wv, ctx.config.starvation_time));
+                    let mut assignment = get_elev_tasks(&wv, &tracker, &ctx.config, &mut trace).await;
+                    assignment.timed_out = std::mem::take(&mut timed_out);
+                    let _ = delegated_tasks_tx.send(assignment).await;
+                }
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        // Wait for a change, for the next hall request to start starving, or for the next task to time out
+        let next_starving = starvation::time_until_next_starving(
+            &wv_watch_rx.borrow(), 
+            world_view::unix_millis(), 
+            ctx.config.starvation_time
+        );
+        let next_timeout = tracker.time_until_next_timeout(Instant::now(), config::TASK_TIMEOUT);
+        let wake = [next_starving, next_timeout].into_iter().flatten().min();
+        tokio::select! 
+        {
+            changed = wv_watch_rx.changed() => if changed.is_err() {return},
+            _ = sleep(wake.unwrap_or(Duration::MAX)) => {},
         }
+    }
+}
+
+/// The parts of the worldview the hall assignment depends on
+///
+/// The manager only recomputes the tasks when this changes, so changes like an obstruction toggle or a new unsent
+/// hall request do not cause a new assignment.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use elevatorpro::manager::AssignmentInput;
+/// use elevatorpro::world_view::{ElevatorContainer, WorldView};
+///
+/// let starvation_time = Duration::from_secs(60);
+/// let mut wv = WorldView::default();
+/// let mut elev = ElevatorContainer::default();
+/// elev.elevator_id = 1;
+/// elev.last_floor_sensor = 0;
+/// wv.add_elev(elev);
+/// let input = AssignmentInput::from_wv(&wv, starvation_time);
+///
+/// wv.elevator_containers[0].obstruction = true;
+/// wv.elevator_containers[0].unsent_hall_request[2] = [true, false];
+/// assert_eq!(AssignmentInput::from_wv(&wv, starvation_time), input);
+///
+/// wv.hall_request[2] = [true, false];
+/// assert_ne!(AssignmentInput::from_wv(&wv, starvation_time), input);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssignmentInput 
+{
+    /// The hall requests of the worldview
+    pub hall_request: Vec<[bool; 2]>,
+
+    /// The elevators which can take hall requests, in worldview order
+    pub elevators: Vec<AssignableElevator>,
+
+    /// The hall requests which have waited longer than the starvation time, see [starvation::starving_hall_requests]
+    pub starving: Vec<[bool; 2]>,
+}
+
+/// The parts of an elevator the hall assignment depends on, see [AssignmentInput]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssignableElevator 
+{
+    /// The ID of the elevator
+    pub elevator_id: u8,
+
+    /// The floor of the elevator
+    pub last_floor_sensor: u8,
+
+    /// The direction of the elevator
+    pub dirn: Dirn,
 
-        // Polling delay to limit update frequency
-        sleep(config::POLL_PERIOD).await;
+    /// The behaviour of the elevator
+    pub behaviour: ElevatorBehaviour,
+
+    /// The cab requests of the elevator
+    pub cab_requests: Vec<bool>,
+
+    /// Which floors the elevator serves
+    pub served_floors: Vec<bool>,
+}
+
+impl AssignmentInput 
+{
+    /// Extracts the assignment input from the worldview, with the hall requests which have waited longer than `starvation_time` as starving
+    pub fn from_wv(
+        wv: &WorldView,
+        starvation_time: Duration
+    ) -> Self 
+    {
+        AssignmentInput 
+        {
+            hall_request: wv.hall_request.clone(),
+            elevators: wv.elevator_containers.iter()
+                .filter(|elev| elev.can_take_hall_requests())
+                .map(|elev| AssignableElevator 
+                {
+                    elevator_id: elev.elevator_id,
+                    last_floor_sensor: elev.last_floor_sensor,
+                    dirn: elev.dirn,
+                    behaviour: elev.behaviour,
+                    cab_requests: elev.cab_requests.clone(),
+                    served_floors: (0..elev.num_floors as usize).map(|floor| elev.serves_floor(floor)).collect(),
+                })
+                .collect(),
+            starving: starvation::starving_hall_requests(wv, world_view::unix_millis(), starvation_time),
+        }
     }
 }
 
 /// Generates a set of hall requests assigned to each elevator based on cost minimization.
 ///
-/// This function first serializes the global world view to JSON, 
-/// then passes the data to an external cost algorithm module which calculates the most optimal
-/// assignment of hall calls to elevators. If successful, it returns a map of elevator IDs
-/// to their respective assigned requests.
+/// The hall requests are split into groups by [partition_hall_requests], and each group is passed
+/// to the hall assigner, which calculates the most optimal assignment of hall calls to elevators.
+/// If successful, it returns a map of elevator IDs to their respective assigned requests.
 ///
 /// Behavior:
-/// - If the cost algorithm returns a valid JSON string, it is parsed and returned as a HashMap.
-/// - If the cost algorithm fails or returns an empty string, an error is logged and an empty map is returned.
-/// - If JSON parsing fails, an error is also logged and an empty map is returned.
+/// - If the hall assigner fails for a group, the group is assigned by [FALLBACK_ASSIGNERS], and the assignment is marked as degraded.
+/// - If the fallbacks also fail, an error is logged and the group's hall requests are left unassigned.
+/// - If a stability margin is set, hall requests are kept by elevators making progress on them, see [stability::keep_stable_assignments].
+/// - Starving hall requests are given to the nearest elevator, see [starvation::escalate].
+/// - Tasks an elevator has timed out on are given to the nearest other elevator, see [TaskTracker::reassign_stalled].
+/// - The estimated time of arrival of each hall request is computed for the final assignment.
+/// - The assignment is written to the assignment trace, if it is enabled, see [TraceWriter].
+/// - The master status is not checked here, so the function can also be run against a simulated fleet, see [crate::bench].
 ///
 /// Parameters:
-/// - `wv`: The serialized global world view as a byte vector.
+/// - `wv`: The global world view.
+/// - `tracker`: The tasks the elevators are making progress on.
+/// - `config`: The hall assigner, stability margin, starvation time and assignment trace of the node.
+/// - `trace`: Writes the assignment to the node's assignment trace.
 ///
 /// Returns:
-/// - A `HashMap` where each key is an elevator ID (`u8`), and each value is a list of `[bool; 2]` 
+/// - A [TaskAssignment] where each key is an elevator ID (`u8`), and each value is a list of `[bool; 2]` 
 ///   arrays indicating hall call assignments (up/down).
-async fn get_elev_tasks(
-    wv: &WorldView
-) -> HashMap<u8, Vec<[bool; 2]>> 
+pub async fn get_elev_tasks(
+    wv: &WorldView, 
+    tracker: &TaskTracker,
+    config: &NodeConfig,
+    trace: &mut TraceWriter
+) -> TaskAssignment 
 {
-    let json_str = json_serial::create_hall_request_json(wv).await;
+    // Every elevator which can take hall requests gets a task list, also when there are no hall requests
+    let mut tasks: HashMap<u8, Vec<[bool; 2]>> = wv.elevator_containers.iter()
+        .filter(|elev| elev.can_take_hall_requests())
+        .map(|elev| (elev.elevator_id, vec![[false; 2]; wv.hall_request.len()]))
+        .collect();
 
-    if let Some(str) = json_str 
+    let mut degraded = false;
+
+    for group_wv in partition_hall_requests(wv) 
     {
-        let json_cost_str = json_serial::run_cost_algorithm(str.clone()).await;
+        let (group_tasks, group_degraded) = get_group_tasks(&group_wv, config.hall_assigner).await;
+        degraded |= group_degraded;
 
-        if json_cost_str.trim().is_empty() 
+        for (id, group_task) in group_tasks 
         {
-            print::err(format!(
-                "run_cost_algorithm returned an empty string"
-            ));
-            return HashMap::new();
+            if let Some(task) = tasks.get_mut(&id) 
+            {
+                for (floor, [up, down]) in task.iter_mut().zip(group_task.iter()) 
+                {
+                    floor[0] |= up;
+                    floor[1] |= down;
+                }
+            }
         }
+    }
 
-        return serde_json::from_str(&json_cost_str).unwrap_or_else(|e| {
-            print::err(format!("Failed to parse JSON from cost algorithm: {}", e));
-            HashMap::new()
-        });
+    if let Some(margin) = config.stability_margin 
+    {
+        stability::keep_stable_assignments(wv, &mut tasks, margin);
     }
 
-    print::err("create_hall_request_json returned None.".to_string());
-    HashMap::new()
+    let starving = starvation::starving_hall_requests(wv, world_view::unix_millis(), config.starvation_time);
+    starvation::escalate(wv, &mut tasks, &starving);
+    tracker.reassign_stalled(wv, &mut tasks);
+
+    let eta = eta::assigned_hall_etas(wv, &tasks, &AssignerConfig::default(), world_view::unix_millis());
+    let assignment = TaskAssignment { tasks, degraded, eta, timed_out: Vec::new() };
+    trace.record(wv, &assignment, config);
+    assignment
+}
+
+/// Splits the hall requests into groups of floors served by the same set of elevators.
+///
+/// Returns one worldview per group, containing only the group's hall requests and the elevators
+/// which can take them. Hall requests no elevator can take are left out, and a warning is printed.
+fn partition_hall_requests(
+    wv: &WorldView
+) -> Vec<WorldView> 
+{
+    let mut groups: BTreeMap<Vec<u8>, Vec<usize>> = BTreeMap::new();
+    for (floor, calls) in wv.hall_request.iter().enumerate() 
+    {
+        if !calls[0] && !calls[1] {continue}
+
+        let capable: Vec<u8> = wv.elevator_containers.iter()
+            .filter(|elev| elev.can_take_hall_requests() && elev.serves_floor(floor))
+            .map(|elev| elev.elevator_id)
+            .collect();
+
+        if capable.is_empty() 
+        {
+            print::warn(format!("No available elevator serves floor {}", floor));
+            continue;
+        }
+        groups.entry(capable).or_default().push(floor);
+    }
+
+    groups.into_iter().map(|(ids, floors)| {
+        let mut group_wv = wv.clone();
+        group_wv.elevator_containers.retain(|elev| ids.contains(&elev.elevator_id));
+        group_wv.set_num_elev(group_wv.elevator_containers.len() as u8);
+        for (floor, calls) in group_wv.hall_request.iter_mut().enumerate() 
+        {
+            if !floors.contains(&floor) 
+            {
+                *calls = [false; 2];
+            }
+        }
+        group_wv
+    }).collect()
+}
+
+/// Runs the selected hall assigner on one group from [partition_hall_requests]
+///
+/// Returns the group's tasks, and whether they came from a fallback in [FALLBACK_ASSIGNERS]
+async fn get_group_tasks(
+    wv: &WorldView,
+    assigner: HallAssignerKind
+) -> (HashMap<u8, Vec<[bool; 2]>>, bool) 
+{
+    let tasks = run_assigner(assigner, wv).await;
+    if assigns_all_hall_requests(wv, &tasks) {return (tasks, false)}
+
+    print::warn(format!("Hall assigner {:?} failed, using fallback", assigner));
+    for fallback in FALLBACK_ASSIGNERS.iter().filter(|fallback| **fallback != assigner) 
+    {
+        let tasks = fallback.assign(wv);
+        if assigns_all_hall_requests(wv, &tasks) {return (tasks, true)}
+    }
+
+    print::err("All hall assigners failed, hall requests are left unassigned".to_string());
+    (HashMap::new(), true)
+}
+
+/// Runs a hall assigner on `wv`
+///
+/// The [External](assigner::External) assigner waits for a subprocess, so it is run on the blocking thread pool
+/// instead of stalling the worker thread the manager runs on.
+async fn run_assigner(
+    assigner: HallAssignerKind, 
+    wv: &WorldView
+) -> HashMap<u8, Vec<[bool; 2]>> 
+{
+    if assigner != HallAssignerKind::External {return assigner.assign(wv)}
+
+    let wv = wv.clone();
+    tokio::task::spawn_blocking(move || assigner.assign(&wv)).await.unwrap_or_else(|e| {
+        print::err(format!("Hall assigner {:?} panicked: {}", assigner, e));
+        HashMap::new()
+    })
+}
+
+/// Checks that every hall request in `wv` is given to an elevator in `wv` which can take hall requests
+fn assigns_all_hall_requests(
+    wv: &WorldView, 
+    tasks: &HashMap<u8, Vec<[bool; 2]>>
+) -> bool 
+{
+    let assigned = |floor: usize, call: usize| wv.elevator_containers.iter()
+        .filter(|elev| elev.can_take_hall_requests())
+        .filter_map(|elev| tasks.get(&elev.elevator_id))
+        .any(|task| task.get(floor).is_some_and(|t| t[call]));
+
+    !tasks.is_empty() && wv.hall_request.iter().enumerate()
+        .all(|(floor, calls)| (0..2).all(|call| !calls[call] || assigned(floor, call)))
 }
@@ -0,0 +1,657 @@
+//! Native hall request assigner
+//!
+//! This is a port of `optimal_hall_requests.d` from the hall request assigner in
+//! `libs/Project_resources/cost_fns/hall_request_assigner`. It gives the same assignments as the D program,
+//! but runs in-process, directly on the [WorldView], without JSON, `sudo` or a D compiler.
+//!
+//! ## How it works
+//! Every elevator is simulated one move at a time, always moving the elevator which has used the least time so far.
+//! A hall request is assigned to the first elevator which would clear it. Ties are broken on the elevator ID
+//! (compared as text, like the D program does).
+//!
+//! ## Example
+//! The example from the hall request assigner README:
+//! ```
+//! use std::collections::BTreeMap;
+//! use elevatorpro::manager::optimal_hall_requests::{optimal_hall_requests, AssignerConfig, LocalElevatorState};
+//! use elevatorpro::world_view::{Dirn, ElevatorBehaviour};
+//!
+//! let hall_requests = vec![[false, false], [true, false], [false, false], [false, true]];
+//! let states = BTreeMap::from([
+//!     ("one".to_string(), LocalElevatorState {
+//!         behaviour: ElevatorBehaviour::Moving,
+//!         floor: 2,
+//!         direction: Dirn::Up,
+//!         cab_requests: vec![false, false, true, true],
+//!     }),
+//!     ("two".to_string(), LocalElevatorState {
+//!         behaviour: ElevatorBehaviour::Idle,
+//!         floor: 0,
+//!         direction: Dirn::Stop,
+//!         cab_requests: vec![false, false, false, false],
+//!     }),
+//! ]);
+//!
+//! let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+//! assert_eq!(assigned["one"], vec![[false, false], [false, false], [false, false], [false, true]]);
+//! assert_eq!(assigned["two"], vec![[false, false], [true, false], [false, false], [false, false]]);
+//! ```
+
+use crate::config;
+use crate::print;
+use crate::world_view::{Dirn, ElevatorBehaviour, WorldView};
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+
+/// Which requests are cleared when an elevator stops at a floor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClearRequestType
+{
+    /// All requests at the floor
+    All,
+    /// The cab request, and the hall request in the direction of travel
+    #[default]
+    InDirn,
+}
+
+/// Parameters of the simulated elevators, the same as the command line arguments of the D program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssignerConfig
+{
+    /// Time the door is open at a stop (`--doorOpenDuration`)
+    pub door_open_duration: Duration,
+
+    /// Travel time between two floors (`--travelDuration`)
+    pub travel_duration: Duration,
+
+    /// Which requests are cleared at a stop (`--clearRequestType`)
+    pub clear_request_type: ClearRequestType,
+}
+
+impl Default for AssignerConfig
+{
+    fn default() -> Self
+    {
+        AssignerConfig
+        {
+            door_open_duration: Duration::from_millis(3000),
+            travel_duration: Duration::from_millis(2500),
+            clear_request_type: ClearRequestType::InDirn,
+        }
+    }
+}
+
+/// The state of one elevator, as given to the assigner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalElevatorState
+{
+    /// Must be [ElevatorBehaviour::Idle], [ElevatorBehaviour::Moving] or [ElevatorBehaviour::DoorOpen]
+    pub behaviour: ElevatorBehaviour,
+
+    /// The current floor, or the last floor passed if moving
+    pub floor: i32,
+
+    /// The direction of the elevator
+    pub direction: Dirn,
+
+    /// The cab requests, one per floor
+    pub cab_requests: Vec<bool>,
+}
+
+
+/// Assigns the hall requests in the worldview to the elevators which can take hall requests
+///
+/// ## Parameters
+/// `wv`: The worldview to assign the hall requests of
+///
+/// ## Returns
+/// A map from elevator ID to the elevator's assigned hall requests, in the same format as the D program gives.
+/// An empty map if no elevator can take hall requests, or an elevator is in a state the assigner can not handle (e.g. an unknown floor).
+pub fn assign_from_wv(
+    wv: &WorldView
+) -> HashMap<u8, Vec<[bool; 2]>>
+{
+    let num_floors = wv.hall_request.len();
+    let states: BTreeMap<String, LocalElevatorState> = wv.elevator_containers.iter()
+        .filter(|elev| elev.can_take_hall_requests())
+        .map(|elev| {
+            let floor = if (0..elev.num_floors).contains(&elev.last_floor_sensor) {elev.last_floor_sensor} else {config::ERROR_ID};
+            // Every cab request vector must be as long as the hall requests
+            let mut cab_requests = elev.cab_requests.clone();
+            cab_requests.resize(num_floors, false);
+
+            (elev.elevator_id.to_string(), LocalElevatorState
+            {
+                behaviour: elev.behaviour,
+                floor: floor as i32,
+                direction: elev.dirn,
+                cab_requests,
+            })
+        })
+        .collect();
+
+    if states.is_empty() {return HashMap::new()}
+
+    match optimal_hall_requests(&wv.hall_request, &states, &AssignerConfig::default())
+    {
+        Ok(assigned) => assigned.into_iter()
+            .filter_map(|(id, tasks)| id.parse::<u8>().ok().map(|id| (id, tasks)))
+            .collect(),
+        Err(e) =>
+        {
+            print::err(format!("Hall request assigner failed: {}", e));
+            HashMap::new()
+        }
+    }
+}
+
+/// Finds the optimal assignment of hall requests to elevators
+///
+/// ## Parameters
+/// `hall_requests`: The hall requests, `[up, down]` per floor
+/// `states`: The elevators, keyed on their ID
+/// `config`: Parameters of the simulated elevators
+///
+/// ## Returns
+/// The hall requests assigned to each elevator, or `Err` if the input is invalid:
+/// - No elevators
+/// - Cab requests not as long as the hall requests
+/// - An elevator at a floor outside of the hall requests, or moving out of them
+/// - An elevator with a behaviour other than idle, moving or door open
+///
+/// ## Example
+/// Two elevators the same number of floors away from a request, where one is moving toward it, gives it to the moving one:
+/// ```
+/// use std::collections::BTreeMap;
+/// use elevatorpro::manager::optimal_hall_requests::{optimal_hall_requests, AssignerConfig, LocalElevatorState};
+/// use elevatorpro::world_view::{Dirn, ElevatorBehaviour};
+///
+/// let state = |behaviour, floor, direction, cab: [u8; 4]| LocalElevatorState {
+///     behaviour, floor, direction, cab_requests: cab.iter().map(|c| *c == 1).collect(),
+/// };
+/// let states = BTreeMap::from([
+///     ("27".to_string(), state(ElevatorBehaviour::Moving, 1, Dirn::Down, [0, 0, 0, 0])),
+///     ("20".to_string(), state(ElevatorBehaviour::DoorOpen, 1, Dirn::Down, [0, 0, 0, 0])),
+/// ]);
+/// let hall_requests = vec![[true, false], [false, false], [false, false], [false, false]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["27"], vec![[true, false], [false, false], [false, false], [false, false]]);
+/// assert_eq!(assigned["20"], vec![[false, false]; 4]);
+///
+/// // Two identical elevators: the request goes to the lowest ID
+/// let states = BTreeMap::from([
+///     ("1".to_string(), state(ElevatorBehaviour::Moving, 1, Dirn::Up, [1, 0, 0, 0])),
+///     ("2".to_string(), state(ElevatorBehaviour::Idle, 1, Dirn::Stop, [1, 0, 0, 0])),
+///     ("3".to_string(), state(ElevatorBehaviour::Idle, 1, Dirn::Stop, [1, 0, 0, 0])),
+/// ]);
+/// let hall_requests = vec![[true, false], [false, false], [false, false], [false, true]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["1"], vec![[false, false], [false, false], [false, false], [false, true]]);
+/// assert_eq!(assigned["2"], vec![[true, false], [false, false], [false, false], [false, false]]);
+/// assert_eq!(assigned["3"], vec![[false, false]; 4]);
+///
+/// // Idle elevators at each end: each takes the closest request, even in the "wrong" direction
+/// let states = BTreeMap::from([
+///     ("1".to_string(), state(ElevatorBehaviour::Idle, 0, Dirn::Stop, [0, 0, 0, 0])),
+///     ("2".to_string(), state(ElevatorBehaviour::Idle, 3, Dirn::Stop, [0, 0, 0, 0])),
+/// ]);
+/// let hall_requests = vec![[false, false], [false, true], [true, false], [false, false]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["1"], vec![[false, false], [false, true], [false, false], [false, false]]);
+/// assert_eq!(assigned["2"], vec![[false, false], [false, false], [true, false], [false, false]]);
+///
+/// // With a cab request ahead, elevator 1 skips the request in the "wrong" direction
+/// let states = BTreeMap::from([
+///     ("1".to_string(), state(ElevatorBehaviour::Idle, 0, Dirn::Stop, [0, 0, 1, 0])),
+///     ("2".to_string(), state(ElevatorBehaviour::Idle, 3, Dirn::Stop, [0, 0, 0, 0])),
+/// ]);
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["1"], vec![[false, false], [false, false], [true, false], [false, false]]);
+/// assert_eq!(assigned["2"], vec![[false, false], [false, true], [false, false], [false, false]]);
+///
+/// // Up and down at the same floor are split between elevators
+/// let states = BTreeMap::from([
+///     ("one".to_string(), state(ElevatorBehaviour::Idle, 0, Dirn::Down, [0, 0, 0, 0])),
+///     ("two".to_string(), state(ElevatorBehaviour::Idle, 3, Dirn::Down, [0, 0, 0, 0])),
+/// ]);
+/// let hall_requests = vec![[false, false], [true, true], [true, false], [false, false]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["one"], vec![[false, false], [true, false], [true, false], [false, false]]);
+/// assert_eq!(assigned["two"], vec![[false, false], [false, true], [false, false], [false, false]]);
+///
+/// // An idle elevator at the floor is not faster than one with cab requests on the way
+/// let states = BTreeMap::from([
+///     ("1".to_string(), state(ElevatorBehaviour::Moving, 3, Dirn::Down, [1, 0, 0, 0])),
+///     ("2".to_string(), state(ElevatorBehaviour::Idle, 3, Dirn::Down, [0, 0, 0, 0])),
+/// ]);
+/// let hall_requests = vec![[false, false], [true, true], [false, false], [false, false]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["1"], vec![[false, false], [false, true], [false, false], [false, false]]);
+/// assert_eq!(assigned["2"], vec![[false, false], [true, false], [false, false], [false, false]]);
+///
+/// // The closest elevator without cab requests takes the request
+/// let states = BTreeMap::from([
+///     ("1".to_string(), state(ElevatorBehaviour::Idle, 0, Dirn::Stop, [0, 0, 0, 0])),
+///     ("2".to_string(), state(ElevatorBehaviour::DoorOpen, 3, Dirn::Down, [1, 0, 0, 0])),
+///     ("3".to_string(), state(ElevatorBehaviour::Moving, 2, Dirn::Up, [1, 0, 0, 1])),
+/// ]);
+/// let hall_requests = vec![[false, false], [true, false], [false, false], [false, false]];
+///
+/// let assigned = optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).unwrap();
+/// assert_eq!(assigned["1"], vec![[false, false], [true, false], [false, false], [false, false]]);
+/// assert_eq!(assigned["2"], vec![[false, false]; 4]);
+/// assert_eq!(assigned["3"], vec![[false, false]; 4]);
+///
+/// // Invalid input is rejected
+/// let mut states = states;
+/// states.get_mut("1").unwrap().floor = 4;
+/// assert!(optimal_hall_requests(&hall_requests, &states, &AssignerConfig::default()).is_err());
+/// ```
+pub fn optimal_hall_requests(
+    hall_requests: &[[bool; 2]],
+    states: &BTreeMap<String, LocalElevatorState>,
+    config: &AssignerConfig
+) -> Result<HashMap<String, Vec<[bool; 2]>>, String>
+{
+    validate(hall_requests, states)?;
+
+    let mut reqs: Vec<[Req; 2]> = hall_requests.iter()
+        .map(|[up, down]| [Req { active: *up, assigned_to: None }, Req { active: *down, assigned_to: None }])
+        .collect();
+
+    // The initial time is offset by the index, so ties are broken on the ID
+    let mut sim: Vec<SimState> = states.iter()
+        .enumerate()
+        .map(|(i, (id, state))| SimState { id: id.clone(), state: state.clone(), time: Duration::from_micros(i as u64) })
+        .collect();
+
+    for s in sim.iter_mut()
+    {
+        perform_initial_move(s, &mut reqs, config);
+    }
+
+    loop
+    {
+        sim.sort_by_key(|s| s.time);
+
+        let mut done = !any_unassigned(&reqs);
+        if unvisited_are_immediately_assignable(&reqs, &sim)
+        {
+            assign_immediate(&mut reqs, &mut sim, config);
+            done = true;
+        }
+
+        if done {break}
+
+        perform_single_move(&mut sim[0], &mut reqs, config);
+    }
+
+    let mut result: HashMap<String, Vec<[bool; 2]>> = states.keys()
+        .map(|id| (id.clone(), vec![[false; 2]; hall_requests.len()]))
+        .collect();
+
+    for (floor, reqs_at_floor) in reqs.iter().enumerate()
+    {
+        for (call, req) in reqs_at_floor.iter().enumerate()
+        {
+            if let (true, Some(id)) = (req.active, &req.assigned_to)
+            {
+                if let Some(tasks) = result.get_mut(id) {tasks[floor][call] = true}
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+
+/// A hall request, and the elevator it is assigned to
+#[derive(Debug, Clone)]
+struct Req
+{
+    active: bool,
+    assigned_to: Option<String>,
+}
+
+impl Req
+{
+    fn is_unassigned(&self) -> bool
+    {
+        self.active && self.assigned_to.is_none()
+    }
+}
+
+/// A simulated elevator, and the time it has used so far
+#[derive(Debug, Clone)]
+struct SimState
+{
+    id: String,
+    state: LocalElevatorState,
+    time: Duration,
+}
+
+/// An elevator with its requests, `[up, down, cab]` per floor
+#[derive(Debug, Clone)]
+struct ElevatorState
+{
+    floor: usize,
+    direction: Dirn,
+    requests: Vec<[bool; 3]>,
+}
+
+const HALL_UP: usize = 0;
+const HALL_DOWN: usize = 1;
+const CAB: usize = 2;
+
+
+/// Checks the preconditions of the D program
+fn validate(
+    hall_requests: &[[bool; 2]],
+    states: &BTreeMap<String, LocalElevatorState>
+) -> Result<(), String>
+{
+    let num_floors = hall_requests.len() as i32;
+    let in_bounds = |floor: i32| floor >= 0 && floor < num_floors;
+
+    if states.is_empty()
+    {
+        return Err("No elevator states provided".to_string());
+    }
+    if states.values().any(|s| s.cab_requests.len() != hall_requests.len())
+    {
+        return Err("Hall and cab requests do not all have the same length".to_string());
+    }
+    if states.values().any(|s| !in_bounds(s.floor))
+    {
+        return Err("Some elevator is at an invalid floor".to_string());
+    }
+    if states.values().any(|s| s.behaviour == ElevatorBehaviour::Moving && !in_bounds(s.floor + s.direction as i32))
+    {
+        return Err("Some elevator is moving away from an end floor".to_string());
+    }
+    if states.values().any(|s| !matches!(s.behaviour, ElevatorBehaviour::Idle | ElevatorBehaviour::Moving | ElevatorBehaviour::DoorOpen))
+    {
+        return Err("Some elevator has a behaviour other than idle, moving or door open".to_string());
+    }
+    Ok(())
+}
+
+fn any_unassigned(
+    reqs: &[[Req; 2]]
+) -> bool
+{
+    reqs.iter().flatten().any(Req::is_unassigned)
+}
+
+/// The elevator with the unassigned hall requests and its own cab requests
+fn with_unassigned_reqs(
+    s: &SimState,
+    reqs: &[[Req; 2]]
+) -> ElevatorState
+{
+    ElevatorState
+    {
+        floor: s.state.floor as usize,
+        direction: s.state.direction,
+        requests: reqs.iter()
+            .zip(s.state.cab_requests.iter())
+            .map(|([up, down], cab)| [up.is_unassigned(), down.is_unassigned(), *cab])
+            .collect(),
+    }
+}
+
+fn perform_initial_move(
+    s: &mut SimState,
+    reqs: &mut [[Req; 2]],
+    config: &AssignerConfig
+)
+{
+    match s.state.behaviour
+    {
+        ElevatorBehaviour::Moving =>
+        {
+            s.state.floor += s.state.direction as i32;
+            s.time += config.travel_duration / 2;
+        }
+        behaviour =>
+        {
+            if behaviour == ElevatorBehaviour::DoorOpen
+            {
+                s.time += config.door_open_duration / 2;
+            }
+            for req in reqs[s.state.floor as usize].iter_mut()
+            {
+                if req.active
+                {
+                    req.assigned_to = Some(s.id.clone());
+                    s.time += config.door_open_duration;
+                }
+            }
+        }
+    }
+}
+
+fn perform_single_move(
+    s: &mut SimState,
+    reqs: &mut [[Req; 2]],
+    config: &AssignerConfig
+)
+{
+    let e = with_unassigned_reqs(s, reqs);
+
+    match s.state.behaviour
+    {
+        ElevatorBehaviour::Moving =>
+        {
+            if should_stop(&e)
+            {
+                s.state.behaviour = ElevatorBehaviour::DoorOpen;
+                s.time += config.door_open_duration;
+                clear_reqs_at_floor(&e, s, reqs, config.clear_request_type);
+            }
+            else
+            {
+                s.state.floor += s.state.direction as i32;
+                s.time += config.travel_duration;
+            }
+        }
+        _ =>
+        {
+            s.state.direction = choose_direction(&e);
+            if s.state.direction == Dirn::Stop
+            {
+                if any_requests_at_floor(&e)
+                {
+                    // Cleared with the direction from before the direction was chosen, like the D program
+                    clear_reqs_at_floor(&e, s, reqs, config.clear_request_type);
+                    s.time += config.door_open_duration;
+                    s.state.behaviour = ElevatorBehaviour::DoorOpen;
+                }
+                else
+                {
+                    s.state.behaviour = ElevatorBehaviour::Idle;
+                }
+            }
+            else
+            {
+                s.state.behaviour = ElevatorBehaviour::Moving;
+                s.state.floor += s.state.direction as i32;
+                s.time += config.travel_duration;
+            }
+        }
+    }
+}
+
+/// No remaining cab requests, no floors with multiple hall requests, and all *unvisited* hall requests are at floors with elevators
+fn unvisited_are_immediately_assignable(
+    reqs: &[[Req; 2]],
+    sim: &[SimState]
+) -> bool
+{
+    if sim.iter().any(|s| s.state.cab_requests.iter().any(|c| *c)) {return false}
+
+    for (floor, reqs_at_floor) in reqs.iter().enumerate()
+    {
+        if reqs_at_floor.iter().all(|r| r.active) {return false}
+
+        for req in reqs_at_floor.iter()
+        {
+            if req.is_unassigned() && !sim.iter().any(|s| s.state.floor as usize == floor)
+            {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn assign_immediate(
+    reqs: &mut [[Req; 2]],
+    sim: &mut [SimState],
+    config: &AssignerConfig
+)
+{
+    for (floor, reqs_at_floor) in reqs.iter_mut().enumerate()
+    {
+        for req in reqs_at_floor.iter_mut()
+        {
+            for s in sim.iter_mut()
+            {
+                if req.is_unassigned() && s.state.floor as usize == floor && !s.state.cab_requests.iter().any(|c| *c)
+                {
+                    req.assigned_to = Some(s.id.clone());
+                    s.time += config.door_open_duration;
+                }
+            }
+        }
+    }
+}
+
+
+/* _______________ The elevator algorithm from elevator_algorithm.d _______________ */
+
+fn requests_above(
+    e: &ElevatorState
+) -> bool
+{
+    e.requests[e.floor + 1..].iter().any(|r| r.iter().any(|b| *b))
+}
+
+fn requests_below(
+    e: &ElevatorState
+) -> bool
+{
+    e.requests[..e.floor].iter().any(|r| r.iter().any(|b| *b))
+}
+
+fn any_requests_at_floor(
+    e: &ElevatorState
+) -> bool
+{
+    e.requests[e.floor].iter().any(|b| *b)
+}
+
+fn should_stop(
+    e: &ElevatorState
+) -> bool
+{
+    let at_end = e.floor == 0 || e.floor == e.requests.len() - 1;
+    match e.direction
+    {
+        Dirn::Up => e.requests[e.floor][HALL_UP] || e.requests[e.floor][CAB] || !requests_above(e) || at_end,
+        Dirn::Down => e.requests[e.floor][HALL_DOWN] || e.requests[e.floor][CAB] || !requests_below(e) || at_end,
+        Dirn::Stop => true,
+    }
+}
+
+fn choose_direction(
+    e: &ElevatorState
+) -> Dirn
+{
+    match e.direction
+    {
+        Dirn::Up =>
+        {
+            if requests_above(e) {Dirn::Up}
+            else if any_requests_at_floor(e) {Dirn::Stop}
+            else if requests_below(e) {Dirn::Down}
+            else {Dirn::Stop}
+        }
+        Dirn::Down | Dirn::Stop =>
+        {
+            if requests_below(e) {Dirn::Down}
+            else if any_requests_at_floor(e) {Dirn::Stop}
+            else if requests_above(e) {Dirn::Up}
+            else {Dirn::Stop}
+        }
+    }
+}
+
+/// Clears the requests at the floor of `e`. Cleared hall requests are assigned to `s`, and cleared cab requests are removed from `s`.
+fn clear_reqs_at_floor(
+    e: &ElevatorState,
+    s: &mut SimState,
+    reqs: &mut [[Req; 2]],
+    clear_request_type: ClearRequestType
+)
+{
+    let mut e2 = e.clone();
+    let floor = e2.floor;
+
+    let mut clear = |e2: &mut ElevatorState, call: usize| {
+        if e2.requests[floor][call]
+        {
+            if call == CAB
+            {
+                s.state.cab_requests[floor] = false;
+            }
+            else
+            {
+                reqs[floor][call].assigned_to = Some(s.id.clone());
+            }
+            e2.requests[floor][call] = false;
+        }
+    };
+
+    match clear_request_type
+    {
+        ClearRequestType::All =>
+        {
+            for call in [HALL_UP, HALL_DOWN, CAB]
+            {
+                clear(&mut e2, call);
+            }
+        }
+        ClearRequestType::InDirn =>
+        {
+            clear(&mut e2, CAB);
+
+            match e.direction
+            {
+                Dirn::Up =>
+                {
+                    if e2.requests[floor][HALL_UP] {clear(&mut e2, HALL_UP)}
+                    else if !requests_above(&e2) {clear(&mut e2, HALL_DOWN)}
+                }
+                Dirn::Down =>
+                {
+                    if e2.requests[floor][HALL_DOWN] {clear(&mut e2, HALL_DOWN)}
+                    else if !requests_below(&e2) {clear(&mut e2, HALL_UP)}
+                }
+                Dirn::Stop =>
+                {
+                    clear(&mut e2, HALL_UP);
+                    clear(&mut e2, HALL_DOWN);
+                }
+            }
+        }
+    }
+}
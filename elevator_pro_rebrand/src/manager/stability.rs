@@ -0,0 +1,118 @@
+//! Assignment stability
+//!
+//! Without stability, every new assignment can move a hall request to another elevator, as the positions of the elevators change.
+//! Users then see the request served by another elevator than the one they watched approaching.
+//!
+//! With a margin set in [crate::config::ASSIGNMENT_STABILITY_MARGIN], a hall request already given to a healthy elevator which
+//! is making progress is only moved if the new elevator's [estimated time of arrival](super::eta) is better by at least the margin.
+//!
+//! ## Example
+//! ```
+//! use std::collections::HashMap;
+//! use std::time::Duration;
+//! use elevatorpro::manager::stability::keep_stable_assignments;
+//! use elevatorpro::world_view::{Dirn, ElevatorBehaviour, WorldView};
+//!
+//! // Elevator 1 is on its way up to the request, elevator 2 has just become idle at its floor
+//! let mut wv = WorldView::with_elevators(4, &[(1, 1), (2, 3)]);
+//! wv.hall_request[3] = [false, true];
+//! let approaching = &mut wv.elevator_containers[0];
+//! approaching.behaviour = ElevatorBehaviour::Moving;
+//! approaching.dirn = Dirn::Up;
+//! approaching.tasks[3] = [false, true];
+//!
+//! let mut new_tasks = HashMap::from([
+//!     (1, vec![[false, false]; 4]),
+//!     (2, vec![[false, false], [false, false], [false, false], [false, true]]),
+//! ]);
+//!
+//! // Elevator 2 is 3.75 s faster, not enough with a margin of 5 s
+//! let mut tasks = new_tasks.clone();
+//! keep_stable_assignments(&wv, &mut tasks, Duration::from_secs(5));
+//! assert_eq!(tasks[&1][3], [false, true]);
+//! assert_eq!(tasks[&2][3], [false, false]);
+//!
+//! // but enough with a margin of 2 s
+//! keep_stable_assignments(&wv, &mut new_tasks, Duration::from_secs(2));
+//! assert_eq!(new_tasks[&1][3], [false, false]);
+//! assert_eq!(new_tasks[&2][3], [false, true]);
+//! ```
+
+use crate::world_view::{ElevatorBehaviour, ElevatorContainer, WorldView};
+
+use super::eta;
+use super::optimal_hall_requests::AssignerConfig;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+
+/// Gives hall requests back to the elevators they were assigned to, unless the new assignment is faster by at least `margin`
+///
+/// ## Parameters
+/// `wv`: The worldview, with the current tasks of the elevators
+/// `tasks`: The new assignment, changed in place
+/// `margin`: How much faster the new elevator must be for a request to be moved
+///
+/// ## Behavior
+/// A request is only kept by an elevator which [is making progress](is_making_progress), and which is in the new assignment.
+/// The estimated times of arrival are computed with the tasks of the new assignment.
+/// If the old elevator's time can not be estimated, the request is moved.
+pub fn keep_stable_assignments(
+    wv: &WorldView,
+    tasks: &mut HashMap<u8, Vec<[bool; 2]>>,
+    margin: Duration
+)
+{
+    let config = AssignerConfig::default();
+
+    for (floor, calls) in wv.hall_request.iter().enumerate()
+    {
+        for call in (0..2).filter(|call| calls[*call])
+        {
+            let Some(old) = wv.elevator_containers.iter()
+                .find(|elev| elev.tasks.get(floor).is_some_and(|task| task[call]) && is_making_progress(elev)) else {continue};
+            let Some(new_id) = tasks.iter()
+                .find(|(_, task)| task.get(floor).is_some_and(|task| task[call]))
+                .map(|(id, _)| *id) else {continue};
+            if new_id == old.elevator_id || !tasks.contains_key(&old.elevator_id) {continue}
+            let Some(new) = wv.elevator_containers.iter().find(|elev| elev.elevator_id == new_id) else {continue};
+
+            let Some(old_eta) = eta_with_tasks(old, &tasks[&old.elevator_id], floor, call, &config) else {continue};
+            let new_eta = eta_with_tasks(new, &tasks[&new_id], floor, call, &config).unwrap_or(Duration::MAX);
+
+            if new_eta.saturating_add(margin) > old_eta
+            {
+                tasks.get_mut(&new_id).unwrap()[floor][call] = false;
+                tasks.get_mut(&old.elevator_id).unwrap()[floor][call] = true;
+            }
+        }
+    }
+}
+
+/// Checks if the elevator is healthy, and is moving or serving a floor
+///
+/// An idle elevator has not started on its tasks yet, so its requests can be moved freely.
+pub fn is_making_progress(
+    elevator: &ElevatorContainer
+) -> bool
+{
+    elevator.can_take_hall_requests()
+        && !elevator.obstruction
+        && matches!(elevator.behaviour, ElevatorBehaviour::Moving | ElevatorBehaviour::DoorOpen)
+}
+
+
+/// The estimated time until the elevator serves the request, if its tasks were `tasks`
+fn eta_with_tasks(
+    elevator: &ElevatorContainer,
+    tasks: &[[bool; 2]],
+    floor: usize,
+    call: usize,
+    config: &AssignerConfig
+) -> Option<Duration>
+{
+    let mut e = elevator.clone();
+    e.tasks = tasks.to_vec();
+    eta::time_to_serve(&e, floor, call, config)
+}
@@ -0,0 +1,157 @@
+//! Starvation protection
+//!
+//! The hall assigners only look at where the elevators are, not at how long a hall request has waited.
+//! Under heavy load, a request can then be pushed back again and again.
+//!
+//! A hall request which has waited longer than [crate::config::STARVATION_TIME], counted from
+//! [WorldView::hall_request_since], is escalated: it is given to the nearest elevator which can take it,
+//! whatever the hall assigner decided.
+//!
+//! ## Example
+//! ```
+//! use std::collections::HashMap;
+//! use std::time::Duration;
+//! use elevatorpro::manager::starvation;
+//! use elevatorpro::world_view::WorldView;
+//!
+//! let mut wv = WorldView::with_elevators(4, &[(1, 0), (2, 2)]);
+//! wv.hall_request[3] = [false, true];
+//! wv.update_hall_request_since(1_000);
+//!
+//! let threshold = Duration::from_secs(60);
+//! assert_eq!(starvation::time_until_next_starving(&wv, 31_000, threshold), Some(Duration::from_secs(30)));
+//!
+//! // After a minute, the request goes to elevator 2, which is closest
+//! let starving = starvation::starving_hall_requests(&wv, 61_000, threshold);
+//! assert_eq!(starving[3], [false, true]);
+//!
+//! let mut tasks = HashMap::from([
+//!     (1, vec![[false, false], [false, false], [false, false], [false, true]]),
+//!     (2, vec![[false, false]; 4]),
+//! ]);
+//! starvation::escalate(&wv, &mut tasks, &starving);
+//! assert_eq!(tasks[&1][3], [false, false]);
+//! assert_eq!(tasks[&2][3], [false, true]);
+//! ```
+
+use crate::print;
+use crate::world_view::WorldView;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+
+/// Finds the hall requests which have waited longer than `threshold`
+///
+/// ## Parameters
+/// `wv`: The worldview, with [WorldView::hall_request_since]
+/// `now_ms`: The current time, from [crate::world_view::unix_millis]
+/// `threshold`: How long a hall request can wait before it is starving
+///
+/// ## Returns
+/// Whether each hall request is starving, `[up, down]` per floor of the hall requests
+pub fn starving_hall_requests(
+    wv: &WorldView,
+    now_ms: u64,
+    threshold: Duration
+) -> Vec<[bool; 2]>
+{
+    wv.hall_request.iter()
+        .enumerate()
+        .map(|(floor, calls)| {
+            let since = wv.hall_request_since.get(floor).copied().unwrap_or([None; 2]);
+            [0, 1].map(|call| calls[call] && since[call].is_some_and(|since| now_ms.saturating_sub(since) >= threshold.as_millis() as u64))
+        })
+        .collect()
+}
+
+/// Returns how long it is until the next hall request starts starving
+///
+/// `None` if no hall request is waiting, or all waiting hall requests are already starving.
+pub fn time_until_next_starving(
+    wv: &WorldView,
+    now_ms: u64,
+    threshold: Duration
+) -> Option<Duration>
+{
+    wv.hall_request.iter()
+        .zip(wv.hall_request_since.iter())
+        .flat_map(|(calls, since)| (0..2).filter(|call| calls[*call]).filter_map(|call| since[call]))
+        .map(|since| (since + threshold.as_millis() as u64).saturating_sub(now_ms))
+        .filter(|ms| *ms > 0)
+        .min()
+        .map(Duration::from_millis)
+}
+
+/// Gives each starving hall request to the nearest elevator which can take it, see [give_to_nearest]
+///
+/// ## Parameters
+/// `wv`: The worldview, with the states of the elevators
+/// `tasks`: The assignment, changed in place
+/// `starving`: The starving hall requests, from [starving_hall_requests]
+pub fn escalate(
+    wv: &WorldView,
+    tasks: &mut HashMap<u8, Vec<[bool; 2]>>,
+    starving: &[[bool; 2]]
+)
+{
+    for (floor, calls) in starving.iter().enumerate()
+    {
+        for call in (0..2).filter(|call| calls[*call])
+        {
+            let was_assigned: Vec<u8> = tasks.iter()
+                .filter(|(_, task)| task.get(floor).is_some_and(|task| task[call]))
+                .map(|(id, _)| *id)
+                .collect();
+
+            if let Some(id) = give_to_nearest(wv, tasks, floor, call, &[]) 
+            {
+                if !was_assigned.contains(&id) 
+                {
+                    print::warn(format!("Hall request at floor {} has waited too long, giving it to elevator {}", floor, id));
+                }
+            }
+        }
+    }
+}
+
+/// Gives a hall request to the nearest elevator which can take it, and removes it from all other elevators
+///
+/// ## Parameters
+/// `wv`: The worldview, with the states of the elevators
+/// `tasks`: The assignment, changed in place
+/// `floor`, `call`: The hall request
+/// `exclude`: Elevators which must not get the request
+///
+/// ## Returns
+/// The elevator which got the request, or `None` if no elevator can take it. The assignment is then left as it is.
+///
+/// ## Behavior
+/// Only elevators in `tasks` which can take hall requests, serve the floor, and are at a known floor are considered.
+/// Of those at the same distance, the elevator which was already given the request keeps it.
+pub fn give_to_nearest(
+    wv: &WorldView,
+    tasks: &mut HashMap<u8, Vec<[bool; 2]>>,
+    floor: usize,
+    call: usize,
+    exclude: &[u8]
+) -> Option<u8>
+{
+    let nearest = wv.elevator_containers.iter()
+        .filter(|elev| elev.can_take_hall_requests() && elev.serves_floor(floor) && elev.last_floor_sensor < elev.num_floors)
+        .filter(|elev| tasks.contains_key(&elev.elevator_id) && !exclude.contains(&elev.elevator_id))
+        .min_by_key(|elev| {
+            let assigned = tasks[&elev.elevator_id].get(floor).is_some_and(|task| task[call]);
+            ((elev.last_floor_sensor as i32 - floor as i32).abs(), !assigned, elev.elevator_id)
+        })?
+        .elevator_id;
+
+    for (id, task) in tasks.iter_mut()
+    {
+        if let Some(task) = task.get_mut(floor)
+        {
+            task[call] = *id == nearest;
+        }
+    }
+    Some(nearest)
+}
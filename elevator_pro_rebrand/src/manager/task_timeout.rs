@@ -0,0 +1,162 @@
+//! Task timeouts
+//!
+//! An elevator can accept a task and then stop making progress on it without entering an error behaviour,
+//! e.g. if it is stuck idle. The master then tracks every task, and when the elevator it is given to has not
+//! moved, changed direction or changed behaviour for [crate::config::TASK_TIMEOUT], the task is given to the
+//! nearest other elevator instead. A warning is recorded against the failing elevator in [WorldView::task_timeout_warnings].
+//!
+//! The failing elevator does not get the task back until it makes progress, or the hall request is cleared.
+//!
+//! ## Example
+//! ```
+//! use std::collections::HashMap;
+//! use std::time::Duration;
+//! use tokio::time::Instant;
+//! use elevatorpro::manager::task_timeout::TaskTracker;
+//! use elevatorpro::world_view::WorldView;
+//!
+//! let mut wv = WorldView::with_elevators(4, &[(1, 0), (2, 1)]);
+//! wv.hall_request[3] = [false, true];
+//! // Elevator 2 has the task, but stays idle
+//! wv.elevator_containers[1].tasks[3] = [false, true];
+//!
+//! let timeout = Duration::from_secs(20);
+//! let start = Instant::now();
+//! let mut tracker = TaskTracker::default();
+//! assert!(tracker.update(&wv, start, timeout).is_empty());
+//! assert_eq!(tracker.time_until_next_timeout(start, timeout), Some(timeout));
+//!
+//! assert_eq!(tracker.update(&wv, start + timeout, timeout), vec![2]);
+//!
+//! // The task is moved to elevator 1, even though the assigner gave it to elevator 2 again
+//! let mut tasks = HashMap::from([
+//!     (1, vec![[false, false]; 4]),
+//!     (2, vec![[false, false], [false, false], [false, false], [false, true]]),
+//! ]);
+//! tracker.reassign_stalled(&wv, &mut tasks);
+//! assert_eq!(tasks[&1][3], [false, true]);
+//! assert_eq!(tasks[&2][3], [false, false]);
+//! ```
+
+use crate::world_view::{Dirn, ElevatorBehaviour, WorldView};
+
+use super::starvation;
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+
+/// Tracks the progress of the elevators on their tasks
+#[derive(Debug, Clone, Default)]
+pub struct TaskTracker
+{
+    /// The tracked tasks, keyed on `(elevator_id, floor, call)`
+    tasks: HashMap<(u8, usize, usize), TrackedTask>,
+}
+
+/// The state of the elevator when it last made progress on a task
+#[derive(Debug, Clone)]
+struct TrackedTask
+{
+    progress: (u8, Dirn, ElevatorBehaviour),
+    since: Instant,
+    timed_out: bool,
+}
+
+impl TaskTracker
+{
+    /// Updates the tracked tasks from the worldview
+    ///
+    /// ## Parameters
+    /// `wv`: The worldview, with the tasks given to the elevators
+    /// `now`: The current time
+    /// `timeout`: How long an elevator can go without progress on a task
+    ///
+    /// ## Behavior
+    /// - New tasks are tracked from `now`.
+    /// - A task is restarted when its elevator changes floor, direction or behaviour.
+    /// - Tasks are forgotten when the hall request is cleared, or the elevator no longer has the task and has not timed out on it.
+    ///   A timed out task is kept until the elevator makes progress, so it is not given back to the elevator.
+    ///
+    /// ## Returns
+    /// The elevators which timed out on a task in this update, once per task
+    pub fn update(
+        &mut self,
+        wv: &WorldView,
+        now: Instant,
+        timeout: Duration
+    ) -> Vec<u8>
+    {
+        let mut timed_out = Vec::new();
+
+        self.tasks.retain(|(id, floor, call), task| {
+            let active = wv.hall_request.get(*floor).is_some_and(|calls| calls[*call]);
+            let Some(elev) = wv.elevator_containers.iter().find(|elev| elev.elevator_id == *id) else {return false};
+            let assigned = elev.tasks.get(*floor).is_some_and(|t| t[*call]);
+            let progressed = task.progress != (elev.last_floor_sensor, elev.dirn, elev.behaviour);
+
+            active && (assigned || task.timed_out) && !(task.timed_out && progressed)
+        });
+
+        for elev in wv.elevator_containers.iter()
+        {
+            let progress = (elev.last_floor_sensor, elev.dirn, elev.behaviour);
+            for (floor, calls) in elev.tasks.iter().enumerate()
+            {
+                for call in (0..2).filter(|call| calls[*call] && wv.hall_request.get(floor).is_some_and(|hall| hall[*call]))
+                {
+                    let task = self.tasks.entry((elev.elevator_id, floor, call))
+                        .or_insert(TrackedTask { progress, since: now, timed_out: false });
+
+                    if task.progress != progress
+                    {
+                        *task = TrackedTask { progress, since: now, timed_out: false };
+                    }
+                    else if !task.timed_out && now.duration_since(task.since) >= timeout
+                    {
+                        task.timed_out = true;
+                        timed_out.push(elev.elevator_id);
+                    }
+                }
+            }
+        }
+        timed_out
+    }
+
+    /// Returns how long it is until the next tracked task times out, if the elevators make no progress
+    pub fn time_until_next_timeout(
+        &self,
+        now: Instant,
+        timeout: Duration
+    ) -> Option<Duration>
+    {
+        self.tasks.values()
+            .filter(|task| !task.timed_out)
+            .map(|task| (task.since + timeout).saturating_duration_since(now))
+            .min()
+    }
+
+    /// Moves the tasks the elevators have timed out on to the nearest other elevator, see [starvation::give_to_nearest]
+    ///
+    /// If no other elevator can take a task, it is left where it is.
+    pub fn reassign_stalled(
+        &self,
+        wv: &WorldView,
+        tasks: &mut HashMap<u8, Vec<[bool; 2]>>
+    )
+    {
+        for (id, floor, call) in self.tasks.iter().filter(|(_, task)| task.timed_out).map(|(key, _)| *key)
+        {
+            let stalled: Vec<u8> = self.tasks.iter()
+                .filter(|((_, f, c), task)| task.timed_out && *f == floor && *c == call)
+                .map(|((id, _, _), _)| *id)
+                .collect();
+
+            if tasks.get(&id).and_then(|task| task.get(floor)).is_some_and(|task| task[call])
+            {
+                starvation::give_to_nearest(wv, tasks, floor, call, &stalled);
+            }
+        }
+    }
+}
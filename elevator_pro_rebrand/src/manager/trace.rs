@@ -0,0 +1,197 @@
+//! Assignment trace
+//!
+//! When a path is set in [crate::config::ASSIGNMENT_TRACE] (see [NodeConfig::assignment_trace]), the manager appends every assignment it computes to the file:
+//! one JSON line per assignment, with the time, the hall assigner, the [AssignmentInput] and the resulting tasks.
+//! Odd assignments can then be looked into after the fact.
+//!
+//! When the file would grow past [crate::config::ASSIGNMENT_TRACE_MAX_BYTES], it is rotated: it is renamed to `<path>.1`,
+//! `<path>.1` to `<path>.2` and so on. Only [crate::config::ASSIGNMENT_TRACE_FILES] rotated files are kept.
+//!
+//! The trace is off by default, and failing to write it never stops the manager, so nodes can run from a read-only directory.
+//!
+//! ## Example
+//! ```
+//! use std::collections::BTreeMap;
+//! use std::time::Duration;
+//! use elevatorpro::manager::AssignmentInput;
+//! use elevatorpro::manager::trace::{self, TraceEntry};
+//! use elevatorpro::world_view::WorldView;
+//!
+//! let dir = std::env::temp_dir().join(format!("assignment_trace_doctest_{}", std::process::id()));
+//! std::fs::create_dir_all(&dir).unwrap();
+//! let path = dir.join("trace.jsonl");
+//!
+//! let entry = TraceEntry
+//! {
+//!     timestamp_ms: 1_000,
+//!     assigner: "Optimal".to_string(),
+//!     input: AssignmentInput::from_wv(&WorldView::default(), Duration::from_secs(60)),
+//!     tasks: BTreeMap::from([(1, vec![[false, false]; 4])]),
+//!     degraded: false,
+//! };
+//!
+//! // Room for two entries per file, and one rotated file
+//! let line = serde_json::to_string(&entry).unwrap().len() as u64 + 1;
+//! for _ in 0..5
+//! {
+//!     trace::append(&path, &entry, 2 * line, 1).unwrap();
+//! }
+//!
+//! assert_eq!(trace::read(&path).unwrap(), vec![entry.clone()]);
+//! assert_eq!(trace::read(&dir.join("trace.jsonl.1")).unwrap().len(), 2);
+//! assert!(!dir.join("trace.jsonl.2").exists());
+//!
+//! std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use crate::config;
+use crate::node::NodeConfig;
+use crate::print;
+use crate::world_view::{self, WorldView};
+
+use super::{AssignmentInput, TaskAssignment};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+
+/// One assignment in the trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry
+{
+    /// When the assignment was computed, from [world_view::unix_millis]
+    pub timestamp_ms: u64,
+
+    /// The hall assigner selected in [NodeConfig::hall_assigner]
+    pub assigner: String,
+
+    /// What the assignment was computed from
+    pub input: AssignmentInput,
+
+    /// The hall requests given to each elevator, `[up, down]` per floor
+    pub tasks: BTreeMap<u8, Vec<[bool; 2]>>,
+
+    /// Whether a fallback assigner was used, see [TaskAssignment::degraded]
+    pub degraded: bool,
+}
+
+/// Writes the assignments of one node to its trace file
+///
+/// Kept by the manager of the node, so a broken trace is only warned about once per node.
+#[derive(Debug, Default)]
+pub struct TraceWriter
+{
+    /// Set after a failed write, and cleared after a successful one
+    write_failed: bool,
+}
+
+impl TraceWriter
+{
+    /// Appends the assignment to the trace file in [NodeConfig::assignment_trace], if it is set
+    ///
+    /// A failed write is warned about the first time, and otherwise ignored.
+    pub fn record(
+        &mut self,
+        wv: &WorldView,
+        assignment: &TaskAssignment,
+        node_config: &NodeConfig
+    )
+    {
+        let Some(path) = node_config.assignment_trace.as_ref() else {return};
+
+        let entry = TraceEntry
+        {
+            timestamp_ms: world_view::unix_millis(),
+            assigner: format!("{:?}", node_config.hall_assigner),
+            input: AssignmentInput::from_wv(wv, node_config.starvation_time),
+            tasks: assignment.tasks.iter().map(|(id, tasks)| (*id, tasks.clone())).collect(),
+            degraded: assignment.degraded,
+        };
+
+        match append(path, &entry, config::ASSIGNMENT_TRACE_MAX_BYTES, config::ASSIGNMENT_TRACE_FILES)
+        {
+            Ok(()) => self.write_failed = false,
+            Err(e) =>
+            {
+                if !std::mem::replace(&mut self.write_failed, true)
+                {
+                    print::warn(format!("Failed to write the assignment trace to {}: {}", path.display(), e));
+                }
+            }
+        }
+    }
+}
+
+/// Appends one entry to a trace file as a JSON line, rotating the file first if the entry would not fit
+///
+/// ## Parameters
+/// `path`: The trace file
+/// `entry`: The entry to append
+/// `max_bytes`: Size the file can reach before it is rotated
+/// `files`: Number of rotated files to keep. With 0, the file is emptied instead of rotated.
+pub fn append(
+    path: &Path,
+    entry: &TraceEntry,
+    max_bytes: u64,
+    files: u32
+) -> io::Result<()>
+{
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > max_bytes
+    {
+        rotate(path, files)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+/// Reads the entries of a trace file, oldest first
+pub fn read(
+    path: &Path
+) -> io::Result<Vec<TraceEntry>>
+{
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+        .collect()
+}
+
+
+/// Shifts `<path>.<n>` to `<path>.<n + 1>` and `path` to `<path>.1`, dropping the oldest file
+fn rotate(
+    path: &Path,
+    files: u32
+) -> io::Result<()>
+{
+    if files == 0 {return fs::remove_file(path)}
+
+    let _ = fs::remove_file(rotated(path, files));
+    for n in (1..files).rev()
+    {
+        let from = rotated(path, n);
+        if from.exists()
+        {
+            fs::rename(from, rotated(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(path, 1))
+}
+
+/// The path of rotated file number `n`
+fn rotated(
+    path: &Path,
+    n: u32
+) -> PathBuf
+{
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
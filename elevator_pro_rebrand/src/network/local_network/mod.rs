@@ -39,12 +39,15 @@ use update_wv::{
     merge_wv_after_offline,
 };
 
+use crate::manager::TaskAssignment;
+use crate::node::NodeContext;
 use crate::print;
 use crate::world_view::{ElevatorContainer, WorldView};
 use crate::world_view::{self};
 
+use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
-use std::collections::HashMap;
+use tokio::task::yield_now;
 
 
 
@@ -63,6 +66,7 @@ use std::collections::HashMap;
 /// worldview is then sent through a `watch` channel to propagate state to other modules or tasks.
 ///
 /// # Parameters
+/// - `ctx`: The node.
 /// - `mpsc_rxs`: A struct containing all MPSC receiver channels used to receive events related to worldview changes.
 /// - `worldview_watch_tx`: A watch channel sender used to broadcast updated copies of the worldview to subscribers.
 /// - `worldview`: A mutable reference to the current local worldview instance.
@@ -95,6 +99,7 @@ use std::collections::HashMap;
 /// It must be run as an asynchronous task during system startup and should never exit during runtime.
 #[allow(non_snake_case)]
 pub async fn update_wv_watch(
+    ctx: Arc<NodeContext>,
     mut mpsc_rxs: MpscRxs, 
     worldview_watch_tx: watch::Sender<WorldView>, 
     mut worldview: &mut WorldView
@@ -115,7 +120,7 @@ pub async fn update_wv_watch(
         {
             Ok(msg) => 
             {
-                wv_edited_I = clear_from_sent_data(&mut worldview, msg);
+                wv_edited_I = clear_from_sent_data(&mut worldview, msg, ctx.id());
             },
             Err(_) => {},
         }
@@ -124,7 +129,16 @@ pub async fn update_wv_watch(
         {
             Ok(mut master_wv) => 
             {
-                wv_edited_I = join_wv_from_udp(&mut worldview, &mut master_wv);
+                // A master with a higher ID is taken over in a new term, instead of joined
+                if world_view::is_master(worldview, ctx.id()) && ctx.id() < master_wv.master_id 
+                {
+                    merge_wv_after_offline(worldview, &mut master_wv);
+                    wv_edited_I = true;
+                }
+                else 
+                {
+                    wv_edited_I = join_wv_from_udp(&mut worldview, &mut master_wv, ctx.id());
+                }
             },
             Err(_) => {}, 
         }
@@ -133,7 +147,7 @@ pub async fn update_wv_watch(
         {
             Ok(_) => 
             {
-                wv_edited_I = abort_network(&mut worldview);
+                wv_edited_I = abort_network(&mut worldview, ctx.id());
             },
             Err(_) => {},
         }
@@ -145,7 +159,7 @@ pub async fn update_wv_watch(
         {
             Ok(container) => 
             {
-                wv_edited_I = join_wv_from_container(&mut worldview, &container).await;
+                wv_edited_I = join_wv_from_container(&mut worldview, &container, ctx.id()).await;
             },
             Err(_) => {},
         }
@@ -154,7 +168,7 @@ pub async fn update_wv_watch(
         {
             Ok(container) => 
             {
-                wv_edited_I = join_wv_from_container(&mut worldview, &container).await;
+                wv_edited_I = join_wv_from_container(&mut worldview, &container, ctx.id()).await;
             },
             Err(_) => {},
         }
@@ -171,9 +185,9 @@ pub async fn update_wv_watch(
         /*_____Update worldview when new tasks has been given_____ */
         match mpsc_rxs.delegated_tasks.try_recv() 
         {
-            Ok(map) => 
+            Ok(assignment) => 
             {
-                wv_edited_I = distribute_tasks(&mut worldview, map);
+                wv_edited_I = distribute_tasks(&mut worldview, assignment);
             },
             Err(_) => {},
         }        
@@ -186,7 +200,7 @@ pub async fn update_wv_watch(
             Ok(container) => 
             {
                 wv_edited_I = update_elev_states(&mut worldview, container);
-                master_container_updated_I = world_view::is_master(&worldview);
+                master_container_updated_I = world_view::is_master(&worldview, ctx.id());
             },
             Err(_) => {},
         }
@@ -206,7 +220,7 @@ pub async fn update_wv_watch(
         /*_____If master container has changed, send the container on master_container_tx_____ */
         if master_container_updated_I 
         {
-            if let Some(container) = world_view::extract_self_elevator_container(&worldview) 
+            if let Some(container) = world_view::extract_self_elevator_container(&worldview, ctx.id()) 
             {
                 let _ = master_container_tx.send(container.clone()).await;
             } else 
@@ -222,6 +236,9 @@ pub async fn update_wv_watch(
             let _ = worldview_watch_tx.send(worldview.clone());
             wv_edited_I = false;
         }
+
+        // Let the other tasks on this worker run, so several nodes can share one runtime
+        yield_now().await;
     }
 }
 
@@ -249,7 +266,7 @@ pub struct MpscTxs
     pub sent_container: mpsc::Sender<ElevatorContainer>,
 
     /// Sends delegated tasks from the manager
-    pub delegated_tasks: mpsc::Sender<HashMap<u8, Vec<[bool; 2]>>>,
+    pub delegated_tasks: mpsc::Sender<TaskAssignment>,
 
     /// Send ElevatorContainer from the local elevator handler
     pub elevator_states: mpsc::Sender<ElevatorContainer>,
@@ -285,7 +302,7 @@ pub struct MpscRxs
     pub sent_container: mpsc::Receiver<ElevatorContainer>,
 
     /// Recieves delegated tasks from the manager
-    pub delegated_tasks: mpsc::Receiver<HashMap<u8, Vec<[bool; 2]>>>,
+    pub delegated_tasks: mpsc::Receiver<TaskAssignment>,
 
     /// Recieves ElevatorContainer from the local elevator handler
     pub elevator_states: mpsc::Receiver<ElevatorContainer>,
@@ -42,31 +42,18 @@
 
 use crate::world_view::{
     self, 
-    Dirn, 
     ElevatorBehaviour, 
     ElevatorContainer, 
     WorldView
 };
+use crate::manager::TaskAssignment;
 use crate::print;
-use crate::network;
 
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
-use std::sync::LazyLock;
 
 
 
 
-
-static HALL_INSTANTS: LazyLock<Mutex<[[Instant; 2]; 4]>> = LazyLock::new(|| {
-    Mutex::new(std::array::from_fn(|_| {
-        std::array::from_fn(|_| Instant::now())
-    }))
-});
-
-
-
 /* _______________ START PUB FUNCTIONS _______________ */
 
 
@@ -80,6 +67,7 @@ static HALL_INSTANTS: LazyLock<Mutex<[[Instant; 2]; 4]>> = LazyLock::new(|| {
 /// ## Arguments
 /// * `my_wv` - A serialized `Vec<u8>` representing the local worldview.
 /// * `master_wv` - A serialized `Vec<u8>` representing the worldview received over UDP.
+/// * `self_id` - The ID of this node.
 ///
 /// ## Returns
 /// A new serialized `Vec<u8>` representing the updated worldview.
@@ -92,11 +80,12 @@ static HALL_INSTANTS: LazyLock<Mutex<[[Instant; 2]; 4]>> = LazyLock::new(|| {
 /// - If the local elevator is missing in `master_wv`, it is added to `master_wv`.
 pub fn join_wv_from_udp(
     my_wv: &mut WorldView, 
-    master_wv: &mut WorldView
+    master_wv: &mut WorldView,
+    self_id: u8
 ) -> bool 
 {
-    let my_self_index = world_view::get_index_to_container(network::read_self_id() , my_wv);
-    let master_self_index = world_view::get_index_to_container(network::read_self_id() , master_wv);
+    let my_self_index = world_view::get_index_to_container(self_id, my_wv);
+    let master_self_index = world_view::get_index_to_container(self_id, master_wv);
     
     
     if let (Some(i_org), Some(i_new)) = (my_self_index, master_self_index) 
@@ -110,6 +99,7 @@ pub fn join_wv_from_udp(
         master_view.obstruction = my_view.obstruction;
         master_view.last_floor_sensor = my_view.last_floor_sensor;
         master_view.unsent_hall_request = my_view.unsent_hall_request.clone();
+        master_view.served_hall_request = my_view.served_hall_request.clone();
         master_view.cab_requests = my_view.cab_requests.clone();
 
 
@@ -126,14 +116,15 @@ pub fn join_wv_from_udp(
 /// ### 'Leaves' the network, removes all elevators that are not the current one
 /// 
 /// This function updates the local worldview by removing all elevators that do not
-/// belong to the current entity, identified by `SELF_ID`.
+/// belong to the current entity, identified by `self_id`.
 /// 
 /// The function first deserializes the worldview, removes all elevators that do not
 /// have the correct `elevator_id`, updates the number of elevators, and sets the master
-/// ID to `SELF_ID`. Then, the updated worldview is serialized back into `wv`.
+/// ID to `self_id`. Then, the updated worldview is serialized back into `wv`.
 /// 
 /// ## Parameters
 /// - `wv`: A mutable reference to a `Vec<u8>` representing the worldview.
+/// - `self_id`: The ID of this node.
 /// 
 /// ## Return Value
 /// - Always returns `true` after the update.
@@ -141,16 +132,21 @@ pub fn join_wv_from_udp(
 /// ## Example
 /// ```rust
 /// let mut worldview = vec![/* some serialized data */];
-/// abort_network(&mut worldview);
+/// abort_network(&mut worldview, self_id);
 /// ```
 pub fn abort_network(
-    wv: &mut WorldView
+    wv: &mut WorldView,
+    self_id: u8
 ) -> bool 
 {
-    wv.elevator_containers.retain(|elevator| elevator.elevator_id == network::read_self_id());
+    wv.elevator_containers.retain(|elevator| elevator.elevator_id == self_id);
     wv.set_num_elev(wv.elevator_containers.len() as u8);
-    wv.master_id = network::read_self_id();
+    if wv.master_id != self_id 
+    {
+        wv.take_over(self_id);
+    }
     wv.hall_request = merge_hall_requests(&wv.hall_request, &wv.elevator_containers[0].tasks);
+    wv.update_hall_request_since(world_view::unix_millis());
     true
 }
 
@@ -174,19 +170,22 @@ pub fn abort_network(
 /// - Adds the elevator to the worldview if not already present.
 /// - Integrates unsent hall requests and cab requests into the global state.
 /// - Clears sent hall requests if the current node is the master.
-/// - If the elevator just served a request (based on floor + direction + timing), clears that request.
+/// - Clears the hall requests the elevator has served, and removes them from the elevator's tasks.
+///   A hall request which is also in the container's unsent hall requests was pressed again, and is kept.
+/// - Registers when new hall requests were first seen, see [WorldView::update_hall_request_since].
 /// - Backs up cab requests into the system-wide backup table for future recovery.
 ///
 /// ## Example
 /// ```
 /// let mut wv = WorldView::default();
 /// let cont = ElevatorContainer::new(1);
-/// let ok = join_wv_from_container(&mut wv, &cont).await;
+/// let ok = join_wv_from_container(&mut wv, &cont, 1).await;
 /// assert!(ok);
 /// ```
 pub async fn join_wv_from_container(
     wv: &mut WorldView, 
-    container: &ElevatorContainer
+    container: &ElevatorContainer,
+    self_id: u8
 ) -> bool 
 {
     // If the slave does not exist, add it as-is
@@ -195,6 +194,12 @@ pub async fn join_wv_from_container(
         wv.add_elev(container.clone());
     }
 
+    // Make room for the hall requests of elevators with more floors than the worldview
+    if wv.hall_request.len() < container.num_floors as usize 
+    {
+        wv.hall_request.resize(container.num_floors as usize, [false; 2]);
+    }
+
     let self_idx = world_view::get_index_to_container(container.elevator_id, &wv);
     if let Some(i) = self_idx 
     {
@@ -217,7 +222,7 @@ pub async fn join_wv_from_container(
         }
         
         // If you are master, this is your own container. You can then safely mark all hall_requests as sent and recieved by the master
-        if world_view::is_master(wv) 
+        if world_view::is_master(wv, self_id) 
         {
             wv.elevator_containers[i].unsent_hall_request = vec![[false; 2]; wv.elevator_containers[i].num_floors as usize];
         }
@@ -231,36 +236,28 @@ pub async fn join_wv_from_container(
         wv.elevator_containers[i].dirn = container.dirn;
         wv.elevator_containers[i].behaviour = container.behaviour;
         wv.elevator_containers[i].last_behaviour = container.last_behaviour;
+        wv.elevator_containers[i].hardware_available = container.hardware_available;
+        wv.elevator_containers[i].served_floors = container.served_floors.clone();
+        wv.elevator_containers[i].served_hall_request = container.served_hall_request.clone();
         
-        //Remove taken hall_requests
-        for (idx, [up, down]) in wv.hall_request.iter_mut().enumerate() 
+        //Remove served hall_requests. The elevator forgets them when they are no longer in its tasks
+        let elev = &mut wv.elevator_containers[i];
+        let served_and_unsent = elev.served_hall_request.iter().zip(container.unsent_hall_request.iter());
+        for ((hall, task), (served, unsent)) in wv.hall_request.iter_mut().zip(elev.tasks.iter_mut()).zip(served_and_unsent) 
         {
-            if (wv.elevator_containers[i].behaviour == ElevatorBehaviour::DoorOpen) && (wv.elevator_containers[i].last_floor_sensor == (idx as u8)) 
+            for call in 0..2 
             {
-                let floor = wv.elevator_containers[i].last_floor_sensor as usize;
-                let dirn = match wv.elevator_containers[i].dirn 
+                if served[call] && !unsent[call] 
                 {
-                    Dirn::Down => Some(1),
-                    Dirn::Up => Some(0),
-                    Dirn::Stop => None,
-                };
-
-                if wv.elevator_containers[i].last_behaviour != ElevatorBehaviour::DoorOpen 
-                {
-                    update_hall_instants(floor, Some(0));
-                    update_hall_instants(floor, Some(1));
-                }
-
-                if wv.elevator_containers[i].dirn == Dirn::Up  && time_since_hall_instants(floor, dirn) > Duration::from_secs(3) 
-                {
-                    *up = false;
-                } else if wv.elevator_containers[i].dirn == Dirn::Down && time_since_hall_instants(floor, dirn) > Duration::from_secs(3) 
-                {
-                    *down = false;
+                    hall[call] = false;
+                    task[call] = false;
                 }
             }
         }
 
+        // Register when new hall requests were first seen
+        wv.update_hall_request_since(world_view::unix_millis());
+
         // Back up the cab requests
         update_cab_request_backup(&mut wv.cab_requests_backup, wv.elevator_containers[i].clone());
 
@@ -314,6 +311,7 @@ pub fn remove_container(
 /// - `wv`: A mutable reference to a `Vec<u8>` representing the current worldview.
 /// - `tcp_container`: A vector containing the serialized data of the elevator container 
 ///   that was sent over TCP, including the tasks' status and call buttons.
+/// - `self_id`: The ID of this node.
 ///
 /// ## Return Value
 /// - Returns `true` if the update was successful and the worldview was modified.
@@ -323,14 +321,15 @@ pub fn remove_container(
 /// ```rust
 /// let mut worldview = vec![/* some serialized data */];
 /// let tcp_container = vec![/* some serialized container data */];
-/// clear_from_sent_data(&mut worldview, tcp_container);
+/// clear_from_sent_data(&mut worldview, tcp_container, self_id);
 /// ```
 pub fn clear_from_sent_data(
     wv: &mut WorldView, 
-    tcp_container: ElevatorContainer
+    tcp_container: ElevatorContainer,
+    self_id: u8
 ) -> bool 
 {
-    let self_idx = world_view::get_index_to_container(network::read_self_id() , &wv);
+    let self_idx = world_view::get_index_to_container(self_id, &wv);
     
     if let Some(i) = self_idx 
     {
@@ -358,28 +357,50 @@ pub fn clear_from_sent_data(
     }
 }
 
-/// This function allocates tasks from the given map to the corresponding elevator_container's tasks vector
+/// This function allocates tasks from the given assignment to the corresponding elevator_container's tasks vector
 /// 
 /// # Parameters
 /// `wv`: A mutable reference to a serialized worldview
+/// `assignment`: The tasks from the manager
 ///  
 /// # Behavior
+/// - Sets [WorldView::assignment_degraded] and [WorldView::hall_request_eta] from the assignment
+/// - Records a warning in [WorldView::task_timeout_warnings] for every task an elevator has timed out on
 /// - Iterates through every elevator_container in the worldview
-/// - If any tasks in the map matches the elevators ID, it sets the elevators tasks equal to the map's tasks
+/// - If any tasks in the assignment matches the elevators ID, it sets the elevators tasks equal to the assignment's tasks
+/// - Elevators without hardware get their tasks cleared, so the hall requests are given to other elevators
+/// - The tasks are cut or padded to the elevators number of floors, and tasks on floors the elevator does not serve are removed
 /// 
 /// # Return
 /// true
 /// 
 pub fn distribute_tasks(
     wv: &mut WorldView, 
-    map: HashMap<u8, Vec<[bool; 2]>>
+    assignment: TaskAssignment
 ) -> bool 
 {
+    wv.assignment_degraded = assignment.degraded;
+    wv.hall_request_eta = assignment.eta;
+    for id in assignment.timed_out 
+    {
+        *wv.task_timeout_warnings.entry(id).or_insert(0) += 1;
+    }
     for elev in wv.elevator_containers.iter_mut() 
     {
-        if let Some(tasks) = map.get(&elev.elevator_id) 
+        if let Some(tasks) = assignment.tasks.get(&elev.elevator_id) 
         {
             elev.tasks = tasks.clone();
+        } else if !elev.hardware_available 
+        {
+            elev.tasks = vec![[false; 2]; elev.tasks.len()];
+        }
+        elev.tasks.resize(elev.num_floors as usize, [false; 2]);
+        for (floor, task) in elev.tasks.iter_mut().enumerate() 
+        {
+            if !elev.served_floors.get(floor).copied().unwrap_or(false) 
+            {
+                *task = [false; 2];
+            }
         }
     }
     true
@@ -403,15 +424,24 @@ pub fn update_elev_states(
         wv.elevator_containers[i].last_behaviour = container.last_behaviour;
         wv.elevator_containers[i].last_floor_sensor = container.last_floor_sensor;
         wv.elevator_containers[i].unsent_hall_request = container.unsent_hall_request;
+        wv.elevator_containers[i].hardware_available = container.hardware_available;
+        wv.elevator_containers[i].served_floors = container.served_floors;
+        wv.elevator_containers[i].served_hall_request = container.served_hall_request;
     }
     true
 }
 
 /// Merges local worldview with networks worldview after being offline
 /// 
+/// Also used by a master which receives a broadcast from a master with a higher ID, to take over its network.
+/// 
 /// # Parameters
 /// `my_wv`: Mutable reference to the local worldview
 /// `read_wv`: Reference to the networks worldview
+/// 
+/// # Behavior
+/// - If the local master has the lowest ID, it takes over the network in a term newer than both worldviews' terms
+/// - Otherwise the network's worldview, and its term, is joined
 pub fn merge_wv_after_offline(
     my_wv: &mut WorldView, 
     read_wv: &mut WorldView) 
@@ -420,7 +450,8 @@ pub fn merge_wv_after_offline(
     if my_wv.master_id < read_wv.master_id 
     {
         read_wv.hall_request = merge_hall_requests(&read_wv.hall_request, &my_wv.hall_request);
-        read_wv.master_id = my_wv.master_id;
+        read_wv.term = read_wv.term.max(my_wv.term);
+        read_wv.take_over(my_wv.master_id);
         let my_wv_elevs: Vec<ElevatorContainer> = my_wv.elevator_containers.clone();
 
         /* Map the IDs in the networks worldview */
@@ -444,6 +475,7 @@ pub fn merge_wv_after_offline(
         read_wv.hall_request = merge_hall_requests(&read_wv.hall_request, &my_wv.hall_request);
     }
 
+    read_wv.update_hall_request_since(world_view::unix_millis());
     *my_wv = read_wv.clone();
 }
 
@@ -461,32 +493,6 @@ pub fn merge_wv_after_offline(
 
 /* _______________ START PRIVATE FUNCTIONS _______________ */
 
-fn update_hall_instants(
-    floor: usize, 
-    direction: Option<usize>
-) 
-{
-    if let Some(dirn) = direction 
-    {
-        let mut lock = HALL_INSTANTS.lock().unwrap();
-        lock[floor][dirn] = Instant::now();
-    }
-}
-
-fn time_since_hall_instants(
-    floor: usize, 
-    direction: Option<usize>
-) -> std::time::Duration 
-{
-    if let Some(dirn) = direction 
-    {
-        let lock = HALL_INSTANTS.lock().unwrap();
-        return lock[floor][dirn].elapsed()
-    }
-    return Instant::now().elapsed();
-}
-
-
 /// Updates the backup hashmap for cab_requests, så they are remembered on the network in the case of power loss on a node
 /// 
 /// ## Parameters
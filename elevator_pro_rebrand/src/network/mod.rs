@@ -5,6 +5,8 @@
 //! 
 //! ## Sub-modules
 //! - [udp_broadcast]
+//! - [wv_delta]
+//! - [protocol]
 //! - [local_network]
 //! 
 //! ## Key Features
@@ -14,14 +16,16 @@
 //! - Monitoring the network, automatically detecting connection loss and unoperatable levels of packetloss
 //! 
 //! ## Functions
-//! - `watch_ethernet`: Updates the network status, making sure the program detects connection loss and high packet loss
-//! - `read_network_status`: Gives a boolean indicating if your network connection is operatable.
+//! - `watch_ethernet`: Updates the network status in the [NodeContext], making sure the program detects connection loss and high packet loss
 
 pub mod udp_broadcast;
+pub mod wv_delta;
+pub mod protocol;
 pub mod local_network;
 pub mod udp_direct;
 
 
+use crate::node::NodeContext;
 use crate::world_view::WorldView;
 use crate::{init, config, print, ip_help_functions, world_view, };
 
@@ -30,8 +34,7 @@ use serde::{Serialize, Deserialize};
 use tokio::net::UdpSocket;
 use tokio::time::{timeout, Duration, Instant};
 use tokio::sync::{mpsc, watch};
-use std::sync::atomic::{Ordering, AtomicU8, AtomicBool};
-use std::sync::OnceLock;
+use std::sync::Arc;
 use std::thread::sleep;
 use local_ip_address::local_ip;
 use std::net::IpAddr;
@@ -40,11 +43,6 @@ use std::net::IpAddr;
 
 
 
-static ONLINE: OnceLock<AtomicBool> = OnceLock::new(); 
-
-/// Atomic bool storing self ID, standard inited as config::ERROR_ID
-pub static SELF_ID: OnceLock<AtomicU8> = OnceLock::new();
-
 /// Struct for wrapping network connection information
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ConnectionStatus 
@@ -89,6 +87,7 @@ impl ConnectionStatus
 /// when the system comes back online.
 /// 
 /// # Arguments
+/// - `ctx` - The node, whose network status is kept up to date.
 /// - `wv_watch_rx` - Receiver for world view updates.
 /// - `network_watch_tx` - Sender for broadcasting connection status updates.
 /// - `new_wv_after_offline_tx` - Sender for broadcasting the new world view after coming back online.
@@ -101,6 +100,7 @@ impl ConnectionStatus
 /// # Notes
 /// - The function loops indefinitely, periodically checking the network status and updating the connection status, and should therefore be called asyncronously
 pub async fn watch_ethernet(
+    ctx: Arc<NodeContext>,
     wv_watch_rx: watch::Receiver<WorldView>, 
     network_watch_tx: watch::Sender<ConnectionStatus>, 
     new_wv_after_offline_tx: mpsc::Sender<WorldView>
@@ -150,8 +150,8 @@ pub async fn watch_ethernet(
             {
                 // Went from offline -> online
                 let mut wv = world_view::get_wv(wv_watch_rx.clone());
-                let self_elev = world_view::extract_self_elevator_container(&wv);
-                wv = init::initialize_worldview(self_elev).await;
+                let self_elev = world_view::extract_self_elevator_container(&wv, ctx.id());
+                wv = init::initialize_worldview(&ctx, self_elev).await;
                 let _ = new_wv_after_offline_tx.send(wv).await;
 
                 print::ok("System is online".to_string());
@@ -159,7 +159,7 @@ pub async fn watch_ethernet(
             {
                 print::warn("System is offline".to_string());
             }
-            set_network_status(net_status);
+            ctx.set_online(net_status);
             last_net_status = net_status;
         }
 
@@ -167,47 +167,6 @@ pub async fn watch_ethernet(
     }
 }
 
-/// Reads and returns a clone of the current network status
-///
-/// This function returns a copy of the network status the moment it was read.
-/// that represents whether the system is online or offline.
-///
-/// # Returns
-/// A bool`:
-/// - `true` if the system is online.
-/// - `false` if the system is offline.
-/// 
-/// # Note
-/// - The initial value is `false` until explicitly changed. 
-/// - The returned value is only a clone of the atomic boolean's value at read-time. The function should be called every time you need to check the online-status
-pub fn read_network_status() -> bool 
-{
-    ONLINE.get_or_init(|| AtomicBool::new(false)).load(Ordering::SeqCst)
-}
-
-/// Reads and returns a clone of the current sself ID
-///
-/// This function returns a copy of the self ID.
-///
-/// # Returns
-/// u8: Your ID on the network
-/// 
-/// # Note
-/// - The value is [config::ERROR_ID] if [watch_ethernet] is not running.
-pub fn read_self_id() -> u8 
-{
-    SELF_ID.get_or_init(|| AtomicU8::new(config::ERROR_ID)).load(Ordering::SeqCst)
-}
-
-/// This function sets your self ID
-/// 
-/// # Note
-/// This function should not be used, as network ID is assigned automatically under initialisation
-pub fn set_self_id(id: u8) 
-{
-    SELF_ID.get_or_init(|| AtomicU8::new(config::ERROR_ID)).store(id, Ordering::SeqCst);
-}
-
 /* _______________ END PUB FUNCTIONS _______________ */
 
 
@@ -369,11 +328,6 @@ async fn start_packet_loss_monitor(
     rx
 }
 
-/// This function sets the network status
-fn set_network_status(status: bool) {
-    ONLINE.get_or_init(|| AtomicBool::new(false)).store(status, Ordering::SeqCst);
-}
-
 
 /* _______________ END PRIVATE FUNCTIONS _______________ */
 
@@ -0,0 +1,397 @@
+//! ## Wire protocol
+//!
+//! Every message the nodes send each other starts with a [Header]:
+//!
+//! | Bytes | Field          | Description                                          |
+//! |-------|----------------|------------------------------------------------------|
+//! | 0-3   | magic          | [MAGIC], tells our messages from other traffic        |
+//! | 4     | version        | [PROTOCOL_VERSION] of the sender                      |
+//! | 5     | kind           | The [MessageKind] of the payload                      |
+//! | 6     | sender id      | The ID of the sending node                            |
+//! | 7-14  | counter        | Strictly increasing per sender, `u64` little endian   |
+//! | 15-18 | payload length | Length of the payload, `u32` little endian            |
+//!
+//! The payload follows the header, and is parsed according to the kind and version.
+//! Last comes a [TAG_LEN] byte tag: the HMAC-SHA256 of the header and payload with the network key in [NodeConfig](crate::node::NodeConfig), truncated.
+//!
+//! ## Authentication
+//! Messages with a wrong tag are dropped before their payload is read, so only nodes with the key can send messages the
+//! other nodes act on. The counter is the sender's clock in microseconds, bumped to be strictly increasing.
+//! A receiver drops a message when
+//! - the counter is more than [config::MAX_CLOCK_SKEW] from its own clock, so old messages can not be replayed to a node
+//!   which has just started
+//! - it has already received the counter from the sender, or the counter is more than [config::REPLAY_WINDOW] behind
+//!   the newest one from the sender. Redundant copies of a message are dropped the same way.
+//!
+//! The nodes must therefore have clocks which agree to within [config::MAX_CLOCK_SKEW].
+//!
+//! ## Versions
+//! The version must be increased whenever the payload of a message kind changes, since bincode can not read
+//! payloads with a different layout. A node accepts messages with versions from [MIN_COMPATIBLE_VERSION] to its own
+//! [PROTOCOL_VERSION], so a newer build can keep reading older payloads while nodes are upgraded one by one.
+//! Messages with other versions, or of kinds this build does not know, are dropped, and a warning is printed the
+//! first time a node sends them.
+//!
+//! | Version | Changes                                 |
+//! |---------|-----------------------------------------|
+//! | 1       | First versioned protocol                |
+//! | 2       | Counter in the header, and a tag after the payload. Version 1 is not accepted, since it is not authenticated |
+//!
+//! ## Example
+//! ```
+//! use elevatorpro::network::protocol::{self, MessageKind, ProtocolError, HEADER_LEN, TAG_LEN};
+//! use elevatorpro::node::{NodeConfig, NodeContext};
+//!
+//! let sender = NodeContext::new(NodeConfig::from_config());
+//! sender.set_id(7);
+//! let receiver = NodeContext::new(NodeConfig::from_config());
+//! let key = receiver.config.network_key.clone();
+//!
+//! let msg = protocol::encode(&sender, MessageKind::Ack, &5u16.to_le_bytes());
+//! assert_eq!(msg.len(), HEADER_LEN + 2 + TAG_LEN);
+//!
+//! let (header, payload) = protocol::decode(&msg, &key).unwrap();
+//! assert_eq!(header.kind, MessageKind::Ack);
+//! assert_eq!(header.sender_id, 7);
+//! assert_eq!(payload, &5u16.to_le_bytes());
+//!
+//! // Accepted once, a replay is dropped
+//! assert!(protocol::accept(&receiver, &msg, MessageKind::Ack).is_some());
+//! assert!(protocol::accept(&receiver, &msg, MessageKind::Ack).is_none());
+//!
+//! // A forged or corrupted message
+//! let mut forged = msg.clone();
+//! forged[HEADER_LEN] ^= 1;
+//! assert_eq!(protocol::decode(&forged, &key), Err(ProtocolError::BadTag));
+//! assert_eq!(protocol::decode(&msg, b"Another key"), Err(ProtocolError::BadTag));
+//!
+//! // A node on an incompatible build
+//! let mut newer = msg.clone();
+//! newer[4] = protocol::PROTOCOL_VERSION + 1;
+//! assert_eq!(protocol::decode(&newer, &key), Err(ProtocolError::IncompatibleVersion(protocol::PROTOCOL_VERSION + 1)));
+//!
+//! // A cut off message
+//! let cut = &msg[..msg.len() - 1];
+//! assert_eq!(protocol::decode(cut, &key), Err(ProtocolError::LengthMismatch { expected: 2, actual: 1 }));
+//! ```
+
+use crate::config;
+use crate::node::NodeContext;
+use crate::print;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::mem::{self, Discriminant};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// First bytes of every message
+pub const MAGIC: [u8; 4] = *b"ELVP";
+
+/// The protocol version this build sends
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// The oldest protocol version this build can read
+pub const MIN_COMPATIBLE_VERSION: u8 = 2;
+
+/// Length of the [Header] in bytes
+pub const HEADER_LEN: usize = 19;
+
+/// Length of the truncated HMAC after the payload, in bytes
+pub const TAG_LEN: usize = 16;
+
+/// The protocol state of one node, kept in its [NodeContext]
+#[derive(Debug, Default)]
+pub struct ProtocolState
+{
+    /// The last counter the node has sent
+    last_counter: AtomicU64,
+
+    /// The counters received from each sender, per message kind
+    received: Mutex<HashMap<(u8, MessageKind), ReplayWindow>>,
+
+    /// Senders and errors already warned about, so every misconfigured node is only warned about once
+    warned: Mutex<HashSet<(u8, Discriminant<ProtocolError>)>>,
+}
+
+/// What the payload of a message is
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind
+{
+    /// A worldview broadcast, see [crate::network::wv_delta::BroadcastMessage]
+    WorldView = 1,
+
+    /// A slave's elevator container to the master, after its sequence number, see [crate::network::udp_direct]
+    Container = 2,
+
+    /// The master's acknowledgement of a container, with its sequence number
+    Ack = 3,
+}
+
+impl MessageKind
+{
+    /// The kind with the byte value, if it is known to this build
+    pub fn from_u8(
+        value: u8
+    ) -> Option<Self>
+    {
+        match value
+        {
+            1 => Some(MessageKind::WorldView),
+            2 => Some(MessageKind::Container),
+            3 => Some(MessageKind::Ack),
+            _ => None,
+        }
+    }
+}
+
+/// The header of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header
+{
+    /// The protocol version of the sender
+    pub version: u8,
+
+    /// What the payload is
+    pub kind: MessageKind,
+
+    /// The ID of the sending node
+    pub sender_id: u8,
+
+    /// The sender's counter, see the [module docs](self)
+    pub counter: u64,
+
+    /// Length of the payload in bytes
+    pub payload_len: u32,
+}
+
+/// Why a message was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError
+{
+    /// The message is shorter than the header and tag
+    TooShort,
+
+    /// The message does not start with [MAGIC], it is not from one of our nodes
+    BadMagic,
+
+    /// The sender uses a protocol version this build can not read
+    IncompatibleVersion(u8),
+
+    /// The message kind is not known to this build
+    UnknownKind(u8),
+
+    /// The payload length in the header does not match the message
+    LengthMismatch
+    {
+        /// The payload length in the header
+        expected: u32,
+        /// The length of the payload received
+        actual: usize,
+    },
+
+    /// The tag does not match the message, it is forged, corrupted, or sent with another key
+    BadTag,
+
+    /// The counter is too far from this node's clock
+    ClockSkew,
+
+    /// The message has already been received, or is too old
+    Replayed,
+}
+
+impl fmt::Display for ProtocolError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            ProtocolError::TooShort => write!(f, "message shorter than the header and tag"),
+            ProtocolError::BadMagic => write!(f, "unknown magic"),
+            ProtocolError::IncompatibleVersion(version) => write!(
+                f, "protocol version {} is not supported, this build reads {} to {}",
+                version, MIN_COMPATIBLE_VERSION, PROTOCOL_VERSION
+            ),
+            ProtocolError::UnknownKind(kind) => write!(f, "unknown message kind {}", kind),
+            ProtocolError::LengthMismatch { expected, actual } => write!(
+                f, "payload length {} does not match the header ({})", actual, expected
+            ),
+            ProtocolError::BadTag => write!(f, "wrong tag, check that the nodes use the same network key"),
+            ProtocolError::ClockSkew => write!(
+                f, "the sender's clock is more than {:?} off", config::MAX_CLOCK_SKEW
+            ),
+            ProtocolError::Replayed => write!(f, "message already received"),
+        }
+    }
+}
+
+/// Builds a message from `ctx` with a payload, with this build's [PROTOCOL_VERSION], the node's next counter,
+/// and a tag with the node's network key
+pub fn encode(
+    ctx: &NodeContext,
+    kind: MessageKind,
+    payload: &[u8]
+) -> Vec<u8>
+{
+    let counter = next_counter(&ctx.protocol);
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + payload.len() + TAG_LEN);
+    buf.extend_from_slice(&MAGIC);
+    buf.push(PROTOCOL_VERSION);
+    buf.push(kind as u8);
+    buf.push(ctx.id());
+    buf.extend_from_slice(&counter.to_le_bytes());
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(payload);
+
+    let tag = mac(&ctx.config.network_key, &buf).finalize().into_bytes();
+    buf.extend_from_slice(&tag[..TAG_LEN]);
+    buf
+}
+
+/// Splits a received message into its header and payload, checking the tag with `key`
+///
+/// The counter is not checked, see [accept].
+///
+/// ## Returns
+/// The header and payload, or why the message was rejected
+pub fn decode<'a>(
+    buf: &'a [u8],
+    key: &[u8]
+) -> Result<(Header, &'a [u8]), ProtocolError>
+{
+    if buf.len() < HEADER_LEN + TAG_LEN {return Err(ProtocolError::TooShort)}
+    if buf[0..4] != MAGIC {return Err(ProtocolError::BadMagic)}
+
+    let version = buf[4];
+    if !(MIN_COMPATIBLE_VERSION..=PROTOCOL_VERSION).contains(&version)
+    {
+        return Err(ProtocolError::IncompatibleVersion(version));
+    }
+
+    let kind = MessageKind::from_u8(buf[5]).ok_or(ProtocolError::UnknownKind(buf[5]))?;
+    let counter = u64::from_le_bytes(buf[7..15].try_into().unwrap());
+    let payload_len = u32::from_le_bytes(buf[15..19].try_into().unwrap());
+
+    let (signed, tag) = buf.split_at(buf.len() - TAG_LEN);
+    let payload = &signed[HEADER_LEN..];
+    if payload.len() != payload_len as usize
+    {
+        return Err(ProtocolError::LengthMismatch { expected: payload_len, actual: payload.len() });
+    }
+    if mac(key, signed).verify_truncated_left(tag).is_err()
+    {
+        return Err(ProtocolError::BadTag);
+    }
+
+    Ok((Header { version, kind, sender_id: buf[6], counter, payload_len }, payload))
+}
+
+/// Decodes a message received by `ctx`, keeping it only if it is authentic, new, and of the expected kind
+///
+/// ## Behavior
+/// Messages which are not ours, and replayed or redundant copies of messages, are dropped silently.
+/// Messages from nodes on an incompatible build, with another key, or with a clock too far off, are dropped with a
+/// warning the first time each node sends them.
+///
+/// ## Returns
+/// The header and payload, or `None` if the message was dropped
+pub fn accept<'a>(
+    ctx: &NodeContext,
+    buf: &'a [u8],
+    kind: MessageKind
+) -> Option<(Header, &'a [u8])>
+{
+    let result = decode(buf, &ctx.config.network_key).and_then(|(header, payload)| {
+        if header.kind != kind {return Ok(None)}
+        check_counter(&ctx.protocol, &header)?;
+        Ok(Some((header, payload)))
+    });
+
+    match result
+    {
+        Ok(message) => message,
+        Err(e @ (ProtocolError::IncompatibleVersion(_) | ProtocolError::UnknownKind(_) | ProtocolError::BadTag | ProtocolError::ClockSkew)) =>
+        {
+            let sender_id = buf[6];
+            if ctx.protocol.warned.lock().unwrap().insert((sender_id, mem::discriminant(&e)))
+            {
+                print::warn(format!("Dropping messages from node {}: {}", sender_id, e));
+            }
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+
+/// The HMAC-SHA256 of `data` with `key`, see the [module docs](self)
+fn mac(
+    key: &[u8],
+    data: &[u8]
+) -> Hmac<Sha256>
+{
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac
+}
+
+/// The counters received from one sender
+#[derive(Debug, Default)]
+struct ReplayWindow
+{
+    /// The counters received within [config::REPLAY_WINDOW] of the newest one, which is the last
+    received: BTreeSet<u64>,
+}
+
+/// Checks the counter of an authentic message against the clock and the counters already received from the sender
+fn check_counter(
+    state: &ProtocolState,
+    header: &Header
+) -> Result<(), ProtocolError>
+{
+    if now_micros().abs_diff(header.counter) > config::MAX_CLOCK_SKEW.as_micros() as u64
+    {
+        return Err(ProtocolError::ClockSkew);
+    }
+
+    let mut received = state.received.lock().unwrap();
+    let window = received.entry((header.sender_id, header.kind)).or_default();
+
+    let newest = window.received.last().copied().unwrap_or(0);
+    let oldest_allowed = newest.saturating_sub(config::REPLAY_WINDOW.as_micros() as u64);
+    if header.counter < oldest_allowed || !window.received.insert(header.counter)
+    {
+        return Err(ProtocolError::Replayed);
+    }
+
+    let oldest_allowed = window.received.last().unwrap().saturating_sub(config::REPLAY_WINDOW.as_micros() as u64);
+    window.received = window.received.split_off(&oldest_allowed);
+    Ok(())
+}
+
+/// The next counter of a node: the time in microseconds, or one more than the last counter if the clock has not moved
+fn next_counter(
+    state: &ProtocolState
+) -> u64
+{
+    let now = now_micros();
+    let last = state.last_counter
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| Some(now.max(last + 1)))
+        .unwrap();
+    now.max(last + 1)
+}
+
+/// The time since the Unix epoch in microseconds
+fn now_micros() -> u64
+{
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
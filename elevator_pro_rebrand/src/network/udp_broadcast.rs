@@ -10,27 +10,34 @@
 //! 
 //! ## Key Features
 //! - Uses a reusable UDP socket for broadcasting and listening.
-//! - Ensures messages are from the correct network by checking a predefined key string.
+//! - Ensures messages are from our nodes by authenticating them with the network key, see [crate::network::protocol].
+//! - Broadcasts full snapshots of the worldview now and then, and only the changes in between, see [crate::network::wv_delta].
 //! - Implements a watchdog mechanism to detect loss of connection to the master.
 //! 
 //! ## Functions
 //! 
 //! - [`start_udp_broadcaster`]: Sends worldview data over UDP if this node is the master.
 //! - [`start_udp_listener`]: Listens for worldview broadcasts from the master and updates state.
-//! - Private helper functions: [`build_message`], [`parse_message`].
+//! - [`send_snapshot_request`]: Asks the master for a full snapshot of the worldview.
+//! - [`build_message`], [`parse_message`]: Builds and parses the broadcasts.
 //! 
 //! ## Usage
 //! These functions should be called asynchronously in a Tokio runtime.
 
 use crate::config;
-use crate::network;
+use crate::network::protocol::{self, MessageKind};
+use crate::network::wv_delta::{BroadcastMessage, DeltaDecoder, DeltaEncoder, Undecoded};
+use crate::node::NodeContext;
 use crate::world_view;
 use crate::world_view::WorldView;
 
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::thread::sleep;
 use std::time::Duration;
 use tokio::net::UdpSocket;
+use tokio::time::Instant;
 use socket2::{Domain, Socket, Type};
 use tokio::sync::mpsc;
 use tokio::sync::watch;
@@ -42,21 +49,25 @@ use tokio::sync::watch;
 /// This function starts and runs the UDP-broadcaster
 /// 
 /// ## Parameters
+/// `ctx`: The node  
 /// `wv_watch_rx`: Rx on watch the worldview is being sent on in the system  
 /// 
 /// ## Behavior
 /// - Sets up a reusable socket on the udp-broadcast address
 /// - Continously reads the latest worldview, if self is master on the network, it broadcasts the worldview. 
+/// - Every broadcast gets the next sequence number in [WorldView::seq], starting over when the term changes.
+/// - Broadcasts a full snapshot every [snapshot period](crate::node::NodeConfig::snapshot_period), or when a node has asked for one, and only the changes in between, see [crate::network::wv_delta].
 /// 
 /// ## Note
 /// This function is permanently blocking, and should be called asynchronously
 pub async fn start_udp_broadcaster(
+    ctx: Arc<NodeContext>,
     wv_watch_rx: watch::Receiver<WorldView>
 ) -> tokio::io::Result<()> 
 {
-    while !network::read_network_status() {}
+    while !ctx.is_online() {}
 
-    let mut prev_network_status = network::read_network_status();
+    let mut prev_network_status = ctx.is_online();
 
     // Set up sockets
     let addr: &str = &format!("{}:{}", config::BC_ADDR, config::BROADCAST_PORT);
@@ -73,18 +84,32 @@ pub async fn start_udp_broadcaster(
     let udp_socket = UdpSocket::from_std(socket.into())?;
 
     let mut wv = world_view::get_wv(wv_watch_rx.clone());
+    let mut seq_term: Option<u64> = None;
+    let mut seq: u64 = 0;
+    let mut encoder = DeltaEncoder::default();
     loop
     {
         let wv_watch_rx_clone = wv_watch_rx.clone();
         world_view::update_wv(wv_watch_rx_clone, &mut wv).await;
         // If you currently are master on the network
-        if network::read_self_id() == wv.master_id 
+        if ctx.id() == wv.master_id 
         {
             sleep(config::UDP_PERIOD);
-            let message_bytes = build_message(&wv);
+
+            // Number the broadcasts, starting over in every new term
+            if seq_term != Some(wv.term) 
+            {
+                seq_term = Some(wv.term);
+                seq = 0;
+            }
+            seq += 1;
+            wv.seq = seq;
+            let force_snapshot = ctx.snapshot_requested.swap(false, Ordering::Relaxed);
+            let message = encoder.encode(&wv, Instant::now(), ctx.config.snapshot_period, force_snapshot);
+            let message_bytes = build_message(&ctx, &message);
 
             // If you are connected to internet
-            if network::read_network_status() 
+            if ctx.is_online() 
             {
                 // If you were not connected to internet last time you ran this
                 if !prev_network_status 
@@ -109,26 +134,32 @@ pub async fn start_udp_broadcaster(
 /// Starts and runs the UDP-listener
 /// 
 /// ## Parameters
+/// `ctx`: The node  
 /// `wv_watch_rx`: Rx on watch the worldview is being sent on in the system  
-/// `udp_wv_tx`: mpsc sender used to update [network::local_network::update_wv_watch] about new worldviews recieved over UDP
+/// `udp_wv_tx`: mpsc sender used to update [crate::network::local_network::update_wv_watch] about new worldviews recieved over UDP
 /// 
 /// ## Behaviour
 /// - Sets up a reusable listener listening for udp-broadcasts
 /// - Continously reads on the listener
-/// - Checks for key-string on all recieved messages, making sure the message is from one of 'our' nodes. 
-/// - If the message is from the current master or a node with lower ID than the current master, it sends it on `udp_wv_tx`
+/// - Authenticates all recieved messages, making sure the message is from one of 'our' nodes and not replayed, see [protocol::accept]. 
+/// - Rebuilds the worldview from the master's snapshots and deltas. If a delta is against a snapshot this node has missed,
+///   it asks for a new snapshot, at most every [config::SNAPSHOT_REQUEST_INTERVAL].
+/// - Passes snapshot requests from other nodes on to the broadcaster when this node is master.
+/// - If the worldview is newer than the newest one this node knows of, it sends it on `udp_wv_tx`.
+///   Stale broadcasts, from an older term or with an older sequence number, are dropped, see [world_view::accept_broadcast]
 /// 
 /// ## Note
 /// This function is permanently blocking, and should be called asynchronously 
 pub async fn start_udp_listener(
+    ctx: Arc<NodeContext>,
     wv_watch_rx: watch::Receiver<WorldView>, 
     udp_wv_tx: mpsc::Sender<WorldView>
 ) -> tokio::io::Result<()> 
 {
-    while !network::read_network_status() {}
+    while !ctx.is_online() {}
 
     //Set up sockets
-    let self_id = network::read_self_id();
+    let self_id = ctx.id();
     let broadcast_listen_addr = format!("{}:{}", config::BC_LISTEN_ADDR, config::BROADCAST_PORT);
     let socket_addr: SocketAddr = broadcast_listen_addr.parse().expect("Invalid address");
     let socket_temp = Socket::new(Domain::IPV4, Type::DGRAM, None)?;
@@ -142,6 +173,9 @@ pub async fn start_udp_listener(
     
     let mut read_wv: Option<WorldView>;
     let mut my_wv = world_view::get_wv(wv_watch_rx.clone());
+    let mut last_accepted: Option<WorldView> = None;
+    let mut decoder = DeltaDecoder::default();
+    let mut last_request: Option<Instant> = None;
 
     loop 
     {
@@ -150,7 +184,31 @@ pub async fn start_udp_listener(
         {
             Ok((len, _)) => 
             {
-                read_wv = parse_message(&buf[..len]);
+                let Some(message) = parse_message(&ctx, &buf[..len]) else {continue};
+                read_wv = match decoder.decode(message) 
+                {
+                    Ok(wv) => Some(wv),
+                    Err(Undecoded::Gap { term, .. }) => 
+                    {
+                        // Only resync with a master this node could accept broadcasts from
+                        let rate_limited = last_request.is_some_and(|t| t.elapsed() < config::SNAPSHOT_REQUEST_INTERVAL);
+                        if term >= my_wv.term && !rate_limited 
+                        {
+                            last_request = Some(Instant::now());
+                            let _ = send_snapshot_request(&ctx, &socket).await;
+                        }
+                        None
+                    }
+                    Err(Undecoded::SnapshotRequest) => 
+                    {
+                        if self_id == my_wv.master_id 
+                        {
+                            ctx.snapshot_requested.store(true, Ordering::Relaxed);
+                        }
+                        None
+                    }
+                    Err(Undecoded::Stale) => None,
+                };
             }
             Err(e) => 
             {
@@ -163,12 +221,18 @@ pub async fn start_udp_listener(
             Some(read_wv) => 
             {
                 world_view::update_wv(wv_watch_rx.clone(), &mut my_wv).await;
-                // Pass the recieved WorldView if the message came from the 
-                // master or a node with a lower ID than current master, 
-                // and this node is not the master
-                if my_wv.master_id >= read_wv.master_id
-                    && self_id != read_wv.master_id
+
+                // The local worldview lags behind the broadcasts already passed on
+                let current = match &last_accepted 
+                {
+                    Some(last) if last.supersedes(&my_wv) => last,
+                    _ => &my_wv,
+                };
+
+                // Pass the recieved WorldView on if it is newer than what this node knows of, see world_view::accept_broadcast
+                if world_view::accept_broadcast(current, &read_wv, self_id) 
                 {
+                    last_accepted = Some(read_wv.clone());
                     my_wv = read_wv;
                     let _ = udp_wv_tx.send(my_wv.clone()).await;
                 }
@@ -179,70 +243,70 @@ pub async fn start_udp_listener(
 }
 
 
-/* __________ END PUBLIC FUNCTIONS __________ */
-
-
-
-
-/* __________ START PRIVATE FUNCTIONS __________ */
-
+/// Asks the master for a full snapshot of the worldview
+/// 
+/// ## Parameters
+/// `ctx`: The node asking  
+/// `socket`: A socket with broadcast enabled
+/// 
+/// ## Behavior
+/// Broadcasts a [BroadcastMessage::SnapshotRequest]. The master's listener passes it on to the broadcaster,
+/// which sends a snapshot with its next broadcast.
+pub async fn send_snapshot_request(
+    ctx: &NodeContext,
+    socket: &UdpSocket
+) -> tokio::io::Result<()> 
+{
+    let addr: SocketAddr = format!("{}:{}", config::BC_ADDR, config::BROADCAST_PORT).parse().expect("Invalid address");
+    socket.send_to(&build_message(ctx, &BroadcastMessage::SnapshotRequest), addr).await?;
+    Ok(())
+}
 
-/// Builds the UDP-broadcast message from the worldview
+/// Builds the UDP-broadcast message
 /// 
 /// # Parameters
-/// `wv`: Reference to the current [WorldView]
+/// `ctx`: The sending node  
+/// `message`: The snapshot, delta or snapshot request to send
 /// 
 /// # Returns
 /// -`Vec<u8>`: Containing serialized data of the message, ready to be sent
 /// 
 /// # Behavior
-/// The function serializes a key, used for other nodes on the network to recognize this broadcast from others, 
-/// and appends the serialized data of the worldview.
-fn build_message(
-    wv: &WorldView
+/// The function serializes the message, and sends it behind a [protocol::Header] of kind [MessageKind::WorldView].
+/// The tag lets other nodes on the network recognize this broadcast from others.
+pub fn build_message(
+    ctx: &NodeContext,
+    message: &BroadcastMessage
 ) -> Vec<u8> 
 {
-    let mut buf = Vec::new();
-
-    // Add the serialized key
-    let key_bytes = world_view::serialize(&config::KEY_STR);
-    buf.extend_from_slice(&key_bytes);
-
-    // Add the serialized worldview
-    let wv_bytes = world_view::serialize(&wv);
-    buf.extend_from_slice(&wv_bytes);
-
-    buf
+    let message_bytes = world_view::serialize(message);
+    protocol::encode(ctx, MessageKind::WorldView, &message_bytes)
 }
 
-/// Reconstructs a [WorldView] from recieved UDP-message
+/// Reconstructs a [BroadcastMessage] from recieved UDP-message
 /// 
 /// # Parameters
+/// `ctx`: The receiving node  
 /// `buf`: Referance to a buffer containing the raw data read from UDP
 /// 
 /// # Returns
-/// -`Option<WorldView>`: A WorldView reconstructed from the data, if no errors occures
-/// -`None`: If an error occures while deserializing, or if the broadcast does not contain our key
+/// -`Option<BroadcastMessage>`: The message reconstructed from the data, if no errors occures
+/// -`None`: If an error occures while deserializing, or if the broadcast is not authentic
 /// 
 /// # Behavior
-/// The function first looks for the [config::KEY_STR] in the beginning og the message, returning `None` if it is not found.  
-/// If it is found, the function tries to deserialize a [WorldView] from the rest of the message, returning it wrapped in an `Option` if it succeeded, returning `None` if it failed. 
+/// The function first checks the [protocol::Header] and tag, returning `None` for messages which are forged, replayed,
+/// of other kinds or from incompatible builds, see [protocol::accept].  
+/// If the message is accepted, the function tries to deserialize a [BroadcastMessage] from the rest of the message, returning it wrapped in an `Option` if it succeeded, returning `None` if it failed. 
+/// Deltas must be decoded with a [DeltaDecoder] to give a [WorldView].
 pub fn parse_message(
+    ctx: &NodeContext,
     buf: &[u8]
-) -> Option<WorldView> 
+) -> Option<BroadcastMessage> 
 {
-    let key_len = bincode::serialized_size(config::KEY_STR).unwrap() as usize;
-
-    if buf.len() <= key_len {return None}
-
-    let (key_part, wv_part) = buf.split_at(key_len);
-    let key: String = bincode::deserialize(key_part).ok()?;
-
-    if key != config::KEY_STR {return None}
-
-    world_view::deserialize(wv_part)
+    let (_, payload) = protocol::accept(ctx, buf, MessageKind::WorldView)?;
+    world_view::deserialize(payload)
 }
 
 
-/* __________ END PRIVATE FUNCTIONS __________ */
+/* __________ END PUBLIC FUNCTIONS __________ */
 
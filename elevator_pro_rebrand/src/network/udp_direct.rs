@@ -38,6 +38,8 @@
 use crate::config;
 use crate::ip_help_functions;
 use crate::network;
+use crate::network::protocol::{self, MessageKind};
+use crate::node::NodeContext;
 use crate::print;
 use crate::world_view;
 use crate::world_view::ElevatorContainer;
@@ -79,6 +81,7 @@ struct ReceiverState
 /// UDP packets for communication. It handles both sending/receiving data from the master and sending/recieving data from slaves, based on the systems current role.
 /// 
 /// # Arguments
+/// - `ctx` - The node.
 /// - `wv_watch_rx` - Receiver for world view updates.
 /// - `container_tx` - Channel for sending received elevator containers to other parts of the system.
 /// - `packetloss_rx` - Receiver for tracking packet loss information.
@@ -96,6 +99,7 @@ struct ReceiverState
 /// - After socket setup, it enters a loop where it listens and sends UDP packets for slave-master communication.
 /// - The loop continues indefinitely, processing messages and sending responses as needed.
 pub async fn start_direct_udp_broadcast(
+    ctx: Arc<NodeContext>,
     wv_watch_rx: watch::Receiver<WorldView>,
     container_tx: mpsc::Sender<ElevatorContainer>,
     packetloss_rx: watch::Receiver<network::ConnectionStatus>,
@@ -104,7 +108,7 @@ pub async fn start_direct_udp_broadcast(
     sent_container_tx: mpsc::Sender<ElevatorContainer>,
 ) 
 {
-    while !network::read_network_status() {}
+    while !ctx.is_online() {}
     let socket = match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)) 
     {
         Ok(sock) => sock,
@@ -114,7 +118,7 @@ pub async fn start_direct_udp_broadcast(
     while socket.set_send_buffer_size(16_000_000).is_err() {}
     while socket.set_recv_buffer_size(16_000_000).is_err() {}
     
-    let addr: SocketAddr = format!("{}.{}:{}", config::NETWORK_PREFIX, network::read_self_id(), config::UDP_CONTAINER_PORT).parse().unwrap();
+    let addr: SocketAddr = format!("{}.{}:{}", config::NETWORK_PREFIX, ctx.id(), config::UDP_CONTAINER_PORT).parse().unwrap();
 
     while socket.bind(&addr.into()).is_err() {}
 
@@ -131,6 +135,7 @@ pub async fn start_direct_udp_broadcast(
     loop 
     {
         receive_udp_master(
+            &ctx,
             &socket,
             &mut wv,
             wv_watch_rx.clone(),
@@ -140,6 +145,7 @@ pub async fn start_direct_udp_broadcast(
         ).await;
         
         send_udp_slave(
+            &ctx,
             &socket,
             &mut wv,
             wv_watch_rx.clone(),
@@ -166,6 +172,7 @@ pub async fn start_direct_udp_broadcast(
 /// Listens for incoming UDP messages from slave nodes and processes them accordingly.
 /// 
 /// # Arguments
+/// - `ctx` - The node.
 /// - `socket` - The UDP socket used for communication.
 /// - `wv` - Mutable reference to the world view state.
 /// - `wv_watch_rx` - A [watch] receiver for world view updates.
@@ -185,6 +192,7 @@ pub async fn start_direct_udp_broadcast(
 /// - If packet loss is high, the redundancy factor for ACK messages increases.
 /// - This function should be run inside a Tokio task to prevent blocking.
 async fn receive_udp_master(
+    ctx: &Arc<NodeContext>,
     socket: &UdpSocket,
     wv: &mut WorldView,
     wv_watch_rx: watch::Receiver<WorldView>,
@@ -204,6 +212,7 @@ async fn receive_udp_master(
         let wv_watch_rx = wv_watch_rx.clone();
         let wv = wv.clone();
         monitor_slave_activity(
+            ctx.clone(),
             wv_watch_rx,
             wv,
             state_cleanup,
@@ -212,7 +221,7 @@ async fn receive_udp_master(
     }
 
     let mut buf = [0; 65535];
-    while wv.master_id == network::read_self_id() 
+    while wv.master_id == ctx.id() 
     {
         let (len, slave_addr) = match socket.try_recv_from(&mut buf) 
         {
@@ -243,7 +252,7 @@ async fn receive_udp_master(
         let last_seen = entry.last_seen;
         let last_seq = entry.last_seq.clone();
         
-        let msg = parse_message(&buf[..len], last_seq);
+        let msg = parse_message(ctx, &buf[..len], last_seq);
         
         match msg 
         {
@@ -272,6 +281,7 @@ async fn receive_udp_master(
                     let packetloss = packetloss_rx.borrow().clone();
                     let redundancy = get_redundancy(packetloss.packet_loss, last_seen).await;
                     send_acks(
+                        ctx,
                         &socket,
                         last_seq,
                         &slave_addr,
@@ -297,6 +307,7 @@ async fn receive_udp_master(
 /// and notifies the worldview updater.
 ///
 /// # Arguments
+/// * `ctx` - The node.
 /// * `wv_watch_rx` - A [watch] reciever to observe worldview updates.
 /// * `wv` - A mutable [`WorldView`] struct.
 /// * `state_cleanup` - A shared [HashMap] tracking the last known state of each slave,
@@ -313,6 +324,7 @@ async fn receive_udp_master(
 ///
 /// This function is essential for maintaining an up-to-date list of active nodes in the system.
 async fn monitor_slave_activity(
+    ctx: Arc<NodeContext>,
     wv_watch_rx: watch::Receiver<WorldView>,
     mut wv: WorldView,
     state_cleanup:  Arc<Mutex<HashMap<SocketAddr, ReceiverState>>>,
@@ -320,7 +332,7 @@ async fn monitor_slave_activity(
 )
 {
     tokio::spawn(async move {
-        while wv.master_id == network::read_self_id() 
+        while wv.master_id == ctx.id() 
         {
             sleep(CLEANUP_INTERVAL).await;
             {
@@ -350,17 +362,18 @@ async fn monitor_slave_activity(
     });
 }
 
-/// This functions acks `seq_num` `redundancy` times to `addr` on `socket`
+/// This functions acks `seq_num` `redundancy` times to `addr` on `socket`, from the node `ctx`
 async fn send_acks(
+    ctx: &NodeContext,
     socket: &UdpSocket, 
     seq_num: u16, 
     addr: &SocketAddr, 
     redundancy: usize
 ) 
 {
+    let data = protocol::encode(ctx, MessageKind::Ack, &seq_num.to_le_bytes());
     for _ in 0..redundancy 
     {
-        let data = seq_num.to_le_bytes();
         let _ = socket.send_to(&data, addr).await;
     }
 }
@@ -372,6 +385,7 @@ async fn send_acks(
 /// If sending fails, it signals a connection failure.
 /// 
 /// # Arguments
+/// * `ctx` - The node.
 /// * `socket` - A reference to the `UdpSocket` used for communication.
 /// * `wv` - A mutable reference to [`WorldView`].
 /// * `wv_watch_rx` - A [watch] reciever to receive worldview updates.
@@ -390,6 +404,7 @@ async fn send_acks(
 /// - Ensures robustness by detecting connection issues and handling packet loss.
 /// - Exits when the node becomes the master.
 async fn send_udp_slave(
+    ctx: &NodeContext,
     socket: &UdpSocket,
     wv: &mut WorldView,
     wv_watch_rx: watch::Receiver<WorldView>,
@@ -400,10 +415,10 @@ async fn send_udp_slave(
 {
     world_view::update_wv(wv_watch_rx.clone(), wv).await;
     let mut seq = 0;
-    while wv.master_id != network::read_self_id() 
+    while wv.master_id != ctx.id() 
     {
         world_view::update_wv(wv_watch_rx.clone(), wv).await;
-        let send = send_udp(socket, wv, packetloss_rx.clone(), 50, seq, 20, sent_container_tx.clone()).await;
+        let send = send_udp(ctx, socket, wv, packetloss_rx.clone(), seq, sent_container_tx.clone()).await;
         if send.is_err() 
         {
             print::err(format!("Failed to send to master: {:?}", send));
@@ -424,12 +439,11 @@ async fn send_udp_slave(
 /// 
 /// # Arguments
 /// 
+/// * `ctx` - The node.
 /// * `socket` - A reference to the `UdpSocket` used for communication.
 /// * `wv` - A reference to the `WorldView`, containing network and system state.
 /// * `packetloss_rx` - A `watch::Receiver<network::ConnectionStatus>` to monitor packet loss.
-/// * `timeout_ms` - The initial timeout in milliseconds before resending the packet.
 /// * `seq_num` - The sequence number assigned to the packet for tracking.
-/// * `retries` - The maximum number of retry attempts before failing.
 /// * `sent_container_tx` - An `mpsc::Sender<ElevatorContainer>` to send successfully transmitted data.
 /// 
 /// # Behavior
@@ -437,10 +451,10 @@ async fn send_udp_slave(
 /// - Determines the master's address based on `wv.master_id`.
 /// - Extracts the slave's elevator container from `WorldView`.
 /// - Sends the packet with redundancy based on current packet loss conditions.
-/// - Implements a linear backoff strategy, increasing timeout after each failure.
+/// - Implements a linear backoff strategy from [config::SLAVE_ACK_TIMEOUT], increasing timeout after each failure.
 /// - Listens for an acknowledgment from the master.
 /// - If the correct ACK is received, it updates `last_seen_from_master` and sends data to `sent_container_tx`.
-/// - If no ACK is received after `retries` attempts, it returns a timeout error.
+/// - If no ACK is received after [config::SLAVE_SEND_RETRIES] attempts, it returns a timeout error.
 /// 
 /// # Notes
 /// 
@@ -448,12 +462,11 @@ async fn send_udp_slave(
 /// - The backoff timeout increases by 5ms on each failure.
 /// - This function should be called within an async runtime.
 async fn send_udp(
+    ctx: &NodeContext,
     socket: &UdpSocket,
     wv: &WorldView,
     packetloss_rx: watch::Receiver<network::ConnectionStatus>,
-    timeout_ms: u64,
     seq_num: u16,
-    retries: u16,
     sent_container_tx: mpsc::Sender<ElevatorContainer>,
 )  -> std::io::Result<()> 
 {
@@ -464,10 +477,10 @@ async fn send_udp(
     let last_seen_from_master = Instant::now();
 
     let mut fails = 0;
-    let mut backoff_timeout_ms = timeout_ms;
+    let mut backoff_timeout_ms = config::SLAVE_ACK_TIMEOUT.as_millis() as u64;
 
     let mut should_send: bool = true;
-    let sent_cont = match world_view::extract_self_elevator_container(wv) 
+    let sent_cont = match world_view::extract_self_elevator_container(wv, ctx.id()) 
     {
         Some(cont) => cont.clone(),
         None => 
@@ -483,6 +496,7 @@ async fn send_udp(
             let packetloss = packetloss_rx.borrow().clone();
             let redundancy = get_redundancy(packetloss.packet_loss, last_seen_from_master).await;
             send_packet(
+                ctx,
                 &socket, 
                 seq_num, 
                 &server_addr, 
@@ -505,9 +519,9 @@ async fn send_udp(
             _ = timeout => 
             {
                 fails += 1;
-                if fails > retries 
+                if fails > config::SLAVE_SEND_RETRIES 
                 {
-                    return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("No Ack from master in {} retries!", retries)));
+                    return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("No Ack from master in {} retries!", config::SLAVE_SEND_RETRIES)));
                 }
                 should_send = true;
             },
@@ -515,7 +529,8 @@ async fn send_udp(
             {
                 if let Ok((len, _)) = result 
                 {
-                    let seq_opt: Option<[u8; 2]> = buf[..len].try_into().ok();
+                    let payload = protocol::accept(ctx, &buf[..len], MessageKind::Ack).map(|(_, payload)| payload);
+                    let seq_opt: Option<[u8; 2]> = payload.and_then(|payload| payload.try_into().ok());
                     if let Some(seq) = seq_opt 
                     {
                         if seq_num == u16::from_le_bytes(seq) 
@@ -539,6 +554,7 @@ async fn send_udp(
 /// to improve reliability in high packet loss environments.
 /// 
 /// # Arguments
+/// * `ctx` - The sending node.
 /// * `socket` - A reference to the `UdpSocket` used for sending data.
 /// * `seq_num` - The sequence number of the packet, used for tracking.
 /// * `addr` - The destination `SocketAddr` (typically the master node).
@@ -555,6 +571,7 @@ async fn send_udp(
 /// - The redundancy factor should be chosen based on network conditions.
 /// - This function does not wait for an acknowledgment; it only transmits packets.
 async fn send_packet(
+    ctx: &NodeContext,
     socket: &UdpSocket, 
     seq_num: u16, 
     addr: &SocketAddr, 
@@ -562,7 +579,7 @@ async fn send_packet(
     wv: &WorldView
 ) -> std::io::Result<()> 
 {
-    let data_opt = build_message(wv, &seq_num);
+    let data_opt = build_message(ctx, wv, &seq_num);
     if let Some(data) =  data_opt 
     {
         for _ in 0..redundancy 
@@ -577,10 +594,12 @@ async fn send_packet(
 }
 
 
-/// Builds a UDP message containing the sequence number and serialized elevator container.
+/// Builds a UDP message containing the sequence number and serialized elevator container,
+/// behind a [protocol::Header] of kind [MessageKind::Container].
 /// 
 /// Returns `None` if extracting the elevator container fails.
 fn build_message(
+    ctx: &NodeContext,
     wv: &WorldView,
     seq_num: &u16,
 ) -> Option<Vec<u8>> 
@@ -591,22 +610,26 @@ fn build_message(
     buf.extend_from_slice(&seq);
 
     
-    let cont = world_view::extract_self_elevator_container(&wv)?;
+    let cont = world_view::extract_self_elevator_container(&wv, ctx.id())?;
 
     let ec_bytes = world_view::serialize(&cont);
     buf.extend_from_slice(&ec_bytes);
 
-    Some(buf)
+    Some(protocol::encode(ctx, MessageKind::Container, &buf))
 }
 
 /// Parses a received UDP message and determines its validity.
 /// 
 /// Returns an `ElevatorContainer` if valid, along with a `RecieveCode` indicating the action to take.
+/// Messages of other kinds or from incompatible builds are ignored, see [protocol::accept].
 fn parse_message(
+    ctx: &NodeContext,
     buf: &[u8],
     expected_seq: u16,
 ) -> (Option<ElevatorContainer>, RecieveCode) 
 {
+    let Some((_, buf)) = protocol::accept(ctx, buf, MessageKind::Container) else {return (None, RecieveCode::Ignore)};
+
     if buf.len() < 2 
     {
         return (None, RecieveCode::Ignore);
@@ -0,0 +1,336 @@
+//! ## Delta-encoded worldview broadcasts
+//!
+//! Sending the whole [WorldView] every [config::UDP_PERIOD](crate::config::UDP_PERIOD) makes the broadcasts grow with the
+//! number of nodes and floors. Instead, the master sends a full snapshot now and then, and in between only a
+//! [WorldViewDelta] with what has changed since the last snapshot.
+//!
+//! Every delta is against the last snapshot, not the previous delta, so a lost delta is harmless: the next one carries
+//! its changes too. A receiver which has missed the snapshot a delta is based on has a gap, and asks the master for
+//! a new snapshot with [BroadcastMessage::SnapshotRequest].
+//!
+//! ## When a snapshot is sent
+//! - On the first broadcast in a term
+//! - When [config::SNAPSHOT_PERIOD](crate::config::SNAPSHOT_PERIOD) has passed since the last snapshot
+//! - When a receiver has asked for one
+//! - When the delta has grown to more than half the size of a snapshot
+//!
+//! ## Example
+//! ```
+//! use std::time::Duration;
+//! use tokio::time::Instant;
+//! use elevatorpro::network::wv_delta::{BroadcastMessage, DeltaDecoder, DeltaEncoder, Undecoded};
+//! use elevatorpro::world_view::WorldView;
+//!
+//! let mut wv = WorldView::with_elevators(4, &[(10, 0), (20, 0), (30, 0)]);
+//! wv.take_over(10);
+//!
+//! let period = Duration::from_secs(1);
+//! let now = Instant::now();
+//! let mut encoder = DeltaEncoder::default();
+//! let mut decoder = DeltaDecoder::default();
+//!
+//! // A receiver joining after the snapshot has a gap
+//! wv.seq = 1;
+//! let snapshot = encoder.encode(&wv, now, period, false);
+//! assert!(matches!(snapshot, BroadcastMessage::Snapshot(_)));
+//!
+//! wv.seq = 2;
+//! wv.hall_request[1] = [true, false];
+//! let delta = encoder.encode(&wv, now, period, false);
+//! assert!(matches!(delta, BroadcastMessage::Delta(_)));
+//! assert_eq!(decoder.decode(delta.clone()), Err(Undecoded::Gap { master_id: 10, term: wv.term }));
+//!
+//! // With the snapshot, the delta gives the master's worldview
+//! decoder.decode(snapshot.clone()).unwrap();
+//! assert_eq!(decoder.decode(delta.clone()), Ok(wv.clone()));
+//!
+//! // The delta is much smaller than the snapshot
+//! let size = |msg: &BroadcastMessage| bincode::serialized_size(msg).unwrap();
+//! assert!(size(&delta) * 4 < size(&snapshot));
+//!
+//! // The next snapshot goes out when the period has passed
+//! wv.seq = 3;
+//! assert!(matches!(encoder.encode(&wv, now + period, period, false), BroadcastMessage::Snapshot(_)));
+//! ```
+
+use crate::world_view::{ElevatorContainer, HallCallEta, WorldView};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+
+/// A worldview broadcast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BroadcastMessage
+{
+    /// The master's full worldview
+    Snapshot(WorldView),
+
+    /// The changes in the master's worldview since its last snapshot
+    Delta(WorldViewDelta),
+
+    /// A receiver asks the master for a new snapshot
+    SnapshotRequest,
+}
+
+/// The changes in a worldview since a snapshot
+///
+/// Fields which have not changed are `None`, or left out of the lists.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldViewDelta
+{
+    /// The master term of the worldview
+    pub term: u64,
+
+    /// The master of the worldview
+    pub master_id: u8,
+
+    /// The sequence number of the broadcast
+    pub seq: u64,
+
+    /// The sequence number of the snapshot the delta is against
+    pub base_seq: u64,
+
+    /// The number of elevators, if changed
+    pub num_elev: Option<u8>,
+
+    /// The hall requests, if changed
+    pub hall_request: Option<Vec<[bool; 2]>>,
+
+    /// The IDs of the elevators, in worldview order
+    pub elevator_ids: Vec<u8>,
+
+    /// The elevators which are new or have changed
+    pub elevators: Vec<ElevatorContainer>,
+
+    /// The cab request backups which are new or have changed
+    pub cab_requests_backup: Vec<(u8, Vec<bool>)>,
+
+    /// The IDs of the cab request backups which are removed
+    pub removed_cab_requests_backup: Vec<u8>,
+
+    /// Whether the assignment is degraded, if changed
+    pub assignment_degraded: Option<bool>,
+
+    /// The estimated times of arrival, if changed
+    pub hall_request_eta: Option<Vec<[Option<HallCallEta>; 2]>>,
+
+    /// When the hall requests were registered, if changed
+    pub hall_request_since: Option<Vec<[Option<u64>; 2]>>,
+
+    /// The task timeout warnings, if changed
+    pub task_timeout_warnings: Option<HashMap<u8, u32>>,
+}
+
+impl WorldViewDelta
+{
+    /// Computes the changes from `snapshot` to `wv`
+    pub fn diff(
+        snapshot: &WorldView,
+        wv: &WorldView
+    ) -> Self
+    {
+        WorldViewDelta
+        {
+            term: wv.term,
+            master_id: wv.master_id,
+            seq: wv.seq,
+            base_seq: snapshot.seq,
+            num_elev: changed(&snapshot.get_num_elev(), &wv.get_num_elev()),
+            hall_request: changed(&snapshot.hall_request, &wv.hall_request),
+            elevator_ids: wv.elevator_containers.iter().map(|elev| elev.elevator_id).collect(),
+            elevators: wv.elevator_containers.iter()
+                .filter(|elev| find_elevator(snapshot, elev.elevator_id) != Some(*elev))
+                .cloned()
+                .collect(),
+            cab_requests_backup: wv.cab_requests_backup.iter()
+                .filter(|(id, cab)| snapshot.cab_requests_backup.get(*id) != Some(*cab))
+                .map(|(id, cab)| (*id, cab.clone()))
+                .collect(),
+            removed_cab_requests_backup: snapshot.cab_requests_backup.keys()
+                .filter(|id| !wv.cab_requests_backup.contains_key(*id))
+                .copied()
+                .collect(),
+            assignment_degraded: changed(&snapshot.assignment_degraded, &wv.assignment_degraded),
+            hall_request_eta: changed(&snapshot.hall_request_eta, &wv.hall_request_eta),
+            hall_request_since: changed(&snapshot.hall_request_since, &wv.hall_request_since),
+            task_timeout_warnings: changed(&snapshot.task_timeout_warnings, &wv.task_timeout_warnings),
+        }
+    }
+
+    /// Applies the changes to the snapshot the delta is against
+    ///
+    /// ## Returns
+    /// The worldview, or `None` if an elevator is neither in the delta nor in the snapshot
+    pub fn apply(
+        &self,
+        snapshot: &WorldView
+    ) -> Option<WorldView>
+    {
+        let mut wv = snapshot.clone();
+        wv.term = self.term;
+        wv.master_id = self.master_id;
+        wv.seq = self.seq;
+
+        if let Some(n) = self.num_elev {wv.set_num_elev(n)}
+        if let Some(hall_request) = &self.hall_request {wv.hall_request = hall_request.clone()}
+        if let Some(degraded) = self.assignment_degraded {wv.assignment_degraded = degraded}
+        if let Some(eta) = &self.hall_request_eta {wv.hall_request_eta = eta.clone()}
+        if let Some(since) = &self.hall_request_since {wv.hall_request_since = since.clone()}
+        if let Some(warnings) = &self.task_timeout_warnings {wv.task_timeout_warnings = warnings.clone()}
+
+        wv.elevator_containers = self.elevator_ids.iter()
+            .map(|id| {
+                self.elevators.iter()
+                    .find(|elev| elev.elevator_id == *id)
+                    .or_else(|| find_elevator(snapshot, *id))
+                    .cloned()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        for id in self.removed_cab_requests_backup.iter()
+        {
+            wv.cab_requests_backup.remove(id);
+        }
+        wv.cab_requests_backup.extend(self.cab_requests_backup.iter().cloned());
+
+        Some(wv)
+    }
+}
+
+/// Encodes the master's broadcasts, see the [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct DeltaEncoder
+{
+    /// The last snapshot sent, and when it was sent
+    snapshot: Option<(WorldView, Instant)>,
+}
+
+impl DeltaEncoder
+{
+    /// Encodes the next broadcast of `wv`
+    ///
+    /// ## Parameters
+    /// `wv`: The master's worldview, with the sequence number of this broadcast
+    /// `now`: The current time
+    /// `period`: The time between two snapshots
+    /// `force_snapshot`: Sends a snapshot regardless, e.g. when a receiver has asked for one
+    ///
+    /// ## Returns
+    /// A [BroadcastMessage::Snapshot] or a [BroadcastMessage::Delta]
+    pub fn encode(
+        &mut self,
+        wv: &WorldView,
+        now: Instant,
+        period: Duration,
+        force_snapshot: bool
+    ) -> BroadcastMessage
+    {
+        if let Some((snapshot, sent)) = &self.snapshot
+        {
+            let same_term = snapshot.term == wv.term && snapshot.master_id == wv.master_id;
+            if !force_snapshot && same_term && now.duration_since(*sent) < period
+            {
+                let delta = WorldViewDelta::diff(snapshot, wv);
+                let delta_size = bincode::serialized_size(&delta).unwrap_or(u64::MAX);
+                let snapshot_size = bincode::serialized_size(wv).unwrap_or(0);
+                if delta_size.saturating_mul(2) <= snapshot_size
+                {
+                    return BroadcastMessage::Delta(delta);
+                }
+            }
+        }
+
+        self.snapshot = Some((wv.clone(), now));
+        BroadcastMessage::Snapshot(wv.clone())
+    }
+}
+
+/// A received broadcast which did not give a worldview, see [DeltaDecoder::decode]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Undecoded
+{
+    /// A delta against a snapshot which was not received. A new snapshot should be requested from the master.
+    Gap
+    {
+        /// The master which sent the delta
+        master_id: u8,
+        /// The term of the delta
+        term: u64,
+    },
+
+    /// A receiver has asked for a new snapshot
+    SnapshotRequest,
+
+    /// A delta against a snapshot older than the last one received, which is dropped
+    Stale,
+}
+
+/// Decodes the broadcasts, keeping the last snapshot from each master
+#[derive(Debug, Clone, Default)]
+pub struct DeltaDecoder
+{
+    snapshots: HashMap<u8, WorldView>,
+}
+
+impl DeltaDecoder
+{
+    /// Decodes a received broadcast
+    ///
+    /// Snapshots are stored, and returned as they are. Deltas are applied to the stored snapshot from the same master,
+    /// if it is the snapshot they are against.
+    ///
+    /// ## Returns
+    /// The master's worldview, or why the broadcast did not give one
+    pub fn decode(
+        &mut self,
+        msg: BroadcastMessage
+    ) -> Result<WorldView, Undecoded>
+    {
+        match msg
+        {
+            BroadcastMessage::Snapshot(wv) =>
+            {
+                self.snapshots.insert(wv.master_id, wv.clone());
+                Ok(wv)
+            }
+            BroadcastMessage::Delta(delta) =>
+            {
+                let gap = Undecoded::Gap { master_id: delta.master_id, term: delta.term };
+                let Some(snapshot) = self.snapshots.get(&delta.master_id) else {return Err(gap)};
+
+                if snapshot.term == delta.term && snapshot.seq == delta.base_seq
+                {
+                    return delta.apply(snapshot).ok_or(gap);
+                }
+                if (delta.term, delta.base_seq) < (snapshot.term, snapshot.seq)
+                {
+                    return Err(Undecoded::Stale);
+                }
+                Err(gap)
+            }
+            BroadcastMessage::SnapshotRequest => Err(Undecoded::SnapshotRequest),
+        }
+    }
+}
+
+
+/// `Some(new)` if it differs from `old`
+fn changed<T: Clone + PartialEq>(
+    old: &T,
+    new: &T
+) -> Option<T>
+{
+    (old != new).then(|| new.clone())
+}
+
+/// The elevator with the ID in the worldview
+fn find_elevator(
+    wv: &WorldView,
+    id: u8
+) -> Option<&ElevatorContainer>
+{
+    wv.elevator_containers.iter().find(|elev| elev.elevator_id == id)
+}
@@ -0,0 +1,236 @@
+//! ## Node context
+//!
+//! Everything which makes a process one node in the elevator network: its ID, whether it is online, its network
+//! configuration, and the state of the [wire protocol](crate::network::protocol). A [NodeContext] is made at startup
+//! and passed to every task, instead of the tasks reading process-wide statics, so several nodes can run in one
+//! process, e.g. in a test.
+//!
+//! The options given at startup are read into a [NodeConfig] with [NodeConfig::from_config]. The options in [crate::config]
+//! which are not in [NodeConfig], like the prints, are shared by all nodes in the process.
+//!
+//! ## Example
+//! ```
+//! use elevatorpro::network::protocol::{self, MessageKind};
+//! use elevatorpro::node::{NodeConfig, NodeContext};
+//!
+//! // Three nodes in one process
+//! let nodes: Vec<_> = [10, 20, 30].iter().map(|id| {
+//!     let ctx = NodeContext::new(NodeConfig::from_config());
+//!     ctx.set_id(*id);
+//!     ctx
+//! }).collect();
+//! assert_eq!(nodes[1].id(), 20);
+//! assert!(!nodes[1].is_online());
+//!
+//! // Every node keeps track of the messages it has received itself
+//! let msg = protocol::encode(&nodes[0], MessageKind::Ack, &[1, 0]);
+//! assert!(protocol::accept(&nodes[1], &msg, MessageKind::Ack).is_some());
+//! assert!(protocol::accept(&nodes[2], &msg, MessageKind::Ack).is_some());
+//! assert!(protocol::accept(&nodes[2], &msg, MessageKind::Ack).is_none());
+//! ```
+//!
+//! Two nodes with their own simulated elevator and number of floors, running side by side in one process.
+//! They are not connected to each other, so each is master of its own network:
+//! ```
+//! # #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
+//! # async fn main() {
+//! use elevatorpro::{elevator_logic, init, manager};
+//! use elevatorpro::elevio::driver::AsyncElevator;
+//! use elevatorpro::elevio::simulator::{ElevatorSimulator, SimConfig};
+//! use elevatorpro::network::local_network::{self, Mpscs};
+//! use elevatorpro::node::{NodeConfig, NodeContext};
+//! use std::time::Duration;
+//! use tokio::sync::watch;
+//!
+//! let mut sims = Vec::new();
+//! let mut wv_rxs = Vec::new();
+//! for (id, num_floors) in [(10, 4), (20, 6)]
+//! {
+//!     let ctx = NodeContext::new(NodeConfig { num_floors, ..NodeConfig::from_config() });
+//!     ctx.set_id(id);
+//!
+//!     let sim_config = SimConfig { num_floors, travel_time: Duration::from_millis(500), sensor_width: 0.25, ..Default::default() };
+//!     let sim = ElevatorSimulator::start("127.0.0.1:0", sim_config).unwrap();
+//!     let elevator = AsyncElevator::connect(&sim.addr().to_string(), num_floors, ctx.config.poll.period);
+//!
+//!     // The ID set above is kept, instead of being taken from the IP address
+//!     let mut wv = init::initialize_worldview(&ctx, None).await;
+//!     assert_eq!((ctx.id(), wv.master_id), (id, id));
+//!     assert_eq!(wv.elevator_containers[0].num_floors, num_floors);
+//!
+//!     let mpscs = Mpscs::new();
+//!     let (wv_tx, wv_rx) = watch::channel(wv.clone());
+//!     let ctx_c = ctx.clone();
+//!     tokio::spawn(async move { local_network::update_wv_watch(ctx_c, mpscs.rxs, wv_tx, &mut wv).await });
+//!     tokio::spawn(elevator_logic::run_local_elevator_with_io(ctx.clone(), elevator, wv_rx.clone(), mpscs.txs.elevator_states));
+//!     tokio::spawn(manager::start_manager(ctx.clone(), wv_rx.clone(), mpscs.txs.delegated_tasks));
+//!
+//!     sims.push(sim);
+//!     wv_rxs.push(wv_rx);
+//! }
+//!
+//! // A hall call on the second node is served by its own elevator, on a floor the first node does not have
+//! tokio::time::sleep(Duration::from_millis(500)).await;
+//! sims[1].press_call_button(4, 0);
+//! let mut served = false;
+//! for _ in 0..400
+//! {
+//!     tokio::time::sleep(Duration::from_millis(50)).await;
+//!     served = sims[1].floor_indicator() == 4 && sims[1].door_light();
+//!     if served {break}
+//! }
+//! assert!(served);
+//!
+//! // The first node is not affected
+//! assert_eq!(sims[0].floor_indicator(), 0);
+//! let wv = wv_rxs[0].borrow().clone();
+//! assert_eq!((wv.master_id, wv.hall_request.len()), (10, 4));
+//! assert!(wv.hall_request.iter().all(|calls| *calls == [false; 2]));
+//! # }
+//! ```
+
+use crate::config;
+use crate::elevator_logic::request::ServingPolicyKind;
+use crate::elevator_logic::timer::TimerConfig;
+use crate::elevio::poll::PollConfig;
+use crate::manager::assigner::HallAssignerKind;
+use crate::network::protocol::ProtocolState;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+
+/// The configuration of one node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeConfig
+{
+    /// Pre-shared key the network messages are authenticated with, see [config::NETWORK_KEY]
+    pub network_key: Vec<u8>,
+
+    /// Time between two full worldview snapshots when master, see [config::SNAPSHOT_PERIOD]
+    pub snapshot_period: Duration,
+
+    /// Number of floors of the node's elevator, see [config::NUM_FLOORS]
+    pub num_floors: u8,
+
+    /// The floors the node's elevator serves, all if `None`, see [config::SERVED_FLOORS]
+    pub served_floors: Option<Vec<u8>>,
+
+    /// Which requests the node's elevator serves when it stops, see [config::SERVING_POLICY]
+    pub serving_policy: ServingPolicyKind,
+
+    /// Durations of the FSM timers, see [config::ELEV_TIMERS]
+    pub timers: TimerConfig,
+
+    /// Poll period and debouncing of the elevator inputs, see [config::ELEV_POLL_CONFIG]
+    pub poll: PollConfig,
+
+    /// Whether the node runs its elevator against the in-process simulator, see [config::ELEV_SIMULATOR_ON]
+    pub elev_simulator: bool,
+
+    /// How the node assigns hall requests when master, see [config::HALL_ASSIGNER]
+    pub hall_assigner: HallAssignerKind,
+
+    /// Margin for moving a hall request from an elevator making progress on it, see [config::ASSIGNMENT_STABILITY_MARGIN]
+    pub stability_margin: Option<Duration>,
+
+    /// Time before a hall request is starving, see [config::STARVATION_TIME]
+    pub starvation_time: Duration,
+
+    /// File the node's assignments are traced to, see [config::ASSIGNMENT_TRACE]
+    pub assignment_trace: Option<PathBuf>,
+}
+
+impl NodeConfig
+{
+    /// Reads the configuration from the options given at startup, see [crate::init::parse_args]
+    pub fn from_config() -> Self
+    {
+        NodeConfig
+        {
+            network_key: config::NETWORK_KEY.lock().unwrap().clone(),
+            snapshot_period: *config::SNAPSHOT_PERIOD.lock().unwrap(),
+            num_floors: *config::NUM_FLOORS.lock().unwrap(),
+            served_floors: config::SERVED_FLOORS.lock().unwrap().clone(),
+            serving_policy: *config::SERVING_POLICY.lock().unwrap(),
+            timers: *config::ELEV_TIMERS.lock().unwrap(),
+            poll: *config::ELEV_POLL_CONFIG.lock().unwrap(),
+            elev_simulator: *config::ELEV_SIMULATOR_ON.lock().unwrap(),
+            hall_assigner: *config::HALL_ASSIGNER.lock().unwrap(),
+            stability_margin: *config::ASSIGNMENT_STABILITY_MARGIN.lock().unwrap(),
+            starvation_time: *config::STARVATION_TIME.lock().unwrap(),
+            assignment_trace: config::ASSIGNMENT_TRACE.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// One node in the elevator network, see the [module docs](self)
+#[derive(Debug)]
+pub struct NodeContext
+{
+    /// The ID of the node, [config::ERROR_ID] until it is set from the IP address
+    id: AtomicU8,
+
+    /// Whether the node is connected to the elevator network
+    online: AtomicBool,
+
+    /// The configuration of the node
+    pub config: NodeConfig,
+
+    /// The counters sent and received by the node
+    pub protocol: ProtocolState,
+
+    /// Set by the UDP listener when another node asks for a snapshot, and cleared by the broadcaster when it has sent one,
+    /// see [crate::network::udp_broadcast]
+    pub(crate) snapshot_requested: AtomicBool,
+}
+
+impl NodeContext
+{
+    /// Creates an offline node with [config::ERROR_ID] as ID
+    pub fn new(
+        config: NodeConfig
+    ) -> Arc<Self>
+    {
+        Arc::new(NodeContext
+        {
+            id: AtomicU8::new(config::ERROR_ID),
+            online: AtomicBool::new(false),
+            config,
+            protocol: ProtocolState::default(),
+            snapshot_requested: AtomicBool::new(false),
+        })
+    }
+
+    /// The ID of the node on the network
+    pub fn id(&self) -> u8
+    {
+        self.id.load(Ordering::SeqCst)
+    }
+
+    /// Sets the ID of the node. If it is not set before [crate::init::initialize_worldview], it is assigned from the IP address.
+    pub fn set_id(
+        &self,
+        id: u8
+    )
+    {
+        self.id.store(id, Ordering::SeqCst);
+    }
+
+    /// Whether the node is connected to the elevator network, kept up to date by [crate::network::watch_ethernet]
+    pub fn is_online(&self) -> bool
+    {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    /// Sets whether the node is connected to the elevator network
+    pub fn set_online(
+        &self,
+        online: bool
+    )
+    {
+        self.online.store(online, Ordering::SeqCst);
+    }
+}
@@ -6,7 +6,7 @@
 
 use crate::config;
 use crate::network;
-use crate::world_view::{Dirn, ElevatorBehaviour, WorldView};
+use crate::world_view::{self, Dirn, ElevatorBehaviour, WorldView};
 
 use ansi_term::Colour::{self, Green, Red, Yellow, Purple, White};
 use unicode_width::UnicodeWidthStr;
@@ -482,6 +482,30 @@ pub fn worldview(worldview: &WorldView, connection: Option<network::ConnectionSt
 
     println!("└─────────────┴──────────┴────────────────────┘");
 
+    if worldview.assignment_degraded 
+    {
+        println!("{}", Yellow.bold().paint("Reservefordeling av hall-ordrar er aktiv"));
+    }
+
+    for (id, count) in worldview.task_timeout_warnings.iter() 
+    {
+        println!("{}", Yellow.paint(format!("Heis {} har gått ut på tid på {} oppdrag", id, count)));
+    }
+
+    // Forventa ankomst for kvar hall-ordre, t.d. "Floor 3 ↑: car 12, ~8 s"
+    let now_ms = world_view::unix_millis();
+    for (floor, etas) in worldview.hall_request_eta.iter().enumerate().rev() 
+    {
+        for (arrow, eta) in ["↑", "↓"].iter().zip(etas.iter()) 
+        {
+            if let Some(eta) = eta 
+            {
+                let remaining_s = eta.arrival_ms.saturating_sub(now_ms) as f32 / 1000.0;
+                println!("Floor {} {}: car {}, ~{} s", floor, arrow, eta.elevator_id, remaining_s.round());
+            }
+        }
+    }
+
     // Heisstatus-tabell
     println!("┌──────┬──────────┬──────────────┬──────────────┬─────────────┬──────────────────────┬───────────────┐");
     println!("{}", ansi_term::Colour::White.bold().paint("│ ID   │ Dør      │ Obstruksjon  │ Tasks        │ Siste etasje│ Calls (Etg:Call)     │ Elev status   │"));
@@ -530,6 +554,7 @@ pub fn worldview(worldview: &WorldView, connection: Option<network::ConnectionSt
 
         let task_status = match (elev.dirn, elev.behaviour) 
         {
+            _ if !elev.hardware_available => pad_text(&Red.paint("No hardware").to_string(), 22),
             (_, ElevatorBehaviour::Idle) => pad_text(&Green.paint("Idle").to_string(), 22),
             (Dirn::Up, ElevatorBehaviour::Moving) => pad_text(&Yellow.paint("⬆️   Moving").to_string(), 23),
             (Dirn::Down, ElevatorBehaviour::Moving) => pad_text(&Yellow.paint("⬇️   Moving").to_string(), 23),
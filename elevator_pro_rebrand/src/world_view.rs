@@ -21,6 +21,7 @@
 //! - [`Dirn`] – Represents the movement direction of an elevator.
 //! - [`ElevatorBehaviour`] – Describes the current state of an elevator.
 //! - [`ElevatorContainer`] – Holds information about an individual elevator's state, tasks, and requests.
+//! - [`HallCallEta`] – The elevator assigned to a hall request, and when it is expected.
 //! - [`WorldView`] – Contains global network state, including all elevators and hall requests.
 //!
 //! ### Overview of Functions:
@@ -28,6 +29,7 @@
 //! - [`get_wv`] – Retrieves the latest local worldview.
 //! - [`update_wv`] – Updates worldview asynchronously if changes are detected.
 //! - [`is_master`] – Checks if the current elevator is the master.
+//! - [`unix_millis`] – Wall-clock timestamps shared on the network.
 //! - [`extract_elevator_container`] / [`extract_self_elevator_container`] – Retrieve elevator state from worldview.
 //! - [`get_index_to_container`] – Finds the index of an elevator container by ID.
 //!
@@ -35,12 +37,13 @@
 
 
 use crate::config;
-use crate::network;
 use crate::print;
 
 use bincode;
 use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::watch;
 
 
@@ -77,21 +80,26 @@ pub struct ElevatorContainer
     pub elevator_id: u8,
 
     /// The number of floors the elevator can access  
-    /// Default: [config::DEFAULT_NUM_FLOORS]
+    /// Default: `num_floors` given to [ElevatorContainer::new]
     pub num_floors: u8,
 
     /// Vector of hall requests not yet sent to master over TCP  
-    /// Default: full of \[false, false\], length [config::DEFAULT_NUM_FLOORS]
+    /// Default: full of \[false, false\], length `num_floors`
     pub unsent_hall_request: Vec<[bool; 2]>,
 
     /// Vector of cab_requests.  
-    /// Default: full of false, length [config::DEFAULT_NUM_FLOORS]
+    /// Default: full of false, length `num_floors`
     pub cab_requests: Vec<bool>,
 
     /// Vector of hall_requests given to this elevator from the manager.  
-    /// Default: full of \[false, false\], length [config::DEFAULT_NUM_FLOORS]
+    /// Default: full of \[false, false\], length `num_floors`
     pub tasks: Vec<[bool; 2]>, 
 
+    /// Vector of tasks the elevator has served, which the master has not yet cleared from the hall requests.  
+    /// A served task is forgotten when the master no longer assigns it to the elevator.  
+    /// Default: full of \[false, false\], length `num_floors`
+    pub served_hall_request: Vec<[bool; 2]>, 
+
     /// [Dirn]  
     ///  Default: [Dirn::Stop]
     pub dirn: Dirn, 
@@ -114,27 +122,103 @@ pub struct ElevatorContainer
     /// The last detected floor sensor position.  
     /// Default: 255
     pub last_floor_sensor: u8,
+
+    /// Indicates whether the node has a connection to the elevator hardware.  
+    /// The manager does not assign hall requests to elevators without hardware.  
+    /// Default: true
+    pub hardware_available: bool,
+
+    /// Which floors the elevator serves, indexed on floor.  
+    /// The manager never assigns hall requests on floors the elevator does not serve.  
+    /// Default: from `served_floors` given to [ElevatorContainer::new], length `num_floors`
+    pub served_floors: Vec<bool>,
 }
 
 impl Default for ElevatorContainer 
 {
+    /// Creates an idle elevator with the number of floors and served floors in [config::NUM_FLOORS] and [config::SERVED_FLOORS].
+    ///
+    /// These are shared by every node in the process, so this is only meant for tests and single-node use.
+    /// A node creates its elevator with [ElevatorContainer::new] from its [NodeConfig](crate::node::NodeConfig).
     fn default() -> Self 
+    {
+        Self::new(*config::NUM_FLOORS.lock().unwrap(), config::SERVED_FLOORS.lock().unwrap().as_deref())
+    }
+}
+
+impl ElevatorContainer 
+{
+    /// Creates an idle elevator with `num_floors` floors, serving `served_floors`, or all floors if `None`
+    pub fn new(
+        num_floors: u8, 
+        served_floors: Option<&[u8]>
+    ) -> Self 
     {
         Self 
         {
             elevator_id: config::ERROR_ID,
-            num_floors: config::DEFAULT_NUM_FLOORS,
-            unsent_hall_request: vec![[false; 2]; config::DEFAULT_NUM_FLOORS as usize],
-            cab_requests: vec![false; config::DEFAULT_NUM_FLOORS as usize],
-            tasks: vec![[false, false]; config::DEFAULT_NUM_FLOORS as usize],
+            num_floors,
+            unsent_hall_request: vec![[false; 2]; num_floors as usize],
+            cab_requests: vec![false; num_floors as usize],
+            tasks: vec![[false, false]; num_floors as usize],
+            served_hall_request: vec![[false, false]; num_floors as usize],
             dirn: Dirn::Stop,
             behaviour: ElevatorBehaviour::Idle,
             last_behaviour: ElevatorBehaviour::Idle,
             obstruction: false,
             stop: false,
             last_floor_sensor: 255, 
+            hardware_available: true,
+            served_floors: match served_floors 
+            {
+                Some(floors) => (0..num_floors).map(|f| floors.contains(&f)).collect(),
+                None => vec![true; num_floors as usize],
+            },
         }
     }
+
+    /// Returns whether the elevator serves `floor`
+    ///
+    /// # Examples
+    /// ```
+    /// use elevatorpro::world_view::ElevatorContainer;
+    ///
+    /// let mut elev = ElevatorContainer::default();
+    /// elev.num_floors = 4;
+    /// elev.served_floors = vec![false, true, true, true];
+    ///
+    /// assert!(!elev.serves_floor(0));
+    /// assert!(elev.serves_floor(3));
+    /// assert!(!elev.serves_floor(4));
+    /// ```
+    pub fn serves_floor(&self, 
+        floor: usize
+    ) -> bool 
+    {
+        floor < self.num_floors as usize && self.served_floors.get(floor).copied().unwrap_or(false)
+    }
+
+    /// Returns whether the manager can give hall requests to the elevator  
+    /// This is not the case if the elevator has lost its hardware or is in an error state
+    pub fn can_take_hall_requests(&self) -> bool 
+    {
+        self.hardware_available 
+            && self.behaviour != ElevatorBehaviour::TravelError 
+            && self.behaviour != ElevatorBehaviour::ObstructionError 
+            && self.behaviour != ElevatorBehaviour::CosmicError
+    }
+}
+
+
+/// The elevator assigned to a hall request, and its estimated time of arrival
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct HallCallEta 
+{
+    /// The ID of the assigned elevator
+    pub elevator_id: u8,
+
+    /// When the elevator is expected to serve the request, in milliseconds since the Unix epoch
+    pub arrival_ms: u64,
 }
 
 
@@ -158,28 +242,103 @@ pub struct WorldView
     
     /// A HashMap backing up cab_call statuses for all elevators, mapping them to their IDs
     pub cab_requests_backup: HashMap<u8, Vec<bool>>,
+
+    /// Indicates that the manager's hall assigner failed, and the hall requests are given out by a fallback.  
+    /// Default: false
+    pub assignment_degraded: bool,
+
+    /// The assigned elevator and estimated time of arrival for each hall request, `[up, down]` per floor, set by the manager.  
+    /// None for hall requests which are not assigned.  
+    /// Default: full of \[None, None\], length `num_floors` given to [WorldView::new]
+    pub hall_request_eta: Vec<[Option<HallCallEta>; 2]>,
+
+    /// When each hall request was first registered by the master, in milliseconds since the Unix epoch, `[up, down]` per floor.  
+    /// None for hall requests which are not active. Kept up to date with [WorldView::update_hall_request_since].  
+    /// Default: full of \[None, None\], length `num_floors` given to [WorldView::new]
+    pub hall_request_since: Vec<[Option<u64>; 2]>,
+
+    /// The number of tasks each elevator has timed out on, mapping them to their IDs, see [crate::manager::task_timeout]  
+    /// Default: empty
+    pub task_timeout_warnings: HashMap<u8, u32>,
+
+    /// The master term. Increased every time a node takes over as master, see [WorldView::take_over].  
+    /// Default: 0
+    pub term: u64,
+
+    /// The sequence number of the master's broadcast in the current term, set by the UDP broadcaster.  
+    /// Default: 0
+    pub seq: u64,
 }
 
 
 impl Default for WorldView 
 {
-    /// Creates a default `WorldView` instance with no elevators and an invalid master ID.
+    /// Creates a default `WorldView` instance with no elevators, an invalid master ID and [config::NUM_FLOORS] floors.
+    ///
+    /// The number of floors is shared by every node in the process, so this is only meant for tests and single-node use.
+    /// A node creates its worldview with [WorldView::new] from its [NodeConfig](crate::node::NodeConfig).
     fn default() -> Self 
     {
+        Self::new(*config::NUM_FLOORS.lock().unwrap())
+    }
+}
+
+
+impl WorldView 
+{
+    /// Creates a `WorldView` with `num_floors` floors, no elevators and an invalid master ID.
+    pub fn new(
+        num_floors: u8
+    ) -> Self 
+    {
+        let num_floors = num_floors as usize;
         Self 
         {
             n: 0,
             master_id: config::ERROR_ID,
-            hall_request: vec![[false; 2]; config::DEFAULT_NUM_FLOORS as usize],
+            hall_request: vec![[false; 2]; num_floors],
             elevator_containers: Vec::new(),
             cab_requests_backup: HashMap::new(),
+            assignment_degraded: false,
+            hall_request_eta: vec![[None; 2]; num_floors],
+            hall_request_since: vec![[None; 2]; num_floors],
+            task_timeout_warnings: HashMap::new(),
+            term: 0,
+            seq: 0,
         }
     }
-}
 
+    /// Creates a `WorldView` with `num_floors` floors and an idle elevator for each `(id, floor)` in `elevators`.
+    ///
+    /// Meant as a fixture for tests and examples.
+    ///
+    /// # Example
+    /// ```
+    /// use elevatorpro::world_view::WorldView;
+    ///
+    /// let wv = WorldView::with_elevators(4, &[(1, 0), (2, 3)]);
+    /// assert_eq!(wv.get_num_elev(), 2);
+    /// assert_eq!(wv.elevator_containers[1].elevator_id, 2);
+    /// assert_eq!(wv.elevator_containers[1].last_floor_sensor, 3);
+    /// ```
+    pub fn with_elevators(
+        num_floors: u8, 
+        elevators: &[(u8, u8)]
+    ) -> Self 
+    {
+        let mut wv = WorldView::new(num_floors);
+        for (elevator_id, floor) in elevators 
+        {
+            wv.add_elev(ElevatorContainer 
+            { 
+                elevator_id: *elevator_id, 
+                last_floor_sensor: *floor, 
+                ..ElevatorContainer::new(num_floors, None) 
+            });
+        }
+        wv
+    }
 
-impl WorldView 
-{
     /// Adds an elevator to the system.
     ///
     /// Updates the number of elevators (`n`) accordingly.
@@ -225,6 +384,74 @@ impl WorldView
     }
 
 
+    /// Registers when each hall request was first seen
+    ///
+    /// New hall requests get the time `now_ms`, active hall requests keep their time, and inactive hall requests lose it.
+    ///
+    /// ## Parameters
+    /// - `now_ms`: The current time, from [unix_millis]
+    ///
+    /// ## Example
+    /// ```
+    /// use elevatorpro::world_view::WorldView;
+    ///
+    /// let mut wv = WorldView::default();
+    /// wv.hall_request[2] = [true, false];
+    /// wv.update_hall_request_since(1000);
+    /// wv.update_hall_request_since(2000);
+    /// assert_eq!(wv.hall_request_since[2], [Some(1000), None]);
+    ///
+    /// wv.hall_request[2] = [false, false];
+    /// wv.update_hall_request_since(3000);
+    /// assert_eq!(wv.hall_request_since[2], [None, None]);
+    /// ```
+    pub fn update_hall_request_since(&mut self, 
+        now_ms: u64
+    ) 
+    {
+        self.hall_request_since.resize(self.hall_request.len(), [None; 2]);
+        for (since, calls) in self.hall_request_since.iter_mut().zip(self.hall_request.iter()) 
+        {
+            for call in 0..2 
+            {
+                since[call] = calls[call].then(|| since[call].unwrap_or(now_ms));
+            }
+        }
+    }
+
+    /// Makes `master_id` the master in a new term
+    ///
+    /// The term is increased by one, and the sequence number starts over.
+    /// Broadcasts from the previous terms are rejected from then on, see [accept_broadcast].
+    ///
+    /// ## Parameters
+    /// - `master_id`: The ID of the new master
+    pub fn take_over(&mut self, 
+        master_id: u8
+    ) 
+    {
+        self.master_id = master_id;
+        self.term += 1;
+        self.seq = 0;
+    }
+
+    /// Checks if this worldview is a newer broadcast than `other`
+    ///
+    /// A higher term is newer. In the same term, a higher sequence number from the same master is newer.
+    /// If two masters share a term, the one with the lowest ID wins, as in the master election.
+    pub fn supersedes(&self, 
+        other: &WorldView
+    ) -> bool 
+    {
+        match self.term.cmp(&other.term) 
+        {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal if self.master_id == other.master_id => self.seq > other.seq,
+            Ordering::Equal => self.master_id < other.master_id,
+        }
+    }
+
     /// Sets the number of elevators manually.
     ///
     /// **Note:** This does not affect the `elevator_containers` list. 
@@ -367,16 +594,90 @@ pub async fn update_wv(
 }
 
 
+/// Returns the current wall-clock time in milliseconds since the Unix epoch
+///
+/// Used for timestamps shared on the network, like [WorldView::hall_request_since]
+pub fn unix_millis() -> u64 
+{
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+
+/// Decides if a worldview broadcast over UDP should be passed on to the local worldview
+///
+/// ## Parameters
+/// - `current`: The newest worldview this node knows of
+/// - `read`: The worldview received
+/// - `self_id`: The ID of this node
+///
+/// ## Returns
+/// - `false` for this node's own broadcasts, and broadcasts which are stale: from an older term, or an older
+///   sequence number from the same master, see [WorldView::supersedes].
+/// - `true` for newer broadcasts.
+/// - If this node is master and has a lower ID than the sender, `true` for broadcasts from the current term or newer.
+///   This node then takes over the sender's network in a new term, instead of joining it.
+///
+/// ## Example
+/// ```
+/// use elevatorpro::world_view::{self, WorldView};
+///
+/// let mut current = WorldView::default();
+/// current.take_over(20);
+/// current.seq = 5;
+///
+/// // A late broadcast from the same master is stale
+/// let mut read = current.clone();
+/// read.seq = 4;
+/// assert!(!world_view::accept_broadcast(&current, &read, 30));
+/// read.seq = 6;
+/// assert!(world_view::accept_broadcast(&current, &read, 30));
+///
+/// // An old master is rejected, even with a lower ID, until it takes over in a new term
+/// let mut old_master = WorldView::default();
+/// old_master.master_id = 10;
+/// old_master.seq = 100;
+/// assert!(!world_view::accept_broadcast(&current, &old_master, 30));
+/// old_master.term = current.term;
+/// old_master.take_over(10);
+/// assert!(world_view::accept_broadcast(&current, &old_master, 30));
+///
+/// // Node 10 is master in an older term. It outranks node 20, so it takes over node 20's network.
+/// let mut node_10 = WorldView::default();
+/// node_10.master_id = 10;
+/// assert!(world_view::accept_broadcast(&node_10, &current, 10));
+/// ```
+pub fn accept_broadcast(
+    current: &WorldView, 
+    read: &WorldView, 
+    self_id: u8
+) -> bool 
+{
+    if read.master_id == self_id {return false}
+
+    if current.master_id == self_id && self_id < read.master_id 
+    {
+        return read.term >= current.term;
+    }
+    read.supersedes(current)
+}
+
+
 /// Checks if the current system is the master based on the latest worldview data.
 ///
-/// This function compares the system's `SELF_ID` with the value at `MASTER_IDX` in the provided worldview (`wv`).
+/// This function compares the node's ID, `self_id`, with the master ID in the provided worldview (`wv`).
 ///
 /// ## Returns
-/// - `true` if the current system's `SELF_ID` matches the value at `MASTER_IDX` in the worldview.
+/// - `true` if `self_id` matches the master ID in the worldview.
 /// - `false` otherwise.
-pub fn is_master(wv: &WorldView) -> bool 
+pub fn is_master(
+    wv: &WorldView,
+    self_id: u8
+) -> bool 
 {
-    return network::read_self_id() == wv.master_id;
+    return self_id == wv.master_id;
 }
 
 
@@ -403,25 +704,22 @@ pub fn extract_elevator_container(
     wv.elevator_containers.iter().find(|elevator| elevator.elevator_id == id)
 }
 
-/// Retrieves a clone of the `ElevatorContainer` with `SELF_ID` from the latest worldview.
-///
-/// This function calls `extract_elevator_container` with `SELF_ID` to fetch the elevator container that matches the
-/// current `SELF_ID` from the provided worldview (`wv`). The `SELF_ID` is a static identifier loaded from memory,
-/// which represents the current elevator's unique identifier.
+/// Retrieves the `ElevatorContainer` of this node from the latest worldview.
 ///
 /// ## Parameters
-/// - `wv`: The latest worldview in serialized state.
+/// - `wv`: The latest worldview.
+/// - `self_id`: The node's ID, see [crate::node::NodeContext::id].
 ///
 /// ## Returns
-/// - A clone of the `ElevatorContainer` associated with `SELF_ID`.
+/// - The `ElevatorContainer` with `self_id`, if it is in the worldview.
 ///
 /// **Note:** This function internally calls `extract_elevator_container` to retrieve the correct elevator container.
 pub fn extract_self_elevator_container(
-    wv: &WorldView
+    wv: &WorldView,
+    self_id: u8
 ) -> Option<&ElevatorContainer> 
 {
-    let id = network::read_self_id();
-    extract_elevator_container(wv, id)
+    extract_elevator_container(wv, self_id)
 }
 
 